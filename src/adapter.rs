@@ -0,0 +1,409 @@
+//! Picks the best adapter for a set of requirements, and creates a device on it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::LfLimitsExt;
+
+const ADAPTER_PREFERENCE_KEY: &str = "adapter_preference";
+
+/// Describes which adapters are acceptable, and which are preferred, when requesting an adapter with
+/// [`crate::LfInstanceExt::request_powerful_adapter`] or a device with [`crate::LfInstanceExt::request_device_for`].
+#[derive(Debug, Clone)]
+pub struct AdapterQuery<'a> {
+    /// If given, only adapters which can present to this surface are considered.
+    pub compatible_surface: Option<&'a wgpu::Surface<'a>>,
+    /// The backends to look for adapters on.
+    pub backends: wgpu::Backends,
+    /// Which type of adapter is preferred. With [`wgpu::PowerPreference::None`], adapters are tried in the order
+    /// they are found.
+    pub power_preference: wgpu::PowerPreference,
+    /// If given, only adapters of these types are considered.
+    pub device_types: Option<&'a [wgpu::DeviceType]>,
+    /// If given, only adapters whose name contains this, ignoring case, are considered.
+    pub name_contains: Option<&'a str>,
+}
+
+impl Default for AdapterQuery<'_> {
+    fn default() -> Self {
+        Self {
+            compatible_surface: None,
+            backends: wgpu::Backends::all(),
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            device_types: None,
+            name_contains: None,
+        }
+    }
+}
+
+/// A user's choice of adapter, such as "always use this GPU", which can be saved to [`crate::local_storage`] and
+/// turned into an [`AdapterQuery`] on the next run. [`crate::Game`]s use the stored preference when one exists,
+/// falling back to the usual adapter if no adapter matches it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdapterPreference {
+    /// If given, only adapters whose name contains this, ignoring case, are used.
+    pub name_contains: Option<String>,
+    /// If given, only adapters of this type are used.
+    #[serde(with = "optional_device_type")]
+    pub device_type: Option<wgpu::DeviceType>,
+    /// If given, only adapters on this backend are used.
+    #[serde(with = "optional_backend")]
+    pub backend: Option<wgpu::Backend>,
+    /// Whether integrated GPUs are preferred over discrete GPUs.
+    pub prefer_low_power: bool,
+}
+
+impl AdapterPreference {
+    /// A preference for exactly the given adapter, matched by name, type and backend.
+    pub fn for_adapter(info: &wgpu::AdapterInfo) -> Self {
+        Self {
+            name_contains: Some(info.name.clone()),
+            device_type: Some(info.device_type),
+            backend: Some(info.backend),
+            prefer_low_power: false,
+        }
+    }
+
+    /// Gets the preference saved with [`AdapterPreference::store`], if there is one.
+    pub fn load() -> Option<Self> {
+        let stored = crate::local_storage::load(ADAPTER_PREFERENCE_KEY)?;
+        match serde_json::from_str(&stored) {
+            Ok(preference) => Some(preference),
+            Err(err) => {
+                log::error!("failed to read stored adapter preference: {err}");
+                None
+            }
+        }
+    }
+
+    /// Saves this preference, to be used the next time a game starts.
+    pub fn store(&self) -> Result<(), crate::local_storage::StoreError> {
+        let serialized =
+            serde_json::to_string(self).expect("adapter preference is always serializable");
+        crate::local_storage::store(ADAPTER_PREFERENCE_KEY, &serialized)
+    }
+
+    /// A query for adapters matching this preference.
+    pub fn query<'a>(
+        &'a self,
+        compatible_surface: Option<&'a wgpu::Surface<'a>>,
+    ) -> AdapterQuery<'a> {
+        AdapterQuery {
+            compatible_surface,
+            backends: self
+                .backend
+                .map(wgpu::Backends::from)
+                .unwrap_or(wgpu::Backends::all()),
+            power_preference: if self.prefer_low_power {
+                wgpu::PowerPreference::LowPower
+            } else {
+                wgpu::PowerPreference::HighPerformance
+            },
+            device_types: self.device_type.as_ref().map(std::slice::from_ref),
+            name_contains: self.name_contains.as_deref(),
+        }
+    }
+}
+
+/// wgpu's types only implement serde traits with its `serde` feature, which isn't exposed by the `wgpu` crate, so we
+/// mirror them here.
+macro_rules! optional_remote {
+    ($module:ident, $remote:ty, $remote_path:literal { $($variant:ident),* $(,)? }) => {
+        mod $module {
+            use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+            #[derive(Serialize, Deserialize)]
+            #[serde(remote = $remote_path)]
+            enum Def {
+                $($variant),*
+            }
+
+            #[derive(Serialize, Deserialize)]
+            struct Wrapper(#[serde(with = "Def")] $remote);
+
+            pub(super) fn serialize<S: Serializer>(
+                value: &Option<$remote>,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                value.map(Wrapper).serialize(serializer)
+            }
+
+            pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Option<$remote>, D::Error> {
+                Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(value)| value))
+            }
+        }
+    };
+}
+
+optional_remote!(optional_device_type, wgpu::DeviceType, "wgpu::DeviceType" {
+    Other,
+    IntegratedGpu,
+    DiscreteGpu,
+    VirtualGpu,
+    Cpu,
+});
+
+optional_remote!(optional_backend, wgpu::Backend, "wgpu::Backend" {
+    Empty,
+    Vulkan,
+    Metal,
+    Dx12,
+    Gl,
+    BrowserWebGpu,
+});
+
+/// Why an adapter wasn't used to create a device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdapterRejectionReason {
+    IncompatibleSurface,
+    DeviceType(wgpu::DeviceType),
+    Name,
+    MissingFeatures(wgpu::Features),
+}
+
+impl std::fmt::Display for AdapterRejectionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AdapterRejectionReason::IncompatibleSurface => {
+                write!(f, "can't present to the surface")
+            }
+            AdapterRejectionReason::DeviceType(ty) => write!(f, "device type {ty:?} not allowed"),
+            AdapterRejectionReason::Name => write!(f, "name not allowed"),
+            AdapterRejectionReason::MissingFeatures(features) => {
+                write!(f, "missing features {features:?}")
+            }
+        }
+    }
+}
+
+/// An adapter which was found but not used, given by [`DeviceRequestError::NoneQualified`].
+#[derive(Debug, Clone)]
+pub struct AdapterRejection {
+    pub info: wgpu::AdapterInfo,
+    pub reason: AdapterRejectionReason,
+}
+
+/// The error given by [`crate::LfInstanceExt::request_device_for`].
+#[derive(Debug, thiserror::Error)]
+pub enum DeviceRequestError {
+    #[error("no adapters were found")]
+    NoAdapters,
+    #[error("no adapter met the requirements: {}", describe_rejections(.rejected))]
+    NoneQualified { rejected: Vec<AdapterRejection> },
+    #[error("failed to create device on {}: {source}", .info.name)]
+    RequestDevice {
+        info: wgpu::AdapterInfo,
+        #[source]
+        source: wgpu::RequestDeviceError,
+    },
+}
+
+fn describe_rejections(rejected: &[AdapterRejection]) -> String {
+    rejected
+        .iter()
+        .map(|rejection| format!("{} ({})", rejection.info.name, rejection.reason))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Lower is preferred.
+#[cfg(not(target_arch = "wasm32"))]
+fn device_type_rank(
+    device_type: wgpu::DeviceType,
+    power_preference: wgpu::PowerPreference,
+) -> usize {
+    let order: &[wgpu::DeviceType] = match power_preference {
+        wgpu::PowerPreference::None => return 0,
+        wgpu::PowerPreference::HighPerformance => &[
+            wgpu::DeviceType::DiscreteGpu,
+            wgpu::DeviceType::IntegratedGpu,
+            wgpu::DeviceType::VirtualGpu,
+            wgpu::DeviceType::Other,
+            wgpu::DeviceType::Cpu,
+        ],
+        wgpu::PowerPreference::LowPower => &[
+            wgpu::DeviceType::IntegratedGpu,
+            wgpu::DeviceType::DiscreteGpu,
+            wgpu::DeviceType::VirtualGpu,
+            wgpu::DeviceType::Other,
+            wgpu::DeviceType::Cpu,
+        ],
+    };
+    order
+        .iter()
+        .position(|ty| *ty == device_type)
+        .unwrap_or(order.len())
+}
+
+fn check_adapter(
+    adapter: &wgpu::Adapter,
+    query: &AdapterQuery<'_>,
+    required_features: wgpu::Features,
+) -> Result<(), AdapterRejectionReason> {
+    if let Some(surface) = query.compatible_surface {
+        if !adapter.is_surface_supported(surface) {
+            return Err(AdapterRejectionReason::IncompatibleSurface);
+        }
+    }
+
+    let device_type = adapter.get_info().device_type;
+    if let Some(device_types) = query.device_types {
+        if !device_types.contains(&device_type) {
+            return Err(AdapterRejectionReason::DeviceType(device_type));
+        }
+    }
+
+    if let Some(name_contains) = query.name_contains {
+        let name = adapter.get_info().name.to_lowercase();
+        if !name.contains(&name_contains.to_lowercase()) {
+            return Err(AdapterRejectionReason::Name);
+        }
+    }
+
+    let missing = required_features - adapter.features();
+    if !missing.is_empty() {
+        return Err(AdapterRejectionReason::MissingFeatures(missing));
+    }
+
+    Ok(())
+}
+
+async fn candidate_adapters(
+    instance: &wgpu::Instance,
+    query: &AdapterQuery<'_>,
+) -> Vec<wgpu::Adapter> {
+    // Browsers only ever give a single adapter, picked using the power preference
+    #[cfg(target_arch = "wasm32")]
+    {
+        return instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: query.power_preference,
+                force_fallback_adapter: false,
+                compatible_surface: query.compatible_surface,
+            })
+            .await
+            .into_iter()
+            .collect();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let mut adapters = instance.enumerate_adapters(query.backends);
+        // Stable, so adapters of the same type keep the order they were found in
+        adapters.sort_by_key(|adapter| {
+            device_type_rank(adapter.get_info().device_type, query.power_preference)
+        });
+        adapters
+    }
+}
+
+/// Splits the candidate adapters into those which qualify, best first, and those which don't.
+async fn rank_adapters(
+    instance: &wgpu::Instance,
+    query: &AdapterQuery<'_>,
+    required_features: wgpu::Features,
+) -> (Vec<wgpu::Adapter>, Vec<AdapterRejection>) {
+    let mut qualified = Vec::new();
+    let mut rejected = Vec::new();
+    for adapter in candidate_adapters(instance, query).await {
+        match check_adapter(&adapter, query, required_features) {
+            Ok(()) => qualified.push(adapter),
+            Err(reason) => rejected.push(AdapterRejection {
+                info: adapter.get_info(),
+                reason,
+            }),
+        }
+    }
+    (qualified, rejected)
+}
+
+pub(crate) async fn request_powerful_adapter(
+    instance: &wgpu::Instance,
+    query: &AdapterQuery<'_>,
+) -> Option<wgpu::Adapter> {
+    let (qualified, _) = rank_adapters(instance, query, wgpu::Features::empty()).await;
+    qualified.into_iter().next()
+}
+
+pub(crate) async fn request_device_for(
+    instance: &wgpu::Instance,
+    query: &AdapterQuery<'_>,
+    required_features: wgpu::Features,
+    target_limits: &wgpu::Limits,
+) -> Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue), DeviceRequestError> {
+    let (qualified, rejected) = rank_adapters(instance, query, required_features).await;
+    let Some(adapter) = qualified.into_iter().next() else {
+        if rejected.is_empty() {
+            return Err(DeviceRequestError::NoAdapters);
+        }
+        return Err(DeviceRequestError::NoneQualified { rejected });
+    };
+
+    // As with `Game::target_limits`, limits are a target rather than a requirement
+    let required_limits = adapter.limits().intersection(target_limits);
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                required_features,
+                required_limits,
+            },
+            None,
+        )
+        .await
+        .map_err(|source| DeviceRequestError::RequestDevice {
+            info: adapter.get_info(),
+            source,
+        })?;
+
+    Ok((adapter, device, queue))
+}
+
+/// A readable description of every graphics adapter available, with its backend, driver and a few important
+/// limits, for showing to users when something goes wrong. On web, browsers don't allow adapters to be listed,
+/// so advice on enabling graphics acceleration is given instead.
+pub fn adapter_report() -> String {
+    #[cfg(target_arch = "wasm32")]
+    {
+        return "Graphics adapters can't be listed in the browser. Make sure your browser supports WebGPU or \
+                WebGL2, and that hardware acceleration is enabled in its settings."
+            .to_owned();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use std::fmt::Write;
+
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::all()),
+            ..Default::default()
+        });
+        let adapters = instance.enumerate_adapters(wgpu::Backends::all());
+        if adapters.is_empty() {
+            return "No graphics adapters were found. Try updating your graphics drivers."
+                .to_owned();
+        }
+
+        let mut report = String::from("Graphics adapters found:");
+        for adapter in adapters {
+            let info = adapter.get_info();
+            let limits = adapter.limits();
+            let _ = write!(
+                report,
+                "\n- {} ({:?}, {:?}), driver {} {}\n  max texture size {}, max bind groups {}, max storage \
+                 buffers per stage {}",
+                info.name,
+                info.backend,
+                info.device_type,
+                info.driver,
+                info.driver_info,
+                limits.max_texture_dimension_2d,
+                limits.max_bind_groups,
+                limits.max_storage_buffers_per_shader_stage,
+            );
+        }
+        report.push_str("\n\nUpdating your graphics drivers may fix this.");
+        report
+    }
+}
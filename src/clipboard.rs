@@ -0,0 +1,209 @@
+//! Reads images from the system clipboard, either with arboard on native, or the asynchronous clipboard API
+//! on web. Also listens for the user pasting, which is given by the browser as a `paste` event on web, and is
+//! read from the clipboard when Ctrl+V (Cmd+V on macOS) is pressed on native.
+
+/// An image read from the clipboard, as tightly packed 8-bit RGBA pixels in rows from top to bottom.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// Something the user pasted into the game, given by [`crate::Game::clipboard_pasted`]. A single paste can give
+/// several of these, such as some text along with the files it describes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasteData {
+    Text(String),
+    /// A decoded image. Given on native, where the clipboard holds images as pixels.
+    Image(ClipboardImage),
+    /// A file, such as an image copied in a browser, with its contents still encoded. Given on web.
+    File {
+        name: String,
+        mime_type: String,
+        bytes: Vec<u8>,
+    },
+}
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "clipboard"))]
+fn get_image_native() -> Option<ClipboardImage> {
+    let mut clipboard = match arboard::Clipboard::new() {
+        Ok(clipboard) => clipboard,
+        Err(err) => {
+            log::error!("failed to open clipboard: {err}");
+            return None;
+        }
+    };
+
+    // An error is also given if the clipboard doesn't hold an image
+    let image = clipboard.get_image().ok()?;
+    Some(ClipboardImage {
+        width: image.width as u32,
+        height: image.height as u32,
+        rgba: image.bytes.into_owned(),
+    })
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn get_image_web() -> Option<ClipboardImage> {
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_futures::JsFuture;
+
+    fn method(object: &wasm_bindgen::JsValue, name: &str) -> Option<js_sys::Function> {
+        js_sys::Reflect::get(object, &name.into())
+            .ok()?
+            .dyn_into()
+            .ok()
+    }
+
+    async fn await_promise(value: wasm_bindgen::JsValue) -> Option<wasm_bindgen::JsValue> {
+        let promise = value.dyn_into::<js_sys::Promise>().ok()?;
+        JsFuture::from(promise).await.ok()
+    }
+
+    let window = web_sys::window()?;
+
+    // The asynchronous clipboard API is behind `web_sys_unstable_apis`, so is accessed dynamically. The promise
+    // is rejected if the user denies permission.
+    let clipboard = js_sys::Reflect::get(&window.navigator(), &"clipboard".into()).ok()?;
+    let items = await_promise(method(&clipboard, "read")?.call0(&clipboard).ok()?).await?;
+
+    for item in js_sys::Array::from(&items).iter() {
+        let types = js_sys::Reflect::get(&item, &"types".into()).ok()?;
+        let Some(image_type) = js_sys::Array::from(&types)
+            .iter()
+            .find(|ty| ty.as_string().is_some_and(|ty| ty.starts_with("image/")))
+        else {
+            continue;
+        };
+
+        let blob = await_promise(method(&item, "getType")?.call1(&item, &image_type).ok()?)
+            .await?
+            .dyn_into::<web_sys::Blob>()
+            .ok()?;
+        let bitmap = JsFuture::from(window.create_image_bitmap_with_blob(&blob).ok()?)
+            .await
+            .ok()?
+            .dyn_into::<web_sys::ImageBitmap>()
+            .ok()?;
+
+        // Decoding is done by the browser, so we draw to a canvas to get the pixels back
+        let (width, height) = (bitmap.width(), bitmap.height());
+        let canvas = window
+            .document()?
+            .create_element("canvas")
+            .ok()?
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .ok()?;
+        canvas.set_width(width);
+        canvas.set_height(height);
+        let context = canvas
+            .get_context("2d")
+            .ok()??
+            .dyn_into::<web_sys::CanvasRenderingContext2d>()
+            .ok()?;
+        context
+            .draw_image_with_image_bitmap(&bitmap, 0.0, 0.0)
+            .ok()?;
+        let data = context
+            .get_image_data(0.0, 0.0, width as f64, height as f64)
+            .ok()?;
+
+        return Some(ClipboardImage {
+            width,
+            height,
+            rgba: data.data().0,
+        });
+    }
+
+    None
+}
+
+/// Reads an image from the clipboard, giving `None` if the clipboard doesn't hold an image, can't be read, or the
+/// user denies permission to read it. Always gives `None` on native without the `clipboard` feature.
+pub(crate) async fn get_image() -> Option<ClipboardImage> {
+    #[cfg(target_arch = "wasm32")]
+    return get_image_web().await;
+
+    #[cfg(all(not(target_arch = "wasm32"), feature = "clipboard"))]
+    return get_image_native();
+
+    #[cfg(all(not(target_arch = "wasm32"), not(feature = "clipboard")))]
+    return None;
+}
+
+/// Reads everything that would be pasted from the clipboard.
+#[cfg(all(not(target_arch = "wasm32"), feature = "clipboard"))]
+pub(crate) fn read_paste() -> Vec<PasteData> {
+    let mut pasted = Vec::new();
+
+    // Errors are also given if the clipboard doesn't hold text
+    match arboard::Clipboard::new() {
+        Ok(mut clipboard) => {
+            if let Ok(text) = clipboard.get_text() {
+                pasted.push(PasteData::Text(text));
+            }
+        }
+        Err(err) => log::error!("failed to open clipboard: {err}"),
+    }
+    if let Some(image) = get_image_native() {
+        pasted.push(PasteData::Image(image));
+    }
+
+    pasted
+}
+
+/// Listens for `paste` events on the page, giving everything pasted through the returned receiver and waking the
+/// event loop.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn listen_for_paste(
+    waker: winit::event_loop::EventLoopProxy<()>,
+) -> flume::Receiver<PasteData> {
+    use wasm_bindgen::{closure::Closure, JsCast};
+    use wasm_bindgen_futures::JsFuture;
+
+    let (sender, receiver) = flume::unbounded();
+
+    let send = move |pasted: PasteData| crate::wasm::send_to_loop(&sender, &waker, pasted);
+
+    let listener = Closure::<dyn FnMut(web_sys::Event)>::new(move |event: web_sys::Event| {
+        // `ClipboardEvent` is behind `web_sys_unstable_apis`, so its data is accessed dynamically
+        let Some(data) = js_sys::Reflect::get(&event, &"clipboardData".into())
+            .ok()
+            .and_then(|data| data.dyn_into::<web_sys::DataTransfer>().ok())
+        else {
+            return;
+        };
+
+        if let Ok(text) = data.get_data("text/plain") {
+            if !text.is_empty() {
+                send(PasteData::Text(text));
+            }
+        }
+
+        let Some(files) = data.files() else {
+            return;
+        };
+        for file in (0..files.length()).filter_map(|i| files.get(i)) {
+            let send = send.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let Ok(buffer) = JsFuture::from(file.array_buffer()).await else {
+                    log::error!("failed to read pasted file {}", file.name());
+                    return;
+                };
+                send(PasteData::File {
+                    name: file.name(),
+                    mime_type: file.type_(),
+                    bytes: js_sys::Uint8Array::new(&buffer).to_vec(),
+                });
+            });
+        }
+    });
+
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .expect("app requires DOM");
+    crate::wasm::add_listener(&document, "paste", listener);
+
+    receiver
+}
@@ -0,0 +1,49 @@
+//! A monotonic clock that works the same on native and web.
+
+use std::time::Duration;
+
+/// Measures time since it was created. `std::time::Instant` panics on web, so this uses `performance.now()` there
+/// and `Instant` on native. The game loop does all of its timing with the clock in [`crate::GameData::clock`],
+/// which starts just before the game is initialised, so it is also the clock to drive shader animations with.
+#[derive(Debug, Clone, Copy)]
+pub struct Clock {
+    start: web_time::Instant,
+}
+
+impl Clock {
+    /// Starts a new clock at zero.
+    pub fn new() -> Self {
+        Self {
+            start: web_time::Instant::now(),
+        }
+    }
+
+    /// The current instant, which is the same for every clock. Compare it with instants given earlier to time
+    /// things, or use [`Clock::elapsed`] for the time since a clock started.
+    pub fn now() -> web_time::Instant {
+        web_time::Instant::now()
+    }
+
+    /// The time since the clock started.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// The time since the clock started, in seconds.
+    pub fn seconds_f64(&self) -> f64 {
+        self.elapsed().as_secs_f64()
+    }
+
+    /// The time since the clock started, in seconds, for passing to shaders. After around four and a half hours,
+    /// `f32`s can no longer hold this to the millisecond, so for long-running animations, wrap
+    /// [`Clock::seconds_f64`] to the animation's period before converting instead.
+    pub fn seconds_f32(&self) -> f32 {
+        self.seconds_f64() as f32
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
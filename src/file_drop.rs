@@ -0,0 +1,115 @@
+//! Files dragged onto the game window. Winit gives the paths of dropped files on native, but not on web, where the
+//! canvas listens for the browser's drag and drop events and reads the dropped files' contents instead.
+
+use std::path::{Path, PathBuf};
+
+/// A file dropped onto the game window, given by [`crate::Game::file_dropped`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DroppedFile {
+    /// The path to a dropped file. Given on native.
+    Path(PathBuf),
+    /// The name and contents of a dropped file. Given on web, where pages can't see paths.
+    Contents {
+        name: String,
+        mime_type: String,
+        bytes: Vec<u8>,
+    },
+}
+
+impl DroppedFile {
+    /// The path of the file on native, or just its name on web. Either way, this has the file's extension.
+    pub fn path(&self) -> &Path {
+        match self {
+            DroppedFile::Path(path) => path,
+            DroppedFile::Contents { name, .. } => Path::new(name),
+        }
+    }
+
+    /// The MIME type the browser gave the file on web, such as `image/png`, which is empty for types the browser
+    /// doesn't know. `None` on native.
+    pub fn mime_type(&self) -> Option<&str> {
+        match self {
+            DroppedFile::Path(_) => None,
+            DroppedFile::Contents { mime_type, .. } => Some(mime_type),
+        }
+    }
+}
+
+/// Drag and drop events on the canvas, given by [`listen_for_drops`].
+#[cfg(target_arch = "wasm32")]
+pub(crate) enum DropEvent {
+    /// Files are being dragged over the canvas. Browsers hide the names of dragged files until they are dropped.
+    Hovered,
+    Left,
+    Dropped(DroppedFile),
+}
+
+/// Listens for files being dragged over and dropped onto the canvas, giving each event through the returned
+/// receiver and waking the event loop.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn listen_for_drops(
+    canvas: &web_sys::HtmlCanvasElement,
+    waker: winit::event_loop::EventLoopProxy<()>,
+) -> flume::Receiver<DropEvent> {
+    use wasm_bindgen::{closure::Closure, JsCast};
+    use wasm_bindgen_futures::JsFuture;
+
+    let (sender, receiver) = flume::unbounded();
+
+    let send = move |event: DropEvent| crate::wasm::send_to_loop(&sender, &waker, event);
+
+    let listen = |name: &'static str, listener: Closure<dyn FnMut(web_sys::Event)>| {
+        crate::wasm::add_listener(canvas, name, listener)
+    };
+
+    // Browsers only allow dropping onto elements which cancel `dragover`
+    let hover_send = send.clone();
+    listen(
+        "dragenter",
+        Closure::new(move |event: web_sys::Event| {
+            event.prevent_default();
+            hover_send(DropEvent::Hovered);
+        }),
+    );
+    listen(
+        "dragover",
+        Closure::new(|event: web_sys::Event| event.prevent_default()),
+    );
+    let leave_send = send.clone();
+    listen(
+        "dragleave",
+        Closure::new(move |_: web_sys::Event| leave_send(DropEvent::Left)),
+    );
+    listen(
+        "drop",
+        Closure::new(move |event: web_sys::Event| {
+            event.prevent_default();
+            send(DropEvent::Left);
+
+            // `DragEvent` isn't enabled in web-sys, so its data is accessed dynamically
+            let Some(files) = js_sys::Reflect::get(&event, &"dataTransfer".into())
+                .ok()
+                .and_then(|data| data.dyn_into::<web_sys::DataTransfer>().ok())
+                .and_then(|data| data.files())
+            else {
+                return;
+            };
+            for file in (0..files.length()).filter_map(|i| files.get(i)) {
+                let send = send.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let Ok(buffer) = JsFuture::from(file.array_buffer()).await else {
+                        log::error!("failed to read dropped file {}", file.name());
+                        return;
+                    };
+                    send(DropEvent::Dropped(DroppedFile::Contents {
+                        name: file.name(),
+                        mime_type: file.type_(),
+                        bytes: js_sys::Uint8Array::new(&buffer).to_vec(),
+                    }));
+                });
+            }
+        }),
+    );
+
+    receiver
+}
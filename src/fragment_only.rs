@@ -18,9 +18,14 @@ pub struct FragmentOnlyRenderPipelineDescriptor<'a> {
     pub multiview: Option<NonZeroU32>,
 }
 
+/// Whether the fullscreen triangle is given by a vertex buffer, rather than generated from the vertex index.
+/// Generating vertices needs no buffer at all, but some WebGL implementations misbehave when doing so, so we
+/// keep the buffer on the web.
+const USE_FULLSCREEN_VERTEX_BUFFER: bool = cfg!(target_arch = "wasm32");
+
 pub struct FragmentOnlyRenderPipeline {
     pipeline: wgpu::RenderPipeline,
-    vertex_buffer: wgpu::Buffer,
+    vertex_buffer: Option<wgpu::Buffer>,
 }
 
 impl AsRef<wgpu::RenderPipeline> for FragmentOnlyRenderPipeline {
@@ -31,41 +36,84 @@ impl AsRef<wgpu::RenderPipeline> for FragmentOnlyRenderPipeline {
 
 impl FragmentOnlyRenderPipeline {
     pub(crate) fn new(device: &wgpu::Device, desc: &FragmentOnlyRenderPipelineDescriptor) -> Self {
+        let pipeline = Self::create_pipeline(device, desc);
+        let vertex_buffer =
+            USE_FULLSCREEN_VERTEX_BUFFER.then(|| Self::create_vertex_buffer(device));
+
+        Self {
+            pipeline,
+            vertex_buffer,
+        }
+    }
+
+    /// Replaces this pipeline with one made from a new descriptor, such as after a shader has been edited or a
+    /// quality setting has changed, keeping the fullscreen triangle already made for this pipeline. Render
+    /// bundles recorded with the old pipeline keep using it, so must be recorded again.
+    ///
+    /// Bind groups stay usable with the new pipeline if their layouts are part of the same
+    /// [`wgpu::PipelineLayout`] given to both descriptors. Pipelines made with no layout derive their bind group
+    /// layouts from their shaders, and bind groups made from
+    /// [`FragmentOnlyRenderPipeline::get_bind_group_layout`] can only be used with the pipeline they came from, so
+    /// must be made again.
+    ///
+    /// To keep the old pipeline when an edited shader fails to compile, make the new shader module with
+    /// [`crate::LfDeviceExt::create_shader_module_checked`] first, and only recreate the pipeline once it succeeds.
+    pub fn recreate(&mut self, device: &wgpu::Device, desc: &FragmentOnlyRenderPipelineDescriptor) {
+        self.pipeline = Self::create_pipeline(device, desc);
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        desc: &FragmentOnlyRenderPipelineDescriptor,
+    ) -> wgpu::RenderPipeline {
+        let fullscreen_vertex_shader_source = if USE_FULLSCREEN_VERTEX_BUFFER {
+            include_str!("shaders/fullscreen.wgsl")
+        } else {
+            include_str!("shaders/fullscreen_vertexless.wgsl")
+        };
         let fullscreen_vertex_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("fullscreen triangle vertex shader"),
-            source: wgpu::ShaderSource::Wgsl(Cow::from(include_str!("shaders/fullscreen.wgsl"))),
+            source: wgpu::ShaderSource::Wgsl(Cow::from(fullscreen_vertex_shader_source)),
         });
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let vertex_attributes = [
+            wgpu::VertexAttribute {
+                offset: 0,
+                shader_location: 0,
+                format: wgpu::VertexFormat::Float32x4,
+            },
+            wgpu::VertexAttribute {
+                offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                shader_location: 1,
+                format: wgpu::VertexFormat::Float32x2,
+            },
+        ];
+        let mut vertex_buffers = Vec::new();
+        if USE_FULLSCREEN_VERTEX_BUFFER {
+            vertex_buffers.push(wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<FullscreenVertex>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &vertex_attributes,
+            });
+        }
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: desc.label.as_deref(),
             layout: desc.layout.clone(),
             vertex: wgpu::VertexState {
                 module: &fullscreen_vertex_shader,
                 entry_point: "main",
-                buffers: &[wgpu::VertexBufferLayout {
-                    array_stride: std::mem::size_of::<FullscreenVertex>() as wgpu::BufferAddress,
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &[
-                        wgpu::VertexAttribute {
-                            offset: 0,
-                            shader_location: 0,
-                            format: wgpu::VertexFormat::Float32x4,
-                        },
-                        wgpu::VertexAttribute {
-                            offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
-                            shader_location: 1,
-                            format: wgpu::VertexFormat::Float32x2,
-                        },
-                    ],
-                }],
+                buffers: &vertex_buffers,
             },
             primitive: wgpu::PrimitiveState::default(),
             depth_stencil: None,
             multisample: desc.multisample.clone(),
             fragment: Some(desc.fragment.clone()),
             multiview: desc.multiview.clone(),
-        });
+        })
+    }
 
+    fn create_vertex_buffer(device: &wgpu::Device) -> wgpu::Buffer {
         let fullscreen_vertices = [
             FullscreenVertex {
                 position: [-2.0, -1.0, 0.5, 1.0],
@@ -81,16 +129,11 @@ impl FragmentOnlyRenderPipeline {
             },
         ];
         let fullscreen_vertices_bytes: &[u8] = bytemuck::cast_slice(&fullscreen_vertices);
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("fullscreen triangle"),
             contents: fullscreen_vertices_bytes,
             usage: wgpu::BufferUsages::VERTEX,
-        });
-
-        Self {
-            pipeline,
-            vertex_buffer,
-        }
+        })
     }
 
     pub fn get_bind_group_layout(&self, index: u32) -> wgpu::BindGroupLayout {
@@ -103,7 +146,9 @@ fn set_pipeline<'a>(
     pipeline: &'a FragmentOnlyRenderPipeline,
 ) {
     encoder.set_pipeline(&pipeline.pipeline);
-    encoder.set_vertex_buffer(0, pipeline.vertex_buffer.slice(..));
+    if let Some(vertex_buffer) = &pipeline.vertex_buffer {
+        encoder.set_vertex_buffer(0, vertex_buffer.slice(..));
+    }
 }
 
 fn draw<'a>(encoder: &mut impl RenderEncoder<'a>) {
@@ -123,6 +168,8 @@ pub struct FragmentOnlyRenderBundleEncoderDescriptor<'a> {
 
 pub struct FragmentOnlyRenderBundleEncoder<'a> {
     encoder: wgpu::RenderBundleEncoder<'a>,
+    color_formats: Vec<Option<wgpu::TextureFormat>>,
+    sample_count: u32,
 }
 
 impl<'a> FragmentOnlyRenderBundleEncoder<'a> {
@@ -138,14 +185,22 @@ impl<'a> FragmentOnlyRenderBundleEncoder<'a> {
             multiview: desc.multiview,
         });
 
-        Self { encoder }
+        Self {
+            encoder,
+            color_formats: desc.color_formats.to_vec(),
+            sample_count: desc.sample_count,
+        }
     }
 
     // Mostly pass-through
     pub fn finish(self, desc: &wgpu::RenderBundleDescriptor<'_>) -> FragmentOnlyRenderBundle {
         let render_bundle = self.encoder.finish(desc);
 
-        FragmentOnlyRenderBundle { render_bundle }
+        FragmentOnlyRenderBundle {
+            render_bundle,
+            color_formats: self.color_formats,
+            sample_count: self.sample_count,
+        }
     }
     pub fn set_pipeline(&mut self, pipeline: &'a FragmentOnlyRenderPipeline) {
         set_pipeline(&mut self.encoder, pipeline)
@@ -158,8 +213,176 @@ impl<'a> FragmentOnlyRenderBundleEncoder<'a> {
     }
 }
 
+/// A set of fragment-only draws recorded once and replayed in any number of passes, saving the cost of
+/// re-encoding static fullscreen passes every frame.
+///
+/// A bundle can only be executed in a pass whose color attachments have the formats and sample count that
+/// the bundle was recorded with. wgpu reports a validation error when the pass is finished otherwise; use
+/// [`FragmentOnlyRenderBundle::check_compatible_with`] to check up front.
+///
+/// ```no_run
+/// use lf_gfx::{LfCommandEncoderExt, LfDeviceExt};
+///
+/// fn record(
+///     device: &wgpu::Device,
+///     pipeline: &lf_gfx::FragmentOnlyRenderPipeline,
+/// ) -> lf_gfx::FragmentOnlyRenderBundle {
+///     let mut encoder = device.create_fragment_only_render_bundle_encoder(
+///         &lf_gfx::FragmentOnlyRenderBundleEncoderDescriptor {
+///             label: Some("fullscreen pass"),
+///             color_formats: &[Some(wgpu::TextureFormat::Bgra8UnormSrgb)],
+///             sample_count: 1,
+///             multiview: None,
+///         },
+///     );
+///     encoder.set_pipeline(pipeline);
+///     encoder.draw();
+///     encoder.finish(&wgpu::RenderBundleDescriptor {
+///         label: Some("fullscreen pass"),
+///     })
+/// }
+///
+/// fn replay(
+///     device: &wgpu::Device,
+///     queue: &wgpu::Queue,
+///     bundle: &lf_gfx::FragmentOnlyRenderBundle,
+///     view: &wgpu::TextureView,
+/// ) {
+///     let mut command_encoder = device.create_command_encoder(&Default::default());
+///     {
+///         let mut pass = command_encoder.begin_fragment_only_render_pass(
+///             &lf_gfx::FragmentOnlyRenderPassDescriptor {
+///                 label: Some("fullscreen pass"),
+///                 color_attachments: &[Some(lf_gfx::FragmentOnlyColorAttachment::load(view))],
+///                 stencil_attachment: None,
+///                 timestamp_writes: None,
+///             },
+///         );
+///         bundle.execute(&mut pass);
+///     }
+///     queue.submit([command_encoder.finish()]);
+/// }
+/// ```
 pub struct FragmentOnlyRenderBundle {
     render_bundle: wgpu::RenderBundle,
+    color_formats: Vec<Option<wgpu::TextureFormat>>,
+    sample_count: u32,
+}
+
+impl FragmentOnlyRenderBundle {
+    /// Replays this bundle in the given pass. Equivalent to [`FragmentOnlyRenderPass::execute_bundles`] with just
+    /// this bundle.
+    pub fn execute<'a>(&'a self, pass: &mut FragmentOnlyRenderPass<'a>) {
+        pass.execute_bundles(std::iter::once(self))
+    }
+
+    /// The formats of the color attachments that this bundle was recorded for.
+    pub fn color_formats(&self) -> &[Option<wgpu::TextureFormat>] {
+        &self.color_formats
+    }
+
+    /// The sample count of the attachments that this bundle was recorded for.
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Checks that this bundle can be executed in a pass with the given color attachment formats and sample
+    /// count, giving what differs if it can't.
+    pub fn check_compatible_with(
+        &self,
+        color_formats: &[Option<wgpu::TextureFormat>],
+        sample_count: u32,
+    ) -> Result<(), BundleCompatibilityError> {
+        if self.color_formats != color_formats {
+            return Err(BundleCompatibilityError::ColorFormats {
+                bundle: self.color_formats.clone(),
+                pass: color_formats.to_vec(),
+            });
+        }
+        if self.sample_count != sample_count {
+            return Err(BundleCompatibilityError::SampleCount {
+                bundle: self.sample_count,
+                pass: sample_count,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Why a [`FragmentOnlyRenderBundle`] can't be executed in a pass, given by
+/// [`FragmentOnlyRenderBundle::check_compatible_with`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum BundleCompatibilityError {
+    #[error(
+        "bundle was recorded for color formats {bundle:?}, but the pass has color formats {pass:?}"
+    )]
+    ColorFormats {
+        bundle: Vec<Option<wgpu::TextureFormat>>,
+        pass: Vec<Option<wgpu::TextureFormat>>,
+    },
+    #[error("bundle was recorded for sample count {bundle}, but the pass has sample count {pass}")]
+    SampleCount { bundle: u32, pass: u32 },
+}
+
+/// A color attachment of a fragment-only render pass, equivalent to [`wgpu::RenderPassColorAttachment`]. Most
+/// passes either clear the attachment or draw over what is already there, so use
+/// [`FragmentOnlyColorAttachment::clear`] or [`FragmentOnlyColorAttachment::load`] rather than setting up the
+/// operations by hand.
+#[derive(Debug, Clone)]
+pub struct FragmentOnlyColorAttachment<'tex> {
+    pub view: &'tex wgpu::TextureView,
+    pub resolve_target: Option<&'tex wgpu::TextureView>,
+    pub ops: wgpu::Operations<wgpu::Color>,
+}
+
+impl<'tex> FragmentOnlyColorAttachment<'tex> {
+    /// An attachment which is cleared to the given color before drawing, keeping what is drawn.
+    pub fn clear(view: &'tex wgpu::TextureView, color: wgpu::Color) -> Self {
+        Self {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(color),
+                store: wgpu::StoreOp::Store,
+            },
+        }
+    }
+
+    /// An attachment which keeps its previous contents, and has what is drawn drawn over them.
+    pub fn load(view: &'tex wgpu::TextureView) -> Self {
+        Self {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Load,
+                store: wgpu::StoreOp::Store,
+            },
+        }
+    }
+
+    /// Resolves the attachment into the given view at the end of the pass, for multisampled attachments.
+    pub fn with_resolve_target(mut self, resolve_target: &'tex wgpu::TextureView) -> Self {
+        self.resolve_target = Some(resolve_target);
+        self
+    }
+
+    fn to_wgpu(&self) -> wgpu::RenderPassColorAttachment<'tex> {
+        wgpu::RenderPassColorAttachment {
+            view: self.view,
+            resolve_target: self.resolve_target,
+            ops: self.ops,
+        }
+    }
+}
+
+impl<'tex> From<wgpu::RenderPassColorAttachment<'tex>> for FragmentOnlyColorAttachment<'tex> {
+    fn from(attachment: wgpu::RenderPassColorAttachment<'tex>) -> Self {
+        Self {
+            view: attachment.view,
+            resolve_target: attachment.resolve_target,
+            ops: attachment.ops,
+        }
+    }
 }
 
 pub struct FragmentOnlyRenderPassStencilAttachment<'tex> {
@@ -169,7 +392,7 @@ pub struct FragmentOnlyRenderPassStencilAttachment<'tex> {
 
 pub struct FragmentOnlyRenderPassDescriptor<'tex, 'desc> {
     pub label: wgpu::Label<'desc>,
-    pub color_attachments: &'desc [Option<wgpu::RenderPassColorAttachment<'tex>>],
+    pub color_attachments: &'desc [Option<FragmentOnlyColorAttachment<'tex>>],
     pub stencil_attachment: Option<FragmentOnlyRenderPassStencilAttachment<'tex>>,
     pub timestamp_writes: Option<wgpu::RenderPassTimestampWrites<'desc>>,
 }
@@ -184,9 +407,18 @@ impl<'a> FragmentOnlyRenderPass<'a> {
         command_encoder: &'a mut wgpu::CommandEncoder,
         desc: &FragmentOnlyRenderPassDescriptor<'a, '_>,
     ) -> Self {
+        let color_attachments: Vec<_> = desc
+            .color_attachments
+            .iter()
+            .map(|attachment| {
+                attachment
+                    .as_ref()
+                    .map(FragmentOnlyColorAttachment::to_wgpu)
+            })
+            .collect();
         let desc = wgpu::RenderPassDescriptor {
             label: desc.label,
-            color_attachments: desc.color_attachments,
+            color_attachments: &color_attachments,
             depth_stencil_attachment: desc.stencil_attachment.as_ref().map(|attachment| {
                 wgpu::RenderPassDepthStencilAttachment {
                     view: attachment.view,
@@ -0,0 +1,82 @@
+//! Uniform data that changes every frame. Writing to a uniform buffer which the GPU may still be reading
+//! from a previous frame either stalls or races, so we keep one copy per frame that may be in flight and
+//! rotate between them.
+
+use std::{
+    marker::PhantomData,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+/// A uniform buffer holding one copy of `T` for every frame that may be in flight at once, plus the one being
+/// written. Bind it with `has_dynamic_offset: true`, and use the offset returned from [`FrameUniform::write`]
+/// when setting the bind group for that frame.
+pub struct FrameUniform<T> {
+    buffer: wgpu::Buffer,
+    stride: wgpu::BufferAddress,
+    copies: u32,
+    next: AtomicU32,
+
+    _phantom: PhantomData<fn(T)>,
+}
+
+impl<T: bytemuck::Pod> FrameUniform<T> {
+    /// Creates a buffer holding `desired_frame_latency + 1` copies of `T`, each aligned to the
+    /// `min_uniform_buffer_offset_alignment` of the given limits.
+    pub fn new(
+        device: &wgpu::Device,
+        limits: &wgpu::Limits,
+        desired_frame_latency: u32,
+        label: wgpu::Label,
+    ) -> Self {
+        let stride = crate::next_multiple_of(
+            std::mem::size_of::<T>() as wgpu::BufferAddress,
+            limits.min_uniform_buffer_offset_alignment as wgpu::BufferAddress,
+        );
+        let copies = desired_frame_latency + 1;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label,
+            size: stride * copies as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            buffer,
+            stride,
+            copies,
+            next: AtomicU32::new(0),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The buffer holding every copy.
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// A binding of a single copy of `T`, to be offset by the value given by [`FrameUniform::write`].
+    pub fn binding(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+            buffer: &self.buffer,
+            offset: 0,
+            size: wgpu::BufferSize::new(std::mem::size_of::<T>() as u64),
+        })
+    }
+
+    /// Writes the value for this frame into the next copy, giving the dynamic offset to bind the copy with.
+    /// Should be called once per frame.
+    pub fn write(&self, queue: &wgpu::Queue, value: &T) -> wgpu::DynamicOffset {
+        let index = self
+            .next
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |index| {
+                Some((index + 1) % self.copies)
+            })
+            .expect("update closure always succeeds");
+
+        let offset = self.stride * index as wgpu::BufferAddress;
+        queue.write_buffer(&self.buffer, offset, bytemuck::bytes_of(value));
+
+        offset as wgpu::DynamicOffset
+    }
+}
@@ -1,22 +1,52 @@
 //! A 'Game' in this context is a program that uses both wgpu and winit.
+mod dynamic_resolution;
+mod features;
+mod frame_budget;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+mod gpu_errors;
 pub(crate) mod input;
+mod loading;
 mod surface;
+mod touch;
 pub(crate) mod window;
 
-use std::sync::{atomic::AtomicBool, Arc};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{atomic::AtomicBool, Arc, Mutex},
+    time::Duration,
+};
 
 use log::info;
+use serde::{Deserialize, Serialize};
+use smallvec::{smallvec, SmallVec};
 use winit::{
-    dpi::PhysicalPosition,
-    event::{DeviceEvent, Event, WindowEvent},
-    event_loop::{EventLoop, EventLoopWindowTarget},
-    keyboard::PhysicalKey,
-    window::Window,
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{DeviceEvent, Event, Ime, WindowEvent},
+    event_loop::{ControlFlow, EventLoop, EventLoopProxy, EventLoopWindowTarget},
+    keyboard::{ModifiersState, PhysicalKey},
+    window::{CursorGrabMode, Theme, UserAttentionType, Window, WindowBuilder, WindowId},
+};
+
+use crate::{
+    clipboard::{ClipboardImage, PasteData},
+    file_drop::DroppedFile,
+    game::window::{
+        ExtraWindow, GameWindow, MonitorId, MonitorInfo, VideoModeSelector, WindowGeometry,
+    },
+    Clock, FrameUniform, LfInstanceExt, LfLimitsExt,
 };
 
-use crate::{game::window::GameWindow, LfLimitsExt};
+pub use self::dynamic_resolution::FrameStats;
+pub use self::features::FeatureReport;
+pub use self::frame_budget::FrameBudgetReport;
+pub use self::gpu_errors::GpuErrorCapture;
+pub use self::loading::LoadingProgress;
 
-use self::input::{InputMap, MouseInputType, VectorInputActivation, VectorInputType};
+use self::input::{
+    InputMap, InputMapStorage, MouseInputType, VectorInputActivation, VectorInputType,
+};
 
 /// A cloneable and distributable flag that can be cheaply queried to see if the game has exited.
 ///
@@ -42,13 +72,15 @@ impl ExitFlag {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum InputMode {
     /// Indicates that any keyboard, mouse or gamepad input should be captured by the input management system,
     /// no raw input events should be passed to the game implementation, and the cursor should be hidden.
     Exclusive,
     /// Indicates that any keyboard, mouse or gamepad input should not be captured by the input management system,
     /// all raw input events should be passed to the game implementation, and the cursor should be shown.
+    /// Inputs with an entry in the input map tagged with this mode, such as with
+    /// [`InputMap::assign_linear_in`](input::InputMap::assign_linear_in), are still given to the game as actions.
     UI,
     /// Indicates that keyboard, mouse or gamepad input should be captured both by the input management system,
     /// and raw input events should be passed to the game implementation, and the cursor should be shown.
@@ -85,22 +117,639 @@ impl InputMode {
     }
 }
 
+/// A touchpad or touchscreen gesture, as used for zooming, panning and rotating in 2D and CAD-style applications.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gesture {
+    /// Two fingers moved together or apart. The delta is the change in scale, positive when zooming in.
+    /// On web, browsers report touchpad pinches as scrolling with <kbd>Ctrl</kbd> held, which is translated into
+    /// this. Also given for two fingers pinching on a touchscreen.
+    Pinch { delta: f64 },
+    /// Two fingers on a touchscreen moved together. The deltas are how far the point between them moved, in
+    /// physical pixels, with `dy` positive downwards. Touchpads give two-finger movement as scrolling instead.
+    Pan { dx: f64, dy: f64 },
+    /// Two fingers rotated about each other. The delta is the change in angle, in radians, positive when
+    /// rotating anticlockwise.
+    Rotate { delta: f32 },
+    /// A double tap with two fingers, which usually toggles between zoom levels. Only reported on macOS.
+    SmartMagnify,
+}
+
+/// How often the game loop draws frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderMode {
+    /// Frames are drawn back-to-back, limited only by [`RunConfig::frame_rate_limit`] and the surface's present mode.
+    #[default]
+    Continuous,
+    /// Frames are only drawn when the windowing system asks for one, for example when the window is uncovered,
+    /// when input is received, when the window is resized, when a [`GameCommand`] is sent, or when requested with
+    /// [`GameData::request_redraw_in`]. The game loop sleeps in between, making this suited to tools and menus
+    /// which are mostly idle. With the `gamepad` feature, the loop briefly wakes to check for gamepad input,
+    /// every 10ms while a gamepad is connected and every second otherwise.
+    OnDemand,
+}
+
+/// Options for how the game loop is run which are not specific to any one game implementation.
+///
+/// Use with [`crate::LfGameExt::run_with_config`]. Construct with struct update syntax over the defaults:
+///
+/// ```
+/// let config = lf_gfx::RunConfig {
+///     alert_on_init_failure: false,
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct RunConfig {
+    /// Flags used to create the wgpu instance. Defaults to debugging and validation on debug builds, and
+    /// discarding labels on release builds.
+    pub instance_flags: wgpu::InstanceFlags,
+    /// Whether [`Game::on_init_failure`] is invoked if the game fails to initialise, before panicking. By default,
+    /// this shows the user an [`crate::alert_dialogue`] explaining what went wrong.
+    pub alert_on_init_failure: bool,
+    /// When frames are drawn.
+    pub render_mode: RenderMode,
+    /// The color that frames are cleared to when the game doesn't draw them itself, such as while loading. Given
+    /// as a linear color, so use [`crate::srgb_color`] for colors picked as sRGB bytes. Defaults to black.
+    pub clear_color: wgpu::Color,
+    /// The maximum number of frames drawn per second when rendering continuously. Between frames the game loop
+    /// sleeps rather than polling for events. Limits of 0 or below, or NaN, are treated as no limit.
+    pub frame_rate_limit: Option<f32>,
+    /// The most [`GameCommand`]s which can be waiting to be handled at once. When `None`, the default, any number
+    /// can wait. With a capacity, [`CommandSender::send`] blocks while the queue is full, and
+    /// [`CommandSender::try_send`] fails, so a thread sending commands faster than the game loop handles them
+    /// can't use up unbounded memory. A capacity of `0` is treated as `1`.
+    pub command_capacity: Option<usize>,
+    /// Whether the canvas is watched for changes to its displayed size, such as from CSS layout or from a mobile
+    /// device being rotated, which winit doesn't always report. The surface is resized to match once the size has
+    /// settled. Only used on web. Defaults to `true`.
+    pub observe_canvas_size: bool,
+    /// When given, wgpu errors which aren't caught by an error scope are logged and kept for
+    /// [`GameData::recent_gpu_errors`], rather than panicking, so that shipped games can collect errors from the
+    /// field for bug reports. Defaults to `None`, keeping wgpu's default handler.
+    pub gpu_error_capture: Option<GpuErrorCapture>,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        #[cfg(debug_assertions)]
+        let instance_flags = wgpu::InstanceFlags::DEBUG | wgpu::InstanceFlags::VALIDATION;
+        #[cfg(not(debug_assertions))]
+        let instance_flags = wgpu::InstanceFlags::DISCARD_HAL_LABELS;
+
+        Self {
+            instance_flags,
+            alert_on_init_failure: true,
+            render_mode: RenderMode::default(),
+            clear_color: wgpu::Color::BLACK,
+            frame_rate_limit: None,
+            command_capacity: None,
+            observe_canvas_size: true,
+            gpu_error_capture: None,
+        }
+    }
+}
+
+/// The most fixed updates run before a single frame. If the game falls further behind than this, the remaining
+/// time is dropped rather than trying to catch up, which would only make the next frame later still.
+const MAX_FIXED_UPDATES_PER_FRAME: u32 = 8;
+
+/// The number of frames that we allow to be queued for presentation to the surface at once.
+const DESIRED_MAXIMUM_FRAME_LATENCY: u32 = 2;
+
+/// The height of a line of scrolling in logical pixels, for converting scrolling given in pixels, such as by
+/// touchpads, into the lines given by mouse wheels. This is roughly the height of a line of text in browsers.
+const SCROLL_LINE_HEIGHT: f64 = 20.0;
+
+/// How often gamepads are polled while waiting for events, as gilrs can't wake the event loop. Gamepads are polled
+/// quickly while one is connected, so that presses are felt straight away, and slowly otherwise, so that newly
+/// connected gamepads are noticed.
+#[cfg(feature = "gamepad")]
+const GAMEPAD_POLL_INTERVAL: Duration = Duration::from_millis(10);
+#[cfg(feature = "gamepad")]
+const GAMEPAD_CONNECTION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the window must go without moving or resizing before its geometry is stored, so that it is stored
+/// once when the user lets go rather than for every step of a drag.
+const WINDOW_GEOMETRY_STORE_DELAY: Duration = Duration::from_millis(500);
+
 /// A command sent to the game to change the game state
 pub enum GameCommand {
     Exit,
     SetInputMode(InputMode),
     SetMouseSensitivity(f32),
+    /// Sets the activation given to [`MouseInputType::ScrollUp`] and [`MouseInputType::ScrollDown`] for each line
+    /// scrolled, clamped at 1. Defaults to 0.25, so that spinning the wheel faster gives stronger activations.
+    SetScrollSensitivity(f32),
+    /// Replaces the input map. Send with [`GameData::set_input_map`], which checks that the map is for the right
+    /// input types.
+    SetInputMap {
+        map: Box<dyn std::any::Any + Send>,
+        press_held: bool,
+    },
+    /// Whether the cursor is kept within the window while it is focused, for example for scrolling by moving the
+    /// cursor to the edge of the screen. This is independent of the cursor being hidden or recentered by the
+    /// [`InputMode`]. Confinement isn't supported on the web or macOS, where this does nothing.
+    SetCursorConfine(bool),
+    /// Makes the main window fullscreen on a monitor from [`GameData::available_monitors`]. With a video mode, the
+    /// monitor is switched to the closest of its modes for exclusive fullscreen, and otherwise the window covers the
+    /// monitor without a border. If the monitor is no longer connected, the monitor the window is on is used.
+    /// Leave fullscreen with [`winit::window::Window::set_fullscreen`] on [`GameData::window`].
+    SetFullscreenOn {
+        monitor: MonitorId,
+        mode: Option<VideoModeSelector>,
+    },
+    /// Begins recording calibrations for the sticks of all connected gamepads, to correct for drift and wear.
+    /// The sticks should be at rest when this is sent, and then be moved around their full range. No stick input
+    /// is given while calibrating. Does nothing without the `gamepad` feature.
+    BeginStickCalibration,
+    /// Finishes recording stick calibrations, which are kept in [`crate::local_storage`] for future runs.
+    EndStickCalibration,
+    /// Rumbles a gamepad. See [`GameData::set_gamepad_rumble`].
+    SetGamepadRumble {
+        gamepad: input::GamepadId,
+        strong: f32,
+        weak: f32,
+        duration: Duration,
+    },
+    /// Closes a window created with [`GameData::create_window`].
+    CloseWindow(WindowId),
+    /// Pauses or resumes the game. While paused, [`Game::update`] and [`Game::fixed_update`] aren't invoked and
+    /// [`GameData::frame_delta`] is zero, but frames are still rendered and input is still handled, so that a
+    /// pause menu can be shown. See [`GameData::is_paused`].
+    SetPaused(bool),
+    /// Sets how the main window's surface textures can be used, such as adding [`wgpu::TextureUsages::COPY_SRC`]
+    /// to copy frames out for screenshots. [`wgpu::TextureUsages::RENDER_ATTACHMENT`] is always included. The
+    /// change is applied once work already submitted has finished, within a few frames, in the same way as a
+    /// resize. Usages that the surface doesn't support are logged and ignored.
+    SetSurfaceUsage(wgpu::TextureUsages),
+    /// Starts or stops text entry, such as when a text field gains or loses focus. See
+    /// [`GameData::set_text_input_active`].
+    SetTextInputActive {
+        active: bool,
+        cursor_area: Rect,
+    },
+    /// Captures the next frame drawn in RenderDoc, including all work submitted while drawing it. Bind this to a
+    /// key to capture the frame showing a problem without catching it by hand. Requires the `renderdoc` feature,
+    /// and does nothing but log a warning without it, on web, or if the game isn't running under RenderDoc.
+    CaptureGpuFrame,
+}
+
+/// The coordinates that pointer movement is given in by [`Game::handle_raw_pointer_delta`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PointerDeltaSpace {
+    /// Physical pixels, the same as the surface and [`GameData::cursor_position`].
+    #[default]
+    Physical,
+    /// Logical pixels, which are physical pixels divided by [`GameData::scale_factor`], so that movements are the
+    /// same size on high-DPI displays.
+    Logical,
+}
+
+/// An area of the window, in physical pixels from the top left, as with [`GameData::cursor_position`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rect {
+    pub position: PhysicalPosition<f64>,
+    pub size: PhysicalSize<f64>,
+}
+
+/// Sends [`GameCommand`]s to the game loop, waking it if it is idle so that the command is handled promptly.
+#[derive(Clone)]
+pub struct CommandSender {
+    sender: flume::Sender<GameCommand>,
+    waker: Arc<Mutex<EventLoopProxy<()>>>,
+}
+
+impl CommandSender {
+    /// Sends a command, blocking while the queue is full if [`RunConfig::command_capacity`] is set. Only fails
+    /// once the game has exited.
+    ///
+    /// Commands are handled by the game loop, so with a capacity this must not be called from the game loop
+    /// itself, such as from within [`Game`] methods, as the queue can't empty while the loop waits. Use
+    /// [`CommandSender::try_send`] there instead.
+    pub fn send(&self, command: GameCommand) -> Result<(), flume::SendError<GameCommand>> {
+        self.sender.send(command)?;
+        self.wake();
+        Ok(())
+    }
+
+    /// Sends a command without blocking. Fails with [`flume::TrySendError::Full`], giving the command back, if
+    /// [`RunConfig::command_capacity`] is set and that many commands are already waiting to be handled, or with
+    /// [`flume::TrySendError::Disconnected`] once the game has exited. Without a capacity, only fails once the
+    /// game has exited.
+    pub fn try_send(&self, command: GameCommand) -> Result<(), flume::TrySendError<GameCommand>> {
+        self.sender.try_send(command)?;
+        self.wake();
+        Ok(())
+    }
+
+    /// Sends a command from the game loop, which can't block on a full queue, dropping the command if it is full.
+    fn send_from_loop(&self, command: GameCommand) {
+        if let Err(flume::TrySendError::Full(_)) = self.try_send(command) {
+            log::error!("command queue is full, so a command was dropped");
+        }
+    }
+
+    fn wake(&self) {
+        // If the loop has already exited then there's nothing to wake
+        let _ = self.waker.lock().unwrap().send_event(());
+    }
 }
 
 pub struct GameData {
-    pub command_sender: flume::Sender<GameCommand>,
+    pub command_sender: CommandSender,
     pub surface_format: wgpu::TextureFormat,
     pub limits: wgpu::Limits,
+    /// The name of the tier chosen from [`Game::limits_profiles`], if the game gave any.
+    pub limits_profile: Option<String>,
+    feature_report: FeatureReport,
     pub size: winit::dpi::PhysicalSize<u32>,
+    /// The size of the view given to [`Game::render_to`]. This is the same as [`GameData::size`], unless
+    /// [`Game::adjust_quality`] has lowered the resolution that frames are rendered at.
+    pub render_size: winit::dpi::PhysicalSize<u32>,
+    /// Where the cursor was last seen over the window, or `None` if it has left the window. Given in the same
+    /// physical pixels as [`GameData::size`] and the surface, from the top left, on every platform, so it can be
+    /// used directly for picking what was drawn under the cursor.
+    ///
+    /// On web, the browser gives positions in CSS pixels, which are scaled by the page's `devicePixelRatio` and
+    /// mapped onto the canvas's device pixels to match the surface. Touches given to [`Game::gesture`] are
+    /// converted in the same way, and positions from elsewhere on the page can be converted with
+    /// [`GameWindow::to_surface_position`].
+    pub cursor_position: Option<PhysicalPosition<f64>>,
     pub window: GameWindow,
+    /// The adapter that the device was created on, for querying capabilities such as texture format features
+    /// and surface capabilities after initialisation.
+    pub adapter: wgpu::Adapter,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub exit_flag: ExitFlag,
+    /// The time since the previous frame, limited to [`Game::max_frame_delta`]. Zero while the game is paused.
+    pub frame_delta: Duration,
+    /// Whether the time since the previous frame was longer than [`Game::max_frame_delta`], and so
+    /// [`GameData::frame_delta`] was clamped. This happens after a hitch, such as the game being paused in a
+    /// debugger or the browser tab being in the background, and games may want to pause instead of carrying on.
+    pub frame_delta_clamped: bool,
+    /// How far through loading the game is. See [`LoadingProgress`].
+    pub loading_progress: LoadingProgress,
+
+    // Tracked from events rather than queried, so that it changes at the same time as the surface is resized
+    scale_factor: f64,
+    clock: Clock,
+    // Set by `GameCommand::SetPaused`
+    paused: bool,
+    // Whether the OS is locking or confining the cursor
+    cursor_grab_active: bool,
+    // Set by `GameCommand::SetTextInputActive`
+    text_input_active: bool,
+    // Set up when `RunConfig::gpu_error_capture` is given
+    gpu_errors: Option<gpu_errors::GpuErrorLog>,
+    clear_color: wgpu::Color,
+    // The latest activation of each of the game's actions
+    action_values: input::ActionValues,
+    // The index of the most recent submission made through `GameData::submit`
+    last_submission: Mutex<Option<wgpu::SubmissionIndex>>,
+    // When a frame was requested with `GameData::request_redraw_in`
+    redraw_requested_at: Mutex<Option<web_time::Instant>>,
+    // Windows requested with `GameData::create_window`, which are built the next time the loop is idle
+    pending_windows: Mutex<Vec<WindowBuilder>>,
+    // Set while input from a gamepad is being handled
+    input_gamepad: Option<input::GamepadId>,
+    // Kept up to date by the game loop as gamepads connect and disconnect
+    connected_gamepads: Mutex<Vec<input::GamepadId>>,
+    // Threads running futures given to `GameData::spawn`, which are joined on exit
+    #[cfg(not(target_arch = "wasm32"))]
+    tasks: Mutex<Vec<std::thread::JoinHandle<()>>>,
+}
+
+impl GameData {
+    /// The theme (light or dark mode) reported by the OS, if one could be determined. On web this is
+    /// derived from the `prefers-color-scheme` media query.
+    pub fn system_theme(&self) -> Option<Theme> {
+        self.window.theme()
+    }
+
+    /// The clock that the game loop is timed with, started just before the game was initialised. Use this for
+    /// time-based animation rather than `std::time::Instant`, which panics on web.
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// The latest activation given to [`Game::handle_linear_input`] for an action, or zero if it hasn't been
+    /// activated, for polling inputs rather than reacting to each change. When the action is bound to several
+    /// inputs, this is the strongest activation of those held. Inputs which are never released, such as mouse
+    /// movement and scrolling, only activate the action until the end of the next frame. Always zero if the
+    /// action isn't the game's [`Game::LinearInputType`].
+    pub fn linear_action_value<TLinear: std::hash::Hash + Eq + 'static>(
+        &self,
+        action: &TLinear,
+    ) -> input::LinearInputActivation {
+        self.action_values.linear(action)
+    }
+
+    /// The latest activation given to [`Game::handle_vector_input`] for an action, or zero if it hasn't been
+    /// activated. When the action is bound to several inputs, this is the largest activation of those. Mouse
+    /// movement only activates the action until the end of the next frame. Always zero if the action isn't the
+    /// game's [`Game::VectorInputType`].
+    pub fn vector_action_value<TVector: std::hash::Hash + Eq + 'static>(
+        &self,
+        action: &TVector,
+    ) -> VectorInputActivation {
+        self.action_values.vector(action)
+    }
+
+    /// Whether the OS is currently locking or confining the cursor, as requested by an exclusive [`InputMode`] or
+    /// [`GameCommand::SetCursorConfine`]. Some platforms can't grab the cursor, in which case this is `false` even
+    /// while a grab is wanted. Exclusive input modes still work without a grab, by recentering the cursor each
+    /// time it moves, but the cursor may escape the window when moved quickly, so games may want to warn the user.
+    pub fn cursor_grab_active(&self) -> bool {
+        self.cursor_grab_active
+    }
+
+    /// The platform's raw handle for the game window, for interop with libraries which draw into or overlay the
+    /// window, such as video decoders. Fails if the platform doesn't currently have a window to give, such as
+    /// on Android while the app is suspended.
+    ///
+    /// The raw handle isn't tied to a lifetime, but is only valid until the window is dropped after
+    /// [`Game::finished`]. Anything given the handle must be torn down first, and must not close or otherwise
+    /// take ownership of the window. Where a library accepts a borrowed handle,
+    /// [`HasWindowHandle`](crate::raw_window_handle::HasWindowHandle) is also implemented for
+    /// [`GameData::window`], which has these constraints checked by the borrow checker.
+    pub fn raw_window_handle(
+        &self,
+    ) -> Result<crate::raw_window_handle::RawWindowHandle, crate::raw_window_handle::HandleError>
+    {
+        use crate::raw_window_handle::HasWindowHandle;
+        self.window.window_handle().map(|handle| handle.as_raw())
+    }
+
+    /// The platform's raw handle for the display that the game window is on, which many libraries need alongside
+    /// [`GameData::raw_window_handle`]. Valid for as long as the window.
+    pub fn raw_display_handle(
+        &self,
+    ) -> Result<crate::raw_window_handle::RawDisplayHandle, crate::raw_window_handle::HandleError>
+    {
+        use crate::raw_window_handle::HasDisplayHandle;
+        self.window.display_handle().map(|handle| handle.as_raw())
+    }
+
+    /// Which features were requested when the device was created, which were granted, and why any that the game
+    /// didn't ask for were enabled.
+    pub fn feature_report(&self) -> &FeatureReport {
+        &self.feature_report
+    }
+
+    /// The wgpu errors which weren't caught by an error scope, oldest first, for showing to the user or including
+    /// in a bug report. Always empty unless [`RunConfig::gpu_error_capture`] was given. When captured errors are
+    /// persisted, this includes errors from previous runs until [`GameData::clear_gpu_errors`] is called.
+    pub fn recent_gpu_errors(&self) -> Vec<String> {
+        self.gpu_errors
+            .as_ref()
+            .map(|errors| errors.recent())
+            .unwrap_or_default()
+    }
+
+    /// Forgets every captured error given by [`GameData::recent_gpu_errors`], such as once they have been reported.
+    pub fn clear_gpu_errors(&self) {
+        if let Some(errors) = &self.gpu_errors {
+            errors.clear();
+        }
+    }
+
+    /// The color that frames are cleared to when the game doesn't draw them itself, given by
+    /// [`RunConfig::clear_color`].
+    pub fn clear_color(&self) -> wgpu::Color {
+        self.clear_color
+    }
+
+    /// Whether the game is paused with [`GameCommand::SetPaused`].
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Whether text is being entered, set by [`GameData::set_text_input_active`].
+    pub fn text_input_active(&self) -> bool {
+        self.text_input_active
+    }
+
+    /// Starts text entry when a text field gains focus, or stops it when the field loses focus. While active, the
+    /// platform's input method editor (IME) is allowed, so that text can be composed in languages such as
+    /// Japanese, and its candidate window is placed next to `cursor_area`, the area of the window taken up by the
+    /// text cursor. Send this again as the text cursor moves to keep the candidate window alongside it.
+    ///
+    /// While active, keyboard input isn't mapped to actions, so that typing doesn't trigger gameplay hotkeys, and
+    /// any keys held when text entry starts are released. Keyboard and IME events are still given to
+    /// [`Game::process_raw_event`] for the text field, as long as the [`InputMode`] passes on raw input. While
+    /// inactive, IME events are discarded, and keys are mapped to actions as usual.
+    pub fn set_text_input_active(&self, active: bool, cursor_area: Rect) {
+        self.command_sender
+            .send_from_loop(GameCommand::SetTextInputActive {
+                active,
+                cursor_area,
+            });
+    }
+
+    /// Pauses or resumes the game. See [`GameCommand::SetPaused`].
+    pub fn set_paused(&self, paused: bool) {
+        self.command_sender
+            .send_from_loop(GameCommand::SetPaused(paused));
+    }
+
+    /// The number of physical pixels per logical pixel for the monitor the window is on. On web, this is the
+    /// page's `devicePixelRatio`.
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// Shows or hides the game window. Used with [`Game::start_hidden`] to reveal the window once the game
+    /// is ready to draw, typically after the first successful [`Game::render_to`].
+    pub fn set_window_visible(&self, visible: bool) {
+        self.window.set_window_visible(visible);
+    }
+
+    /// Requests the user's attention while the window isn't focused. See [`GameWindow::request_attention`].
+    pub fn request_attention(&self, kind: Option<UserAttentionType>) {
+        self.window.request_attention(kind);
+    }
+
+    /// Submits command buffers to the queue, as with [`wgpu::Queue::submit`], but records the submission so that
+    /// it can later be waited on with [`GameData::wait_for_last_submission`].
+    pub fn submit<I: IntoIterator<Item = wgpu::CommandBuffer>>(
+        &self,
+        command_buffers: I,
+    ) -> wgpu::SubmissionIndex {
+        let index = self.queue.submit(command_buffers);
+        *self.last_submission.lock().unwrap() = Some(index.clone());
+        index
+    }
+
+    /// Rounds an offset up to the next multiple of the device's `min_uniform_buffer_offset_alignment`, as
+    /// required of the dynamic offsets given to uniform buffer bindings.
+    pub fn aligned_uniform_offset(&self, unaligned: wgpu::BufferAddress) -> wgpu::BufferAddress {
+        crate::next_multiple_of(
+            unaligned,
+            self.limits.min_uniform_buffer_offset_alignment as wgpu::BufferAddress,
+        )
+    }
+
+    /// Writes a value into the `index`th slot of a buffer holding an array of `T`s spaced out to the device's
+    /// `min_uniform_buffer_offset_alignment`, giving the dynamic offset to bind that slot with.
+    pub fn write_uniform_at<T: bytemuck::Pod>(
+        &self,
+        buffer: &wgpu::Buffer,
+        index: u32,
+        value: &T,
+    ) -> wgpu::DynamicOffset {
+        let stride = self.aligned_uniform_offset(std::mem::size_of::<T>() as wgpu::BufferAddress);
+        let offset = stride * index as wgpu::BufferAddress;
+        self.queue
+            .write_buffer(buffer, offset, bytemuck::bytes_of(value));
+        offset as wgpu::DynamicOffset
+    }
+
+    /// The index of the most recent submission made with [`GameData::submit`], if any have been made.
+    pub fn last_submission(&self) -> Option<wgpu::SubmissionIndex> {
+        self.last_submission.lock().unwrap().clone()
+    }
+
+    /// Blocks until the most recent submission made with [`GameData::submit`] has finished executing on the GPU.
+    /// Does nothing on web, where the browser can't be blocked.
+    pub fn wait_for_last_submission(&self) {
+        if let Some(index) = self.last_submission() {
+            self.device
+                .poll(wgpu::Maintain::WaitForSubmissionIndex(index));
+        }
+    }
+
+    /// Requests that a frame is drawn after the given delay. Frames are drawn continuously by default, so
+    /// this is only needed with [`RenderMode::OnDemand`], for example to animate a blinking cursor. If multiple
+    /// frames are requested before any are drawn, the earliest request is kept.
+    pub fn request_redraw_in(&self, delay: Duration) {
+        let at = Clock::now() + delay;
+        let mut redraw_requested_at = self.redraw_requested_at.lock().unwrap();
+        *redraw_requested_at = Some(match *redraw_requested_at {
+            Some(existing) => existing.min(at),
+            None => at,
+        });
+    }
+
+    /// Reads an image from the clipboard, for pasting into the game. Gives `None` if the clipboard doesn't
+    /// hold an image, can't be read, or the user denies permission to read it.
+    ///
+    /// On web, the browser only allows the clipboard to be read asynchronously, and may prompt the user for
+    /// permission first, so the image is given by a future. Spawn it rather than blocking on it. On native, the
+    /// future is ready immediately, and always gives `None` without the `clipboard` feature.
+    pub fn get_clipboard_image(&self) -> impl std::future::Future<Output = Option<ClipboardImage>> {
+        crate::clipboard::get_image()
+    }
+
+    /// Runs a future in the background, for work such as loading assets. On web the future is run on the
+    /// browser's event loop, and on native it is given its own thread.
+    ///
+    /// Futures can't be cancelled, so long-running work should check a clone of [`GameData::exit_flag`] and
+    /// finish once it is set. On native, the game waits for every spawned future to finish before exiting.
+    pub fn spawn(&self, future: impl Future<Output = ()> + wgpu::WasmNotSend + 'static) {
+        #[cfg(target_arch = "wasm32")]
+        wasm_bindgen_futures::spawn_local(future);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let task = std::thread::spawn(move || pollster::block_on(future));
+
+            let mut tasks = self.tasks.lock().unwrap();
+            tasks.retain(|task| !task.is_finished());
+            tasks.push(task);
+        }
+    }
+
+    /// Requests that another window is opened, for example for an inspector in an editor. Windows can't be created
+    /// while handling an event, so it is built once the current event has been handled, and given to
+    /// [`Game::window_created`]. The window's events are given to the game with its [`WindowId`], and it is
+    /// drawn to with [`Game::render_to_extra_window`]. Close it by sending [`GameCommand::CloseWindow`].
+    pub fn create_window(&self, builder: WindowBuilder) {
+        self.pending_windows.lock().unwrap().push(builder);
+    }
+
+    /// The number of [`GameCommand`]s sent but not yet handled by the game loop. Commands are handled once
+    /// before each frame, so a count which keeps growing means commands are being sent faster than the game
+    /// loop can keep up with.
+    pub fn pending_command_count(&self) -> usize {
+        self.command_sender.sender.len()
+    }
+
+    /// Replaces the input map, such as to apply new bindings from a settings screen, and stores it as the
+    /// user's input preferences if there is [`Game::input_storage`]. The map must be for the game's
+    /// [`Game::LinearInputType`] and [`Game::VectorInputType`], or it is ignored and an error is logged.
+    ///
+    /// Any inputs held when the map is replaced are released under the old map, so that nothing stays held
+    /// because its release was mapped to something else. If `press_held` is true, they are then pressed again
+    /// under the new map, so that a key held while rebinding immediately does whatever it is now bound to.
+    pub fn set_input_map<TLinear: Send + 'static, TVector: Send + 'static>(
+        &self,
+        map: InputMap<TLinear, TVector>,
+        press_held: bool,
+    ) {
+        self.command_sender
+            .send_from_loop(GameCommand::SetInputMap {
+                map: Box::new(map),
+                press_held,
+            });
+    }
+
+    /// The ids of all currently connected gamepads. Always empty without the `gamepad` feature.
+    pub fn connected_gamepads(&self) -> Vec<input::GamepadId> {
+        self.connected_gamepads.lock().unwrap().clone()
+    }
+
+    /// The gamepad that the input currently being handled came from, while in [`Game::handle_linear_input`] or
+    /// [`Game::handle_vector_input`], so that several players can each use their own gamepad. `None` for input
+    /// from the keyboard and mouse, and outside of input handling.
+    pub fn input_gamepad(&self) -> Option<input::GamepadId> {
+        self.input_gamepad
+    }
+
+    /// Rumbles a gamepad with the given strengths, from `0.0` to `1.0`, for the given time, replacing any rumble
+    /// already playing on that gamepad. The strong motor gives low frequency rumble, and the weak motor high
+    /// frequency rumble. Setting both strengths to `0.0` stops the gamepad rumbling. All rumble is stopped when
+    /// the game exits.
+    ///
+    /// Does nothing if the gamepad doesn't support force feedback, which includes all gamepads on the web and on
+    /// macOS, or without the `gamepad` feature.
+    pub fn set_gamepad_rumble(
+        &self,
+        gamepad: input::GamepadId,
+        strong: f32,
+        weak: f32,
+        duration: Duration,
+    ) {
+        self.command_sender
+            .send_from_loop(GameCommand::SetGamepadRumble {
+                gamepad,
+                strong,
+                weak,
+                duration,
+            });
+    }
+
+    /// The size of the primary monitor, if it can be determined. On web, this is the size of the screen that the
+    /// page is on.
+    pub fn primary_monitor_size(&self) -> Option<winit::dpi::PhysicalSize<u32>> {
+        self.window.primary_monitor_size()
+    }
+
+    /// All of the monitors connected to the system.
+    pub fn available_monitors(&self) -> Vec<MonitorInfo> {
+        self.window.available_monitors()
+    }
+
+    /// Creates a uniform buffer with enough copies of `T` to write a new value every frame without
+    /// overwriting data that the GPU may still be reading from a frame in flight.
+    pub fn create_frame_uniform<T: bytemuck::Pod>(&self, label: wgpu::Label) -> FrameUniform<T> {
+        FrameUniform::new(
+            &self.device,
+            &self.limits,
+            DESIRED_MAXIMUM_FRAME_LATENCY,
+            label,
+        )
+    }
 }
 
 /// All of the callbacks required to implement a game. This API is built on top of a message passing
@@ -110,24 +759,116 @@ pub trait Game: Sized {
     /// Data processed before the window exists. This should be minimal and kept to `mpsc` message reception from initialiser threads.
     type InitData;
 
-    type LinearInputType;
-    type VectorInputType;
+    /// The actions that linear inputs are mapped to. These are hashed so that each action's latest activation can
+    /// be polled with [`GameData::linear_action_value`].
+    type LinearInputType: std::hash::Hash + Eq + Clone + Send + Sync + 'static;
+    /// The actions that vector inputs are mapped to, which can be polled with [`GameData::vector_action_value`].
+    type VectorInputType: std::hash::Hash + Eq + Clone + Send + Sync + 'static;
 
     fn title() -> impl Into<String>;
 
+    /// Whether the window is created hidden, to be shown later with [`GameData::set_window_visible`]. This
+    /// avoids showing an empty window while the game is initialising. Defaults to `false`.
+    fn start_hidden() -> bool {
+        false
+    }
+
+    /// Whether the window's size, position, and maximized or fullscreen state are saved to
+    /// [`crate::local_storage`] when the game exits, and restored the next time it runs. They are also saved once
+    /// the window has been still for a moment after being moved or resized, so that they are kept if the game
+    /// crashes. If the monitor the
+    /// window was on is no longer connected, the OS chooses where to place it. Does nothing on web, where the
+    /// page lays out the canvas. Defaults to `false`.
+    fn remember_window_geometry() -> bool {
+        false
+    }
+
+    /// The backends that adapters may be picked from. The `WGPU_BACKEND` environment variable overrides this when
+    /// set. Defaults to WebGPU, falling back to WebGL2, on web, and to the primary backends (Vulkan, Metal, DX12)
+    /// on native, avoiding the less reliable secondary backends.
+    fn backends() -> wgpu::Backends {
+        if cfg!(target_arch = "wasm32") {
+            wgpu::Backends::BROWSER_WEBGPU | wgpu::Backends::GL
+        } else {
+            wgpu::Backends::PRIMARY
+        }
+    }
+
+    /// The present modes the game would like to use, most preferred first. The first which the surface supports
+    /// is used, falling back to [`wgpu::PresentMode::Fifo`], which is always supported. The `Auto` modes are always
+    /// supported, and pick a mode themselves. Defaults to [`wgpu::PresentMode::AutoVsync`].
+    fn present_mode_preference() -> &'static [wgpu::PresentMode] {
+        &[wgpu::PresentMode::AutoVsync]
+    }
+
+    /// The features the game can't run without. Initialisation fails if the adapter doesn't support all of them.
+    /// Defaults to none.
+    fn required_features() -> wgpu::Features {
+        wgpu::Features::empty()
+    }
+    /// The features the game would like, but can run without. Those which the adapter supports are enabled, and
+    /// the rest are listed as denied in [`GameData::feature_report`]. Defaults to none.
+    fn optional_features() -> wgpu::Features {
+        wgpu::Features::empty()
+    }
+
     fn target_limits() -> wgpu::Limits {
         wgpu::Limits::downlevel_webgl2_defaults()
     }
+    /// Tiers of limits that the game can run with, such as quality settings. If given, the highest tier
+    /// supported by the adapter is used in place of [`Game::target_limits`], and its name is given in
+    /// [`GameData::limits_profile`]. Initialisation fails if no tier is supported. Defaults to `None`.
+    fn limits_profiles() -> Option<crate::LimitsProfiles> {
+        None
+    }
     fn default_inputs(&self) -> InputMap<Self::LinearInputType, Self::VectorInputType>;
 
+    /// Where the user's input preferences are kept between runs, such as
+    /// `Some(InputMapStorage::new("input_map"))`. When given, the stored preferences are combined with
+    /// [`Game::default_inputs`] on start, and maps given to [`GameData::set_input_map`] are stored. Defaults to
+    /// `None`, so input preferences aren't stored.
+    fn input_storage() -> Option<InputMapStorage<Self::LinearInputType, Self::VectorInputType>> {
+        None
+    }
+
     fn init(data: &GameData, init: Self::InitData) -> anyhow::Result<Self>;
 
+    /// Invoked straight after [`Game::init`], with the input map in effect: the [`Game::default_inputs`]
+    /// combined with the user's stored preferences, if there is [`Game::input_storage`]. Useful for building UI
+    /// which shows the current bindings.
+    fn inputs_loaded(
+        &mut self,
+        _: &GameData,
+        _inputs: &InputMap<Self::LinearInputType, Self::VectorInputType>,
+    ) {
+    }
+
+    /// Invoked if the game fails to initialise, such as when no adapter is found, the device can't be created,
+    /// or [`Game::init`] gives an error, if [`RunConfig::alert_on_init_failure`] is set. The game panics
+    /// afterwards.
+    ///
+    /// By default, shows an [`crate::alert_dialogue`] with the error and a [`crate::adapter_report`], so that
+    /// players have something to act on or send to support.
+    fn on_init_failure(error: &anyhow::Error) {
+        crate::alert_dialogue(&format!(
+            "The game failed to start:\n{error:#}\n\n{}",
+            crate::adapter_report()
+        ));
+    }
+
     /// Allows you to intercept and cancel events, before passing them off to the standard event handler,
     /// to allow for egui integration, among others.
     ///
+    /// The events returned are handled in order in place of the given event, so an event can be passed through
+    /// unchanged, dropped by returning no events, or translated into any number of other events.
+    ///
     /// This method only receives input events if the cursor is not captured, to avoid UI glitches.
-    fn process_raw_event<'a, T>(&mut self, _: &GameData, event: Event<T>) -> Option<Event<T>> {
-        Some(event)
+    fn process_raw_event<'a, T>(
+        &mut self,
+        _: &GameData,
+        event: Event<T>,
+    ) -> SmallVec<[Event<T>; 1]> {
+        smallvec![event]
     }
 
     fn window_resize(&mut self, data: &GameData, new_size: winit::dpi::PhysicalSize<u32>);
@@ -146,93 +887,448 @@ pub trait Game: Sized {
         activation: input::VectorInputActivation,
     );
 
+    /// Whether synthetic key events are given as input. Winit synthesizes presses for keys which are already
+    /// held when the window gains focus, and releases for keys held when it loses focus, so games that want to
+    /// know the full keyboard state after alt-tabbing should enable this. Defaults to `false`.
+    fn process_synthetic_keys() -> bool {
+        false
+    }
+
+    /// Invoked in place of [`Game::handle_linear_input`] for input given by a synthetic key event, when
+    /// [`Game::process_synthetic_keys`] is enabled. Defaults to calling [`Game::handle_linear_input`].
+    fn handle_synthetic_linear_input(
+        &mut self,
+        data: &GameData,
+        input: &Self::LinearInputType,
+        activation: input::LinearInputActivation,
+    ) {
+        self.handle_linear_input(data, input, activation)
+    }
+
+    /// Invoked whenever the pointer moves, with how far it moved in the space given by
+    /// [`Game::pointer_delta_space`]. Unlike the [`input::VectorInputType::MouseMove`] input, the movement isn't
+    /// scaled by the mouse sensitivity or clamped, and is given in every [`InputMode`], for games that need
+    /// precise distances, such as for drawing or box selection. While the cursor is locked by
+    /// [`InputMode::Exclusive`], the movement is the mouse's raw motion, which most platforms give in units close
+    /// to pixels but which isn't affected by pointer acceleration.
+    fn handle_raw_pointer_delta(&mut self, _: &GameData, _dx: f64, _dy: f64) {}
+
+    /// The coordinates that [`Game::handle_raw_pointer_delta`] gives movement in. Defaults to
+    /// [`PointerDeltaSpace::Physical`], matching [`GameData::cursor_position`].
+    fn pointer_delta_space() -> PointerDeltaSpace {
+        PointerDeltaSpace::Physical
+    }
+
+    /// Invoked when the user makes a touchpad or touchscreen gesture, such as pinching to zoom.
+    fn gesture(&mut self, _: &GameData, _: Gesture) {}
+
+    /// Invoked when the user pastes into the game, once for each thing pasted. On web, this is given by the
+    /// browser's `paste` event, which gives text and files. On native, the clipboard is read when Ctrl+V (Cmd+V on
+    /// macOS) is pressed, giving text and images, and requires the `clipboard` feature. The key press is still
+    /// given as input.
+    fn clipboard_pasted(&mut self, _: &GameData, _: PasteData) {}
+
+    /// Whether a file dragged onto the window would be accepted if dropped, to filter which files are given to
+    /// [`Game::file_dropped`]. On web, only the file's name is given, once it has been dropped, and the file must
+    /// also be accepted by [`Game::accepts_dropped_mime_type`]. Defaults to accepting every file.
+    fn accepts_dropped_file(&self, _path: &std::path::Path) -> bool {
+        true
+    }
+
+    /// Whether a file dropped onto the window on web would be accepted, given the MIME type the browser gave it,
+    /// such as `image/png`. Browsers give an empty MIME type for types they don't know, which includes most
+    /// game-specific formats, so those are best accepted by their extension in [`Game::accepts_dropped_file`].
+    /// Never invoked on native, where files don't have MIME types. Defaults to accepting every file.
+    fn accepts_dropped_mime_type(&self, _mime_type: &str) -> bool {
+        true
+    }
+
+    /// Invoked when a file is dragged over the window, with whether [`Game::accepts_dropped_file`] accepts it, to
+    /// show feedback such as "this file type isn't accepted". Invoked once for each file on native. Browsers
+    /// hide dragged files until they are dropped, so on web this is invoked once, accepted.
+    fn drag_hover(&mut self, _: &GameData, _accepted: bool) {}
+
+    /// Invoked when files stop being dragged over the window, either because they were dropped or because the
+    /// drag left the window or was cancelled.
+    fn drag_hover_ended(&mut self, _: &GameData) {}
+
+    /// Invoked for each file dropped onto the window which [`Game::accepts_dropped_file`] accepts.
+    fn file_dropped(&mut self, _: &GameData, _: DroppedFile) {}
+
+    /// Invoked once a window requested with [`GameData::create_window`] has been opened.
+    fn window_created(&mut self, _: &GameData, _window: Arc<Window>) {}
+
+    /// Invoked when a window created with [`GameData::create_window`] is resized.
+    fn extra_window_resize(
+        &mut self,
+        _: &GameData,
+        _window: WindowId,
+        _new_size: winit::dpi::PhysicalSize<u32>,
+    ) {
+    }
+
+    /// Invoked when a window created with [`GameData::create_window`] is told to close. By default, closes
+    /// the window.
+    fn extra_window_close_requested(&mut self, data: &GameData, window: WindowId) {
+        data.command_sender
+            .send_from_loop(GameCommand::CloseWindow(window));
+    }
+
+    /// Invoked for every other event given to a window created with [`GameData::create_window`], with the window
+    /// it was given to, such as the keyboard, mouse and touch input made while the window is focused. Input to
+    /// extra windows isn't passed through the input map, which only handles input to the main window.
+    fn extra_window_event(&mut self, _: &GameData, _window: WindowId, _event: WindowEvent) {}
+
+    /// Requests that the next frame of a window created with [`GameData::create_window`] is drawn into the
+    /// view. Failures are handled as with [`Game::render_to`]. By default, nothing is drawn.
+    fn render_to_extra_window(
+        &mut self,
+        _: &GameData,
+        _window: WindowId,
+        _view: wgpu::TextureView,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Invoked when the OS theme changes between light and dark mode. The current theme can be queried at any
+    /// time with [`GameData::system_theme`].
+    fn theme_changed(&mut self, _: &GameData, _: Theme) {}
+
+    /// Invoked when the window gains or loses focus. While unfocused, no input is captured by the input
+    /// management system and the cursor is released, regardless of the current [`InputMode`]. The input
+    /// mode is restored when focus returns. Any inputs held when focus is lost, or when switching to an input
+    /// mode which doesn't capture input, are released with an activation of zero.
+    fn focus_changed(&mut self, _: &GameData, _focused: bool) {}
+
+    /// Invoked when the platform's input method editor (IME) is enabled or disabled for the window. The IME is
+    /// only allowed while [`GameData::set_text_input_active`] is set, and can then be enabled or disabled by the
+    /// user, such as by switching keyboard layout, so games may want to show which is in use.
+    fn on_ime_enabled(&mut self, _: &GameData, _enabled: bool) {}
+
+    /// Invoked when the window is minimized or restored. No frames are drawn while the window is minimized, so
+    /// this is a good place to pause any simulation.
+    fn minimized_changed(&mut self, _: &GameData, _minimized: bool) {}
+
+    /// Invoked when the window's scale factor changes, such as when it is moved to a monitor with a different
+    /// DPI or the page is zoomed on web, so that UI can be rescaled. Invoked after the surface has been resized
+    /// to the window's new physical size, and [`GameData::scale_factor`] already gives the new scale factor.
+    fn scale_factor_changed(&mut self, _: &GameData, _old: f64, _new: f64) {}
+
+    /// The most frames drawn per second while the window isn't focused, when rendering continuously, to save power
+    /// while the game keeps animating in the background. Frames are never drawn faster than
+    /// [`RunConfig::frame_rate_limit`]. Rates of 0 or below, or NaN, are treated as `None`, so to stop drawing
+    /// while unfocused, use [`RenderMode::OnDemand`] instead. Defaults to `None`, drawing at the same rate whether
+    /// focused or not.
+    fn unfocused_frame_rate() -> Option<f32> {
+        None
+    }
+
+    /// The longest time that is reported between two frames in [`GameData::frame_delta`]. Longer gaps, caused by
+    /// hitches, are clamped to this to avoid simulations taking huge steps. Defaults to 100ms.
+    fn max_frame_delta() -> Duration {
+        Duration::from_millis(100)
+    }
+
+    /// The time between calls to [`Game::fixed_update`], or `None` if the game doesn't use fixed updates.
+    /// Defaults to `None`.
+    fn fixed_timestep() -> Option<Duration> {
+        None
+    }
+
+    /// Invoked once before each frame is drawn, after any fixed updates. The time since the last frame is
+    /// given by [`GameData::frame_delta`].
+    fn update(&mut self, _: &GameData) {}
+
+    /// Invoked at a steady rate given by [`Game::fixed_timestep`], for simulations which need a constant time
+    /// step. Runs as many times before each frame as is needed to catch up with the time passed, up to a limit,
+    /// after which the game is allowed to fall behind.
+    fn fixed_update(&mut self, _: &GameData, _timestep: Duration) {}
+
     /// Requests that the next frame is drawn into the view, pretty please :)
-    fn render_to(&mut self, data: &GameData, view: wgpu::TextureView);
+    ///
+    /// If drawing fails, return an error and the frame is discarded rather than presented, and the
+    /// error is given to [`Game::on_render_error`]. Implementations which can't fail should return `Ok(())`.
+    fn render_to(&mut self, data: &GameData, view: wgpu::TextureView) -> anyhow::Result<()>;
+
+    /// Requests that the next frame is drawn into the surface texture. Override this in place of
+    /// [`Game::render_to`] to create custom views of the texture, such as with one of the surface's
+    /// `view_formats`, or to check whether the surface is `suboptimal`. The texture is presented afterwards, so
+    /// shouldn't be presented here.
+    ///
+    /// Defaults to calling [`Game::render_to`] with a default view of the texture.
+    fn render_to_surface_texture(
+        &mut self,
+        data: &GameData,
+        output: &wgpu::SurfaceTexture,
+    ) -> anyhow::Result<()> {
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        self.render_to(data, view)
+    }
+
+    /// How long drawing a frame on the CPU may take before [`Game::frame_budget_exceeded`] is invoked, to catch
+    /// performance regressions during development. Defaults to 1/60th of a second. Return `None` to never check.
+    fn frame_budget() -> Option<Duration> {
+        Some(Duration::from_micros(16_667))
+    }
+
+    /// Invoked after a frame is drawn if the call to [`Game::render_to`], or whichever method drew the frame, took
+    /// longer than [`Game::frame_budget`] on the CPU. Invoked at most once a second, with every frame over budget
+    /// since the last invocation, and the time spent by the GPU if the device has
+    /// [`wgpu::Features::TIMESTAMP_QUERY`]. Defaults to logging a warning.
+    fn frame_budget_exceeded(&mut self, _: &GameData, report: FrameBudgetReport) {
+        let budget = Self::frame_budget().unwrap_or_default();
+        let gpu_time = match report.gpu_time {
+            Some(gpu_time) => format!(", and {gpu_time:?} on the GPU"),
+            None => String::new(),
+        };
+        log::warn!(
+            "{} frames went over the budget of {budget:?}, the slowest taking {:?} on the CPU{gpu_time}",
+            report.frames_over_budget,
+            report.cpu_time,
+        );
+    }
+
+    /// Invoked right after a frame drawn by [`Game::render_to`] is presented, with the index of that frame.
+    /// Frame indices start at zero and increase by one for every presented frame, so this is the place for
+    /// end-of-frame bookkeeping such as advancing N-buffered resources.
+    fn frame_presented(&mut self, _: &GameData, _frame_index: u64) {}
+
+    /// Invoked before each frame with the timings of recent frames, to pick the fraction of the surface's
+    /// resolution to render at, in (0, 1]. When below 1, [`Game::render_to`] is given a view of a smaller texture,
+    /// of size [`GameData::render_size`], which is then upscaled onto the surface. Changes to the scale are
+    /// smoothed over several frames, and it can't go below 0.25. [`Game::render_to_surface_texture`] isn't used
+    /// while the scale is below 1. Defaults to `None`, always rendering at full resolution.
+    fn adjust_quality(&mut self, _: &GameData, _stats: &FrameStats) -> Option<f32> {
+        None
+    }
+
+    /// Draws a frame while the game is loading, in place of [`Game::render_to`], given the progress from `0.0` to
+    /// `1.0`. See [`GameData::loading_progress`]. By default, clears the view to [`RunConfig::clear_color`].
+    fn loading_frame(
+        &mut self,
+        data: &GameData,
+        view: wgpu::TextureView,
+        _progress: f32,
+    ) -> anyhow::Result<()> {
+        let mut encoder = data
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("loading frame"),
+            });
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("loading frame"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(data.clear_color()),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        data.queue.submit([encoder.finish()]);
+        Ok(())
+    }
+
+    /// Invoked once loading reaches completion, before the first frame is drawn with [`Game::render_to`]. Only
+    /// invoked if a loading screen was shown.
+    fn loading_finished(&mut self, _: &GameData) {}
+
+    /// Invoked if the GPU device is lost, such as when the graphics driver crashes or is updated, or the GPU is
+    /// removed. Nothing more can be drawn, so the game exits afterwards, and no more frames are drawn. By
+    /// default, shows an [`crate::alert_dialogue`] explaining what happened.
+    fn device_lost(&mut self, _: &GameData, _reason: wgpu::DeviceLostReason, message: &str) {
+        crate::alert_dialogue(&format!(
+            "The connection to the graphics device was lost, so the game has to close. This can happen when \
+             graphics drivers crash or are updated.\n\n{message}"
+        ));
+    }
+
+    /// Invoked when [`Game::render_to`] fails. By default, logs the error and continues with the next frame.
+    fn on_render_error(&mut self, _: &GameData, err: anyhow::Error) {
+        log::error!("failed to render frame: {err}");
+    }
 
     /// Invoked when the window is told to close (i.e. x pressed, sigint, etc.) but not when
     /// a synthetic exit is triggered by enqueuing `GameCommand::Exit`. To actually do something with the
     /// user's request to quit, this method must enqueue `GameCommand::Exit`
     fn user_exit_requested(&mut self, data: &GameData) {
-        let _ = data.command_sender.send(GameCommand::Exit);
+        data.command_sender.send_from_loop(GameCommand::Exit);
     }
 
     /// Invoked right at the end of the program life, after the final frame is rendered.
     fn finished(self, _: GameData) {}
 }
 
+fn choose_present_mode<T: Game>(supported: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+    T::present_mode_preference()
+        .iter()
+        .copied()
+        .find(|mode| {
+            matches!(
+                mode,
+                wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync
+            ) || supported.contains(mode)
+        })
+        .unwrap_or(wgpu::PresentMode::Fifo)
+}
+
 /// All the data held by a program/game while running. `T` gives the top-level state for the game
 /// implementation
 pub(crate) struct GameState<T: Game> {
     data: GameData,
     game: T,
     input_map: input::InputMap<T::LinearInputType, T::VectorInputType>,
+    input_storage: Option<InputMapStorage<T::LinearInputType, T::VectorInputType>>,
     command_receiver: flume::Receiver<GameCommand>,
+    // Pastes given by the browser's `paste` event
+    #[cfg(target_arch = "wasm32")]
+    paste_receiver: flume::Receiver<PasteData>,
+    // Files dragged onto the canvas
+    #[cfg(target_arch = "wasm32")]
+    drop_receiver: flume::Receiver<crate::file_drop::DropEvent>,
+    // Whether files are being dragged over the window
+    drag_hovering: bool,
+    // Fingers on the touchscreen, for recognising gestures
+    touches: touch::TouchTracker,
+    // Sizes of the canvas, given by `RunConfig::observe_canvas_size`
+    #[cfg(target_arch = "wasm32")]
+    canvas_size_receiver: Option<flume::Receiver<winit::dpi::PhysicalSize<u32>>>,
+    config: RunConfig,
 
     surface: surface::ResizableSurface<'static>,
-
+    // Needed to create surfaces for extra windows
+    instance: wgpu::Instance,
+    extra_windows: HashMap<WindowId, ExtraWindow>,
+    // When we last began drawing a frame, used to pace frames when the frame rate is limited
+    last_frame_start: web_time::Instant,
+    // The index given to the next presented frame
+    frame_index: u64,
+    // Time not yet simulated by fixed updates
+    fixed_update_accumulator: Duration,
+    // While true, frames are drawn with `Game::loading_frame`
+    loading: bool,
+    // Frame timings, and the resolution chosen by `Game::adjust_quality`
+    dynamic_resolution: dynamic_resolution::DynamicResolution,
+    frame_budget: frame_budget::FrameBudget,
+    // While true, no frames are drawn
+    minimized: bool,
+    // The scale factor before it last changed, until the game has been told of the change
+    pending_scale_factor_change: Option<f64>,
+    // Given the reason and message when the device is lost unexpectedly
+    device_lost_receiver: flume::Receiver<(wgpu::DeviceLostReason, String)>,
+    // Once true, nothing more can be drawn
+    device_lost: bool,
+    // While false, input isn't captured and the cursor is released, regardless of input mode
+    focused: bool,
+    // Kept up to date as the window moves and resizes, if the game wants it remembered, and stored on exit
+    window_geometry: Option<WindowGeometry>,
+    // When the window last moved or resized, if its geometry hasn't been stored since
+    window_geometry_changed_at: Option<web_time::Instant>,
+
+    // The modifier keys last reported as held
+    modifiers: ModifiersState,
+    // `None` if gamepads aren't supported on this platform
+    #[cfg(feature = "gamepad")]
+    gamepads: Option<gamepad::Gamepads>,
+    // Set by `GameCommand::CaptureGpuFrame`, along with the RenderDoc API, which is loaded the first time a
+    // capture is asked for
+    #[cfg(all(feature = "renderdoc", not(target_arch = "wasm32")))]
+    capture_next_frame: bool,
+    #[cfg(all(feature = "renderdoc", not(target_arch = "wasm32")))]
+    renderdoc: Option<renderdoc::RenderDoc<renderdoc::V110>>,
     // While true, disallows cursor movement
     input_mode: InputMode,
+    // While true, the cursor is kept within the window while focused
+    cursor_confined: bool,
+    // While true, the platform is locking the cursor for us, so mouse movement is read from device events
+    native_cursor_lock: bool,
+    // Linear inputs which have been activated but not yet released, and how far. Inputs are kept for each gamepad
+    // giving them, so that releasing a button on one gamepad doesn't release it on another
+    held_linear_inputs:
+        HashMap<(input::LinearInputType, Option<input::GamepadId>), input::LinearInputActivation>,
     // The last position we saw the cursor at
     last_cursor_position: PhysicalPosition<f64>,
     // A multiplier, from pixels moved to intensity, clamped at 1.0
     mouse_sensitivity: f32,
+    // A multiplier, from lines scrolled to intensity, clamped at 1.0
+    scroll_sensitivity: f32,
 }
 
 impl<T: Game + 'static> GameState<T> {
     // Creating some of the wgpu types requires async code
-    async fn new(init: T::InitData, window: GameWindow) -> anyhow::Result<Self> {
+    async fn new(
+        init: T::InitData,
+        window: GameWindow,
+        run_config: RunConfig,
+        waker: EventLoopProxy<()>,
+    ) -> anyhow::Result<Self> {
         let size = (&window).inner_size();
-
-        #[cfg(debug_assertions)]
-        let flags = wgpu::InstanceFlags::DEBUG | wgpu::InstanceFlags::VALIDATION;
-        #[cfg(not(debug_assertions))]
-        let flags = wgpu::InstanceFlags::DISCARD_HAL_LABELS;
+        let window_geometry = (T::remember_window_geometry() && cfg!(not(target_arch = "wasm32")))
+            .then(|| WindowGeometry::of(&window));
 
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::all()),
+            backends: wgpu::util::backend_bits_from_env().unwrap_or(T::backends()),
             dx12_shader_compiler: wgpu::util::dx12_shader_compiler_from_env().unwrap_or_default(),
-            flags,
+            flags: run_config.instance_flags,
             gles_minor_version: wgpu::Gles3MinorVersion::Automatic,
         });
 
         let surface = window.create_surface(&instance)?;
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                force_fallback_adapter: false,
-                compatible_surface: Some(&surface),
-            })
-            .await
-            .ok_or(anyhow::Error::msg("failed to request adapter"))?;
+        // A remembered choice of adapter is used if it still matches one
+        let mut preferred_adapter = None;
+        if let Some(preference) = crate::AdapterPreference::load() {
+            let query = preference.query(Some(&surface));
+            preferred_adapter = instance.request_powerful_adapter(&query).await;
+            if preferred_adapter.is_none() {
+                log::warn!("no adapter matches the stored preference {preference:?}");
+            }
+        }
+        let adapter = match preferred_adapter {
+            Some(adapter) => adapter,
+            None => instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::HighPerformance,
+                    force_fallback_adapter: false,
+                    compatible_surface: Some(&surface),
+                })
+                .await
+                .ok_or(anyhow::Error::msg("failed to request adapter"))?,
+        };
 
-        let available_limits = if cfg!(target_arch = "wasm32") {
-            wgpu::Limits::downlevel_webgl2_defaults()
-        } else {
-            adapter.limits()
+        // WebGL2 can't report its actual limits, so we assume the minimum. Browsers with WebGPU, and native
+        // backends, report their limits accurately.
+        let available_limits = match adapter.get_info().backend {
+            wgpu::Backend::Gl if cfg!(target_arch = "wasm32") => {
+                wgpu::Limits::downlevel_webgl2_defaults()
+            }
+            _ => adapter.limits(),
         };
 
-        let target_limits = T::target_limits();
+        let (target_limits, limits_profile) = match T::limits_profiles() {
+            Some(profiles) => {
+                let (name, limits) =
+                    profiles
+                        .best_supported(&available_limits)
+                        .ok_or(anyhow::Error::msg(
+                            "adapter does not support any of the limits profiles",
+                        ))?;
+                info!("using limits profile {name}");
+                (limits.clone(), Some(name.to_owned()))
+            }
+            None => (T::target_limits(), None),
+        };
         let required_limits = available_limits.intersection(&target_limits);
 
-        let mut required_features = wgpu::Features::empty();
-        // Assume integrated and virtual GPUs, and CPUs, are UMA
-        if adapter
-            .features()
-            .contains(wgpu::Features::MAPPABLE_PRIMARY_BUFFERS)
-            && matches!(
-                adapter.get_info().device_type,
-                wgpu::DeviceType::IntegratedGpu
-                    | wgpu::DeviceType::Cpu
-                    | wgpu::DeviceType::VirtualGpu
-            )
-        {
-            required_features |= wgpu::Features::MAPPABLE_PRIMARY_BUFFERS;
-        }
-        // Things that are always helpful
-        required_features |= adapter.features().intersection(
-            wgpu::Features::TIMESTAMP_QUERY | wgpu::Features::TIMESTAMP_QUERY_INSIDE_PASSES,
-        );
+        let feature_report =
+            FeatureReport::negotiate(&adapter, T::required_features(), T::optional_features())?;
+        info!("features: {feature_report:#?}");
 
         info!("info: {:#?}", adapter.get_info());
         info!("limits: {:#?}", adapter.limits());
@@ -240,7 +1336,7 @@ impl<T: Game + 'static> GameState<T> {
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
-                    required_features,
+                    required_features: feature_report.granted,
                     required_limits: required_limits.clone(),
                     label: None,
                 },
@@ -250,13 +1346,12 @@ impl<T: Game + 'static> GameState<T> {
             .map_err(|err| anyhow::Error::msg(format!("failed to get device: {err}")))?;
 
         // Configure surface
-        let mut surface_config = surface
-            .get_default_config(&adapter, size.width, size.height)
-            .ok_or(anyhow::Error::msg("failed to get surface configuration"))?;
-        surface_config.present_mode = wgpu::PresentMode::AutoVsync;
-        surface.configure(&device, &surface_config);
-
         let surface_caps = surface.get_capabilities(&adapter);
+        if surface_caps.formats.is_empty() {
+            return Err(anyhow::Error::msg(
+                "surface is not supported by the adapter",
+            ));
+        }
 
         let surface_format = surface_caps
             .formats
@@ -266,60 +1361,175 @@ impl<T: Game + 'static> GameState<T> {
             .next()
             .unwrap_or(surface_caps.formats[0]);
 
+        // Allows games to view the surface as either sRGB or linear, where supported
+        let mut view_formats = Vec::new();
+        if adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::SURFACE_VIEW_FORMATS)
+        {
+            let counterpart = if surface_format.is_srgb() {
+                surface_format.remove_srgb_suffix()
+            } else {
+                surface_format.add_srgb_suffix()
+            };
+            if counterpart != surface_format {
+                view_formats.push(counterpart);
+            }
+        }
+
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
             width: size.width,
             height: size.height,
-            present_mode: surface_caps.present_modes[0],
+            present_mode: choose_present_mode::<T>(&surface_caps.present_modes),
             alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
-            desired_maximum_frame_latency: 2,
+            view_formats,
+            desired_maximum_frame_latency: DESIRED_MAXIMUM_FRAME_LATENCY,
         };
-        let surface = surface::ResizableSurface::new(surface, &device, config);
+        let surface = surface::ResizableSurface::new(surface, &adapter, &device, config);
+
+        let (device_lost_sender, device_lost_receiver) = flume::unbounded();
+        device.set_device_lost_callback(move |reason, message| {
+            // The callback is also invoked when the device is dropped on exit
+            if let wgpu::DeviceLostReason::Unknown = reason {
+                let _ = device_lost_sender.send((reason, message));
+            }
+        });
 
-        let (command_sender, command_receiver) = flume::unbounded();
+        #[cfg(target_arch = "wasm32")]
+        let paste_receiver = crate::clipboard::listen_for_paste(waker.clone());
+        #[cfg(target_arch = "wasm32")]
+        let drop_receiver = crate::file_drop::listen_for_drops(&window.canvas(), waker.clone());
+        #[cfg(target_arch = "wasm32")]
+        let canvas_size_receiver = run_config
+            .observe_canvas_size
+            .then(|| crate::wasm::observe_canvas_size(window.canvas(), waker.clone()));
+
+        let (sender, command_receiver) = match run_config.command_capacity {
+            Some(capacity) => flume::bounded(capacity.max(1)),
+            None => flume::unbounded(),
+        };
+        let command_sender = CommandSender {
+            sender,
+            waker: Arc::new(Mutex::new(waker)),
+        };
 
         // Some state can be set by commands to ensure valid initial state.
         command_sender
             .try_send(GameCommand::SetInputMode(InputMode::Unified))
-            .expect("unbounded queue held by this thread should send immediately");
+            .expect("empty queue held by this thread should send immediately");
 
+        let gpu_errors = run_config
+            .gpu_error_capture
+            .clone()
+            .map(|config| gpu_errors::GpuErrorLog::install(&device, config));
+
+        let clock = Clock::new();
         let data = GameData {
             command_sender,
             surface_format,
             limits: required_limits,
+            limits_profile,
+            feature_report,
             size,
+            render_size: size,
+            scale_factor: window.scale_factor(),
+            clock,
+            paused: false,
+            cursor_grab_active: false,
+            text_input_active: false,
+            gpu_errors,
+            clear_color: run_config.clear_color,
+            action_values: input::ActionValues::new::<T::LinearInputType, T::VectorInputType>(),
+            cursor_position: None,
             window,
+            adapter,
             device,
             queue,
             exit_flag: ExitFlag::new(),
+            frame_delta: Duration::ZERO,
+            frame_delta_clamped: false,
+            loading_progress: LoadingProgress::new(),
+            last_submission: Mutex::new(None),
+            redraw_requested_at: Mutex::new(None),
+            pending_windows: Mutex::new(Vec::new()),
+            input_gamepad: None,
+            connected_gamepads: Mutex::new(Vec::new()),
+            #[cfg(not(target_arch = "wasm32"))]
+            tasks: Mutex::new(Vec::new()),
         };
-        let game = T::init(&data, init)?;
+        let mut game = T::init(&data, init)?;
 
         // Gather inputs as a combination of registered user preferences and defaults.
-        let input_map = game.default_inputs();
+        let mut input_map = game.default_inputs();
+        let input_storage = T::input_storage();
+        if let Some(input_storage) = &input_storage {
+            input_storage.load_over(&mut input_map);
+        }
+        game.inputs_loaded(&data, &input_map);
+
+        let frame_budget =
+            frame_budget::FrameBudget::new(&data.device, &data.queue, data.feature_report.granted);
 
         Ok(Self {
             data,
             game,
             surface,
+            instance,
+            extra_windows: HashMap::new(),
             command_receiver,
+            #[cfg(target_arch = "wasm32")]
+            paste_receiver,
+            #[cfg(target_arch = "wasm32")]
+            drop_receiver,
+            drag_hovering: false,
+            touches: touch::TouchTracker::default(),
+            #[cfg(target_arch = "wasm32")]
+            canvas_size_receiver,
+            config: run_config,
+            last_frame_start: Clock::now(),
+            frame_index: 0,
+            fixed_update_accumulator: Duration::ZERO,
+            loading: false,
+            dynamic_resolution: dynamic_resolution::DynamicResolution::new(),
+            frame_budget,
+            minimized: false,
+            pending_scale_factor_change: None,
+            device_lost_receiver,
+            device_lost: false,
+            focused: true,
+            window_geometry,
+            window_geometry_changed_at: None,
             input_map,
+            input_storage,
+            modifiers: ModifiersState::empty(),
+            #[cfg(feature = "gamepad")]
+            gamepads: gamepad::Gamepads::new(),
+            #[cfg(all(feature = "renderdoc", not(target_arch = "wasm32")))]
+            capture_next_frame: false,
+            #[cfg(all(feature = "renderdoc", not(target_arch = "wasm32")))]
+            renderdoc: None,
             input_mode: InputMode::Unified,
+            cursor_confined: false,
+            native_cursor_lock: false,
+            held_linear_inputs: HashMap::new(),
             last_cursor_position: PhysicalPosition { x: 0.0, y: 0.0 },
             mouse_sensitivity: 0.01,
+            scroll_sensitivity: 0.25,
         })
     }
 
-    pub(crate) fn run(init: T::InitData) {
+    pub(crate) fn run(init: T::InitData, config: RunConfig) {
         let event_loop = EventLoop::new().expect("could not create game loop");
 
         // Built on first `Event::Resumed`
         // Taken out on `Event::LoopDestroyed`
         let mut state: Option<Self> = None;
         let (state_transmission, state_reception) = flume::bounded(1);
-        let mut init = Some((init, state_transmission));
+        let waker = event_loop.create_proxy();
+        let mut init = Some((init, config, waker, state_transmission));
 
         event_loop
             .run(move |event, window_target| {
@@ -330,19 +1540,22 @@ impl<T: Game + 'static> GameState<T> {
 
                 // Resume always emmitted to begin with - use it to begin an async method to create the game state.
                 if state.is_none() && event == Event::Resumed {
-                    if let Some((init, state_transmission)) = init.take() {
+                    if let Some((init, config, waker, state_transmission)) = init.take() {
                         async fn build_state<T: Game + 'static>(
                             init: T::InitData,
                             window: GameWindow,
+                            config: RunConfig,
+                            waker: EventLoopProxy<()>,
                             state_transmission: flume::Sender<GameState<T>>,
                         ) {
-                            let state = GameState::<T>::new(init, window).await;
+                            let alert_on_init_failure = config.alert_on_init_failure;
+                            let state = GameState::<T>::new(init, window, config, waker).await;
                             let state = match state {
                                 Ok(state) => state,
                                 Err(err) => {
-                                    crate::alert_dialogue(&format!(
-                                        "Initialisation failure:\n{err}"
-                                    ));
+                                    if alert_on_init_failure {
+                                        T::on_init_failure(&err);
+                                    }
                                     panic!("{err}");
                                 }
                             };
@@ -350,7 +1563,13 @@ impl<T: Game + 'static> GameState<T> {
                         }
 
                         let window = GameWindow::new::<T>(window_target);
-                        crate::block_on(build_state::<T>(init, window, state_transmission));
+                        crate::block_on(build_state::<T>(
+                            init,
+                            window,
+                            config,
+                            waker,
+                            state_transmission,
+                        ));
                     }
                 }
 
@@ -403,27 +1622,63 @@ impl<T: Game + 'static> GameState<T> {
         }
     }
 
+    /// Whether an event should trigger a frame when rendering on demand: input, commands sent to the game, and
+    /// changes to the window's size.
+    fn should_wake(event: &Event<()>) -> bool {
+        Self::is_input_event(event)
+            || matches!(
+                event,
+                Event::UserEvent(())
+                    | Event::WindowEvent {
+                        event: WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. },
+                        ..
+                    }
+            )
+    }
+
     fn receive_event(&mut self, mut event: Event<()>, window_target: &EventLoopWindowTarget<()>) {
         // Discard events that aren't for us
         event = match event {
-            Event::WindowEvent { window_id, .. } if window_id != self.window().id() => return,
+            Event::WindowEvent { window_id, .. }
+                if window_id != self.window().id()
+                    && !self.extra_windows.contains_key(&window_id) =>
+            {
+                return
+            }
             event => event,
         };
 
+        // The IME only has events once it has been disabled, unless text is being entered
+        if let Event::WindowEvent {
+            event: WindowEvent::Ime(ime),
+            ..
+        } = &event
+        {
+            if !self.data.text_input_active && *ime != Ime::Disabled {
+                return;
+            }
+        }
+
         // We filter all window events through the game to allow it to integrate with other libraries, such as egui.
         // But only send keyboard and mouse input events to UI if the mouse isn't captured.
         let should_send_input = self.input_mode.should_propogate_raw_input();
-        if should_send_input || !Self::is_input_event(&event) {
-            event = match self.game.process_raw_event(&self.data, event) {
-                None => return,
-                Some(event) => event,
-            };
-        }
+        let events = if should_send_input || !Self::is_input_event(&event) {
+            self.game.process_raw_event(&self.data, event)
+        } else {
+            smallvec![event]
+        };
 
-        self.process_event(event, window_target)
+        for event in events {
+            self.process_event(event, window_target)
+        }
     }
 
     fn process_event(&mut self, event: Event<()>, window_target: &EventLoopWindowTarget<()>) {
+        // When rendering on demand, anything that may change what is drawn wakes the loop for a single frame
+        if self.config.render_mode == RenderMode::OnDemand && Self::should_wake(&event) {
+            self.request_redraw();
+        }
+
         match event {
             Event::WindowEvent { event, window_id } if window_id == self.window().id() => {
                 match event {
@@ -432,61 +1687,144 @@ impl<T: Game + 'static> GameState<T> {
                     WindowEvent::Resized(winit::dpi::PhysicalSize {
                         width: 0,
                         height: 0,
-                    }) => {}
+                    }) => self.set_minimized(true),
                     WindowEvent::Resized(physical_size) => {
                         log::debug!("Resized: {:?}", physical_size);
+                        self.set_minimized(false);
                         self.resize(physical_size);
+                        self.update_window_geometry();
+                    }
+                    WindowEvent::Moved(_) => self.update_window_geometry(),
+                    WindowEvent::HoveredFile(path) => {
+                        let accepted = self.game.accepts_dropped_file(&path);
+                        self.drag_hovering = true;
+                        self.game.drag_hover(&self.data, accepted);
+                    }
+                    WindowEvent::HoveredFileCancelled => self.end_drag_hover(),
+                    WindowEvent::DroppedFile(path) => {
+                        self.end_drag_hover();
+                        self.file_dropped(DroppedFile::Path(path));
                     }
                     WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
                         log::debug!("Scale Factor Changed: {:?}", scale_factor);
-                        //self.resize(*new_inner_size);
+                        // The window is resized after this event, so the game is told once the surface has
+                        // been resized too
+                        self.pending_scale_factor_change
+                            .get_or_insert(self.data.scale_factor);
+                        self.data.scale_factor = scale_factor;
+                    }
+                    WindowEvent::Focused(focused) => self.set_focused(focused),
+                    WindowEvent::TouchpadMagnify { delta, .. } => {
+                        self.game.gesture(&self.data, Gesture::Pinch { delta })
+                    }
+                    WindowEvent::TouchpadRotate { delta, .. } => self.game.gesture(
+                        &self.data,
+                        Gesture::Rotate {
+                            delta: delta.to_radians(),
+                        },
+                    ),
+                    WindowEvent::SmartMagnify { .. } => {
+                        self.game.gesture(&self.data, Gesture::SmartMagnify)
+                    }
+                    WindowEvent::Touch(mut touch) => {
+                        touch.location = self
+                            .data
+                            .window
+                            .physical_to_surface_position(touch.location);
+                        for gesture in self.touches.touch(&touch) {
+                            self.game.gesture(&self.data, gesture);
+                        }
+                    }
+                    WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers.state(),
+                    WindowEvent::Ime(Ime::Enabled) => self.game.on_ime_enabled(&self.data, true),
+                    WindowEvent::Ime(Ime::Disabled) => self.game.on_ime_enabled(&self.data, false),
+                    // Browsers give pinches as scrolling with ctrl held
+                    #[cfg(target_arch = "wasm32")]
+                    WindowEvent::MouseWheel { delta, .. } if self.modifiers.control_key() => {
+                        let delta = match delta {
+                            winit::event::MouseScrollDelta::LineDelta(_, y) => -y as f64 * 0.1,
+                            winit::event::MouseScrollDelta::PixelDelta(position) => {
+                                -position.y * 0.01
+                            }
+                        };
+                        self.game.gesture(&self.data, Gesture::Pinch { delta })
+                    }
+                    WindowEvent::MouseWheel { delta, .. } => self.mouse_scrolled(delta),
+                    WindowEvent::ThemeChanged(theme) => {
+                        log::debug!("Theme Changed: {:?}", theme);
+                        self.game.theme_changed(&self.data, theme);
                     }
                     WindowEvent::KeyboardInput {
                         device_id: _device_id,
                         event,
                         is_synthetic,
-                    } if !is_synthetic && !event.repeat => {
-                        if let PhysicalKey::Code(key) = event.physical_key {
+                    } if (!is_synthetic || T::process_synthetic_keys()) && !event.repeat => {
+                        #[cfg(all(not(target_arch = "wasm32"), feature = "clipboard"))]
+                        if !is_synthetic {
+                            self.check_for_paste(&event);
+                        }
+
+                        // Typed text is for the text field, not the game's actions
+                        if self.data.text_input_active {
+                            return;
+                        }
+
+                        let key = match event.physical_key {
+                            PhysicalKey::Code(key) => input::KeyCode::try_from(key).ok(),
+                            PhysicalKey::Unidentified(_) => None,
+                        };
+                        if let Some(key) = key {
                             let activation = match event.state {
                                 winit::event::ElementState::Pressed => 1.0,
                                 winit::event::ElementState::Released => 0.0,
                             };
                             let activation = input::LinearInputActivation::try_from(activation)
                                 .expect("from const");
-                            self.linear_input(
-                                input::LinearInputType::KnownKeyboard(key.into()),
-                                activation,
-                            );
+                            let inputted = input::LinearInputType::KnownKeyboard(key);
+                            if is_synthetic {
+                                self.synthetic_linear_input(inputted, activation);
+                            } else {
+                                self.linear_input(inputted, activation);
+                            }
                         } else {
-                            eprintln!("unknown key code, scan code: {:?}", event.physical_key)
+                            log::debug!("unknown key code, scan code: {:?}", event.physical_key)
                         }
                     }
+                    WindowEvent::MouseInput { state, button, .. } => {
+                        let inputted = input::LinearInputType::Mouse(button.into());
+                        // Some platforms give a press or release more than once, which shouldn't reach the game
+                        // as more than one, in the same way that key repeats are ignored
+                        let held = self.held_linear_inputs.contains_key(&(inputted, None));
+                        let activation = match state {
+                            winit::event::ElementState::Pressed if !held => 1.0,
+                            winit::event::ElementState::Released if held => 0.0,
+                            _ => return,
+                        };
+                        let activation =
+                            input::LinearInputActivation::try_from(activation).expect("from const");
+                        self.linear_input(inputted, activation);
+                    }
+                    WindowEvent::CursorLeft { .. } => self.data.cursor_position = None,
                     WindowEvent::CursorMoved {
                         device_id: _device_id,
                         position,
                         ..
                     } => {
-                        let delta_x = position.x - self.last_cursor_position.x;
-                        let delta_y = position.y - self.last_cursor_position.y;
-
-                        // Only trigger a single linear event, depending on the largest movement
-                        if delta_x.abs() > 2.0 || delta_y.abs() > 2.0 {
-                            self.process_linear_mouse_movement(delta_x, delta_y);
+                        // While the cursor is locked natively, movement is given by device events instead
+                        if !self.native_cursor_lock {
+                            let delta_x = position.x - self.last_cursor_position.x;
+                            let delta_y = position.y - self.last_cursor_position.y;
+                            self.mouse_moved(delta_x, delta_y);
                         }
 
-                        // Also trigger a vector input
-                        self.vector_input(
-                            VectorInputType::MouseMove,
-                            VectorInputActivation::clamp(
-                                delta_x as f32 * self.mouse_sensitivity,
-                                delta_y as f32 * self.mouse_sensitivity,
-                            ),
-                        );
-
                         self.last_cursor_position = position.cast();
+                        self.data.cursor_position =
+                            Some(self.data.window.physical_to_surface_position(position));
 
-                        // Winit doesn't support cursor locking on a lot of platforms, so do it manually.
-                        let should_lock_cursor = self.input_mode.should_lock_cursor();
+                        // Winit doesn't support cursor locking on a lot of platforms, so there we do it manually.
+                        let should_lock_cursor = !self.native_cursor_lock
+                            && self.focused
+                            && self.input_mode.should_lock_cursor();
                         if should_lock_cursor {
                             let mut center = self.data.window.inner_size();
                             center.width /= 2;
@@ -504,7 +1842,13 @@ impl<T: Game + 'static> GameState<T> {
                         }
                     }
                     WindowEvent::RedrawRequested => {
+                        let now = Clock::now();
+                        self.set_frame_delta(now - self.last_frame_start);
+                        self.last_frame_start = now;
                         self.data.device.poll(wgpu::MaintainBase::Poll);
+                        if self.check_device_lost(window_target) {
+                            return;
+                        }
 
                         self.pre_frame_update();
 
@@ -513,34 +1857,488 @@ impl<T: Game + 'static> GameState<T> {
                             window_target.exit();
                         }
 
+                        // There's nothing to draw to while minimized
+                        if self.minimized {
+                            return;
+                        }
+
+                        self.update();
+
                         let res = self.render();
+                        self.data
+                            .action_values
+                            .clear_one_off::<T::LinearInputType, T::VectorInputType>();
                         match res {
                             Ok(_) => {}
                             Err(wgpu::SurfaceError::Lost) => self.resize(self.data.size),
                             Err(wgpu::SurfaceError::OutOfMemory) => {
                                 window_target.exit();
                             }
-                            // All other errors (Outdated, Timeout) should be resolved by the next frame
+                            // All other errors (Timeout, or Outdated after reconfiguring) should be resolved by the next frame
                             Err(e) => eprintln!("{:?}", e),
                         }
                     }
                     _ => {}
                 }
             }
+            Event::WindowEvent { event, window_id }
+                if self.extra_windows.contains_key(&window_id) =>
+            {
+                self.process_extra_window_event(window_id, event)
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta: (x, y) },
+                ..
+            } if self.native_cursor_lock => self.mouse_moved(x, y),
             Event::DeviceEvent { device_id, event } => {
                 log::debug!("device event: {device_id:?}::{event:?}");
             }
             Event::AboutToWait => {
-                self.window().request_redraw();
+                self.check_device_lost(window_target);
+                self.finish_scale_factor_change();
+                #[cfg(feature = "gamepad")]
+                self.poll_gamepads();
+                #[cfg(target_arch = "wasm32")]
+                self.receive_pastes();
+                #[cfg(target_arch = "wasm32")]
+                self.receive_canvas_sizes();
+                #[cfg(target_arch = "wasm32")]
+                self.receive_drops();
+                self.create_pending_windows(window_target);
+                if let Some(gpu_errors) = &self.data.gpu_errors {
+                    gpu_errors.persist_pending();
+                }
+                self.store_settled_window_geometry();
+                self.schedule_next_frame(window_target);
             }
             _ => {}
         }
     }
 
+    /// Chooses how long the event loop sleeps for, based on the render mode and frame rate limit:
+    /// continuous rendering polls when unlimited, or waits until the next frame is due when limited, while
+    /// on-demand rendering waits for the next event.
+    fn schedule_next_frame(&mut self, window_target: &EventLoopWindowTarget<()>) {
+        // Nothing is drawn while minimized, so sleep until something changes
+        if self.minimized {
+            window_target.set_control_flow(ControlFlow::Wait);
+            return;
+        }
+
+        match (self.config.render_mode, self.frame_rate_limit()) {
+            (RenderMode::Continuous, None) => {
+                window_target.set_control_flow(ControlFlow::Poll);
+                self.request_redraw();
+            }
+            (RenderMode::Continuous, Some(frame_rate_limit)) => {
+                // Limits so small that the next frame would be too far away to represent are never reached
+                let Some(next_frame_start) = Duration::try_from_secs_f32(1.0 / frame_rate_limit)
+                    .ok()
+                    .and_then(|frame_time| self.last_frame_start.checked_add(frame_time))
+                else {
+                    window_target.set_control_flow(ControlFlow::Wait);
+                    return;
+                };
+                if Clock::now() >= next_frame_start {
+                    self.request_redraw();
+                }
+                window_target.set_control_flow(ControlFlow::WaitUntil(next_frame_start));
+            }
+            (RenderMode::OnDemand, _) => {
+                let mut redraw_requested_at = self.data.redraw_requested_at.lock().unwrap();
+                let wake_at = match *redraw_requested_at {
+                    Some(at) if Clock::now() >= at => {
+                        *redraw_requested_at = None;
+                        self.request_redraw();
+                        None
+                    }
+                    at => at,
+                };
+                drop(redraw_requested_at);
+
+                let wake_at = [
+                    wake_at,
+                    self.next_gamepad_poll(),
+                    self.next_window_geometry_store(),
+                ]
+                .into_iter()
+                .flatten()
+                .min();
+                window_target.set_control_flow(match wake_at {
+                    Some(at) => ControlFlow::WaitUntil(at),
+                    None => ControlFlow::Wait,
+                });
+            }
+        }
+    }
+
+    /// When the loop should next wake to poll gamepads, if it would otherwise wait for events.
+    fn next_gamepad_poll(&self) -> Option<web_time::Instant> {
+        #[cfg(feature = "gamepad")]
+        if self.gamepads.is_some() {
+            let interval = if self.data.connected_gamepads.lock().unwrap().is_empty() {
+                GAMEPAD_CONNECTION_POLL_INTERVAL
+            } else {
+                GAMEPAD_POLL_INTERVAL
+            };
+            return Some(Clock::now() + interval);
+        }
+        None
+    }
+
+    /// The frame rate limit when rendering continuously, lowered to [`Game::unfocused_frame_rate`] while the window
+    /// isn't focused.
+    fn frame_rate_limit(&self) -> Option<f32> {
+        let unfocused_frame_rate = if self.focused {
+            None
+        } else {
+            T::unfocused_frame_rate().filter(|rate| *rate > 0.0)
+        };
+        let limit = self.config.frame_rate_limit.filter(|limit| *limit > 0.0);
+        match (limit, unfocused_frame_rate) {
+            (Some(limit), Some(unfocused)) => Some(limit.min(unfocused)),
+            (limit, unfocused) => limit.or(unfocused),
+        }
+    }
+
+    /// Requests a frame for the main window and every extra window.
+    fn request_redraw(&self) {
+        self.window().request_redraw();
+        for extra_window in self.extra_windows.values() {
+            extra_window.window.request_redraw();
+        }
+    }
+
+    fn create_pending_windows(&mut self, window_target: &EventLoopWindowTarget<()>) {
+        let pending_windows = std::mem::take(&mut *self.data.pending_windows.lock().unwrap());
+        for builder in pending_windows {
+            if let Err(err) = self.create_window(builder, window_target) {
+                log::error!("failed to create window: {err}");
+            }
+        }
+    }
+
+    fn create_window(
+        &mut self,
+        builder: WindowBuilder,
+        window_target: &EventLoopWindowTarget<()>,
+    ) -> anyhow::Result<()> {
+        let window = Arc::new(builder.build(window_target)?);
+        let surface = window::create_surface(&self.instance, &window)?;
+
+        // Extra windows are drawn with the same format and presentation as the main window
+        let size = window.inner_size();
+        let mut config = self.surface.target_config().clone();
+        config.width = size.width.max(1);
+        config.height = size.height.max(1);
+        let surface =
+            surface::ResizableSurface::new(surface, &self.data.adapter, &self.data.device, config);
+
+        self.extra_windows.insert(
+            window.id(),
+            ExtraWindow {
+                window: Arc::clone(&window),
+                surface,
+            },
+        );
+        self.game.window_created(&self.data, window);
+
+        Ok(())
+    }
+
+    fn process_extra_window_event(&mut self, window_id: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => self
+                .game
+                .extra_window_close_requested(&self.data, window_id),
+            WindowEvent::Destroyed => {
+                self.extra_windows.remove(&window_id);
+            }
+            WindowEvent::Resized(new_size) if new_size.width > 0 && new_size.height > 0 => {
+                if let Some(extra_window) = self.extra_windows.get_mut(&window_id) {
+                    extra_window.surface.resize(new_size, &self.data.queue);
+                }
+                self.game
+                    .extra_window_resize(&self.data, window_id, new_size);
+            }
+            WindowEvent::RedrawRequested => self.render_extra_window(window_id),
+            event => self.game.extra_window_event(&self.data, window_id, event),
+        }
+    }
+
+    fn render_extra_window(&mut self, window_id: WindowId) {
+        if self.device_lost {
+            return;
+        }
+        let Some(extra_window) = self.extra_windows.get_mut(&window_id) else {
+            return;
+        };
+        // If we are in the process of resizing, don't do anything
+        let Some(output) = extra_window.surface.get_current_texture(&self.data.device) else {
+            return;
+        };
+        let output = match output {
+            Ok(output) => output,
+            Err(wgpu::SurfaceError::Lost) => {
+                let size = extra_window.window.inner_size();
+                extra_window.surface.resize(size, &self.data.queue);
+                return;
+            }
+            Err(err) => {
+                log::error!("failed to get extra window surface: {err:?}");
+                return;
+            }
+        };
+
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        match self
+            .game
+            .render_to_extra_window(&self.data, window_id, view)
+        {
+            Ok(()) => {
+                if let Some(extra_window) = self.extra_windows.get(&window_id) {
+                    extra_window.window.pre_present_notify();
+                }
+                output.present();
+            }
+            // Dropping the frame without presenting it discards whatever was partially drawn
+            Err(err) => self.game.on_render_error(&self.data, err),
+        }
+    }
+
     pub fn window(&self) -> &Window {
         &self.data.window
     }
 
+    #[cfg(feature = "gamepad")]
+    fn poll_gamepads(&mut self) {
+        let Some(gamepads) = &mut self.gamepads else {
+            return;
+        };
+        let changed = gamepads.poll();
+        *self.data.connected_gamepads.lock().unwrap() = gamepads.connected();
+        if !changed.is_empty() && self.config.render_mode == RenderMode::OnDemand {
+            self.request_redraw();
+        }
+        for input in changed {
+            match input {
+                gamepad::GamepadInput::Stick {
+                    gamepad,
+                    stick,
+                    position: [x, y],
+                } => {
+                    self.data.input_gamepad = Some(gamepad);
+                    self.vector_input(
+                        VectorInputType::GamepadStick(stick),
+                        VectorInputActivation::clamp(x, y),
+                    );
+                }
+                gamepad::GamepadInput::Button {
+                    gamepad,
+                    button,
+                    value,
+                } => {
+                    self.data.input_gamepad = Some(gamepad);
+                    self.linear_input(
+                        input::LinearInputType::GamepadButton(button),
+                        input::LinearInputActivation::clamp(value),
+                    );
+                }
+            }
+        }
+        self.data.input_gamepad = None;
+    }
+
+    /// Pastes are read from the clipboard when the paste shortcut is pressed.
+    #[cfg(all(not(target_arch = "wasm32"), feature = "clipboard"))]
+    fn check_for_paste(&mut self, event: &winit::event::KeyEvent) {
+        let shortcut_modifier = if cfg!(target_os = "macos") {
+            self.modifiers.super_key()
+        } else {
+            self.modifiers.control_key()
+        };
+        if !shortcut_modifier
+            || event.state != winit::event::ElementState::Pressed
+            || event.physical_key != PhysicalKey::Code(winit::keyboard::KeyCode::KeyV)
+        {
+            return;
+        }
+
+        for pasted in crate::clipboard::read_paste() {
+            self.game.clipboard_pasted(&self.data, pasted);
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn receive_pastes(&mut self) {
+        while let Ok(pasted) = self.paste_receiver.try_recv() {
+            self.game.clipboard_pasted(&self.data, pasted);
+        }
+    }
+
+    fn end_drag_hover(&mut self) {
+        if std::mem::take(&mut self.drag_hovering) {
+            self.game.drag_hover_ended(&self.data);
+        }
+    }
+
+    fn file_dropped(&mut self, file: DroppedFile) {
+        let accepted = self.game.accepts_dropped_file(file.path())
+            && file
+                .mime_type()
+                .is_none_or(|mime_type| self.game.accepts_dropped_mime_type(mime_type));
+        if accepted {
+            self.game.file_dropped(&self.data, file);
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn receive_drops(&mut self) {
+        use crate::file_drop::DropEvent;
+
+        while let Ok(event) = self.drop_receiver.try_recv() {
+            match event {
+                DropEvent::Hovered if !self.drag_hovering => {
+                    self.drag_hovering = true;
+                    self.game.drag_hover(&self.data, true);
+                }
+                DropEvent::Hovered => {}
+                DropEvent::Left => self.end_drag_hover(),
+                DropEvent::Dropped(file) => self.file_dropped(file),
+            }
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn receive_canvas_sizes(&mut self) {
+        let Some(size) = self
+            .canvas_size_receiver
+            .as_ref()
+            .and_then(|receiver| receiver.try_iter().last())
+        else {
+            return;
+        };
+        if size == self.data.size || size.width == 0 || size.height == 0 {
+            return;
+        }
+
+        log::debug!("Canvas resized: {:?}", size);
+        let canvas = self.data.window.canvas();
+        canvas.set_width(size.width);
+        canvas.set_height(size.height);
+        self.resize(size);
+    }
+
+    fn begin_stick_calibration(&mut self) {
+        #[cfg(feature = "gamepad")]
+        if let Some(gamepads) = &mut self.gamepads {
+            gamepads.begin_calibration();
+        }
+    }
+
+    fn end_stick_calibration(&mut self) {
+        #[cfg(feature = "gamepad")]
+        if let Some(gamepads) = &mut self.gamepads {
+            gamepads.end_calibration();
+        }
+    }
+
+    fn set_gamepad_rumble(
+        &mut self,
+        gamepad: input::GamepadId,
+        strong: f32,
+        weak: f32,
+        duration: Duration,
+    ) {
+        #[cfg(feature = "gamepad")]
+        if let Some(gamepads) = &mut self.gamepads {
+            gamepads.set_rumble(gamepad, strong, weak, duration);
+        }
+        #[cfg(not(feature = "gamepad"))]
+        let _ = (gamepad, strong, weak, duration);
+    }
+
+    /// Input is only captured while the window is focused, so that the game doesn't keep reacting to input
+    /// meant for other windows.
+    fn should_handle_input(&self) -> bool {
+        self.focused && self.input_mode.should_handle_input()
+    }
+
+    /// The cursor is only hidden while the window is focused, so that it is released to the OS when the user
+    /// switches to another window.
+    fn update_cursor_visibility(&self) {
+        let should_show_cursor = !(self.focused && self.input_mode.should_hide_cursor());
+        self.data.window.set_cursor_visible(should_show_cursor);
+    }
+
+    fn update_cursor_grab(&mut self) {
+        // Where the platform can lock the cursor we let it, and otherwise recenter the cursor ourselves
+        self.native_cursor_lock = false;
+        if self.focused && self.input_mode.should_lock_cursor() {
+            match self.data.window.set_cursor_grab(CursorGrabMode::Locked) {
+                Ok(()) => self.native_cursor_lock = true,
+                Err(err) => log::debug!("cursor can't be locked, so will be recentered: {err}"),
+            }
+        }
+        if self.native_cursor_lock {
+            self.data.cursor_grab_active = true;
+            return;
+        }
+
+        let grab_mode = if self.focused && self.cursor_confined {
+            CursorGrabMode::Confined
+        } else {
+            CursorGrabMode::None
+        };
+        let res = self.data.window.set_cursor_grab(grab_mode);
+        self.data.cursor_grab_active = grab_mode != CursorGrabMode::None && res.is_ok();
+        if let Err(err) = res {
+            if grab_mode == CursorGrabMode::None {
+                log::debug!("failed to release cursor grab: {err}");
+            } else {
+                log::warn!("failed to set cursor grab mode to {grab_mode:?}: {err}");
+            }
+        }
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        if self.focused != focused {
+            log::debug!("Focused: {:?}", focused);
+            self.focused = focused;
+            self.update_cursor_visibility();
+            self.update_cursor_grab();
+            if !self.focused {
+                self.release_held_inputs(self.input_mode);
+            }
+            self.game.focus_changed(&self.data, focused);
+        }
+    }
+
+    fn finish_scale_factor_change(&mut self) {
+        let Some(old_scale_factor) = self.pending_scale_factor_change.take() else {
+            return;
+        };
+
+        // Not every platform sends a resize after the scale factor changes
+        let size = self.data.window.inner_size();
+        if size != self.data.size {
+            self.resize(size);
+        }
+
+        self.game
+            .scale_factor_changed(&self.data, old_scale_factor, self.data.scale_factor);
+    }
+
+    fn set_minimized(&mut self, minimized: bool) {
+        if self.minimized != minimized {
+            log::debug!("Minimized: {:?}", minimized);
+            self.minimized = minimized;
+            self.game.minimized_changed(&self.data, minimized);
+        }
+    }
+
     fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.data.size = new_size;
@@ -551,6 +2349,54 @@ impl<T: Game + 'static> GameState<T> {
         }
     }
 
+    fn mouse_moved(&mut self, delta_x: f64, delta_y: f64) {
+        let scale = match T::pointer_delta_space() {
+            PointerDeltaSpace::Physical => 1.0,
+            PointerDeltaSpace::Logical => self.data.scale_factor.recip(),
+        };
+        self.game
+            .handle_raw_pointer_delta(&self.data, delta_x * scale, delta_y * scale);
+
+        // Only trigger a single linear event, depending on the largest movement
+        if delta_x.abs() > 2.0 || delta_y.abs() > 2.0 {
+            self.process_linear_mouse_movement(delta_x, delta_y);
+        }
+
+        // Also trigger a vector input
+        self.vector_input(
+            VectorInputType::MouseMove,
+            VectorInputActivation::clamp(
+                delta_x as f32 * self.mouse_sensitivity,
+                delta_y as f32 * self.mouse_sensitivity,
+            ),
+        );
+    }
+
+    /// Gives vertical scrolling as linear input. Some platforms scroll by lines, such as with a mouse wheel, and
+    /// others by pixels, such as with a touchpad, so pixels are converted to lines first, with a line being
+    /// [`SCROLL_LINE_HEIGHT`] logical pixels.
+    fn mouse_scrolled(&mut self, delta: winit::event::MouseScrollDelta) {
+        let lines = match delta {
+            winit::event::MouseScrollDelta::LineDelta(_, y) => y as f64,
+            winit::event::MouseScrollDelta::PixelDelta(position) => {
+                position.y / self.data.scale_factor / SCROLL_LINE_HEIGHT
+            }
+        };
+        if lines == 0.0 {
+            return;
+        }
+
+        let direction = if lines > 0.0 {
+            MouseInputType::ScrollUp
+        } else {
+            MouseInputType::ScrollDown
+        };
+        self.linear_input(
+            input::LinearInputType::Mouse(direction),
+            input::LinearInputActivation::clamp(lines.abs() as f32 * self.scroll_sensitivity),
+        );
+    }
+
     fn process_linear_mouse_movement(&mut self, delta_x: f64, delta_y: f64) {
         if delta_x.abs() > delta_y.abs() {
             if delta_x > 0.0 {
@@ -584,31 +2430,205 @@ impl<T: Game + 'static> GameState<T> {
         inputted: input::LinearInputType,
         activation: input::LinearInputActivation,
     ) {
-        if !self.input_mode.should_handle_input() {
+        let activation = self.input_map.shape_linear(inputted, activation);
+        if !self.track_linear_input(inputted, activation) {
             return;
         }
-        let input_value = self.input_map.get_linear(inputted);
-        if let Some(input_value) = input_value {
+        self.handle_bound_linear_input(self.input_mode, inputted, activation);
+    }
+
+    fn synthetic_linear_input(
+        &mut self,
+        inputted: input::LinearInputType,
+        activation: input::LinearInputActivation,
+    ) {
+        let activation = self.input_map.shape_linear(inputted, activation);
+        if !self.track_linear_input(inputted, activation) {
+            return;
+        }
+        for input_value in self.input_map.get_all_linear_in(self.input_mode, inputted) {
+            self.data.action_values.set_linear(
+                input_value,
+                inputted,
+                self.data.input_gamepad,
+                activation,
+            );
+            self.game
+                .handle_synthetic_linear_input(&self.data, input_value, activation)
+        }
+    }
+
+    /// Gives the activation to the game as each action that the input is bound to in `mode`.
+    fn handle_bound_linear_input(
+        &mut self,
+        mode: InputMode,
+        inputted: input::LinearInputType,
+        activation: input::LinearInputActivation,
+    ) {
+        for input_value in self.input_map.get_all_linear_in(mode, inputted) {
+            self.data.action_values.set_linear(
+                input_value,
+                inputted,
+                self.data.input_gamepad,
+                activation,
+            );
             self.game
                 .handle_linear_input(&self.data, input_value, activation)
         }
     }
 
+    /// Records whether the input is held, giving whether it should be passed on to the game.
+    fn track_linear_input(
+        &mut self,
+        inputted: input::LinearInputType,
+        activation: input::LinearInputActivation,
+    ) -> bool {
+        if !self.focused || !self.input_map.handles_linear_in(self.input_mode, inputted) {
+            return false;
+        }
+        // One-off inputs, such as scrolling, are never released, so mustn't be pressed again as though held when
+        // the mode or input map changes
+        if inputted.is_one_off() {
+            return true;
+        }
+        let held = (inputted, self.data.input_gamepad);
+        if activation.get() > 0.0 {
+            self.held_linear_inputs.insert(held, activation);
+        } else {
+            self.held_linear_inputs.remove(&held);
+        }
+        true
+    }
+
+    /// Gives a held input to the game again, as coming from the gamepad it was held on.
+    fn handle_held_linear_input(
+        &mut self,
+        mode: InputMode,
+        (inputted, gamepad): (input::LinearInputType, Option<input::GamepadId>),
+        activation: input::LinearInputActivation,
+    ) {
+        let input_gamepad = std::mem::replace(&mut self.data.input_gamepad, gamepad);
+        self.handle_bound_linear_input(mode, inputted, activation);
+        self.data.input_gamepad = input_gamepad;
+    }
+
+    /// Once input stops being handled, releases of held inputs would never reach the game, so we release them
+    /// all now to let the game settle. The inputs are released as the actions they were mapped to in `mode`, the
+    /// mode they were pressed in.
+    fn release_held_inputs(&mut self, mode: InputMode) {
+        let released = input::LinearInputActivation::try_from(0.0).expect("from const");
+        for held in std::mem::take(&mut self.held_linear_inputs).into_keys() {
+            self.handle_held_linear_input(mode, held, released);
+        }
+    }
+
+    /// Held inputs whose action differs between the previous mode and the current one are released as the old
+    /// action and pressed as the new one, so that no action is left held once the mode changes.
+    fn remap_held_inputs(&mut self, previous_mode: InputMode) {
+        let released = input::LinearInputActivation::try_from(0.0).expect("from const");
+        for (held, activation) in self.held_linear_inputs.clone() {
+            let previous_values = self.input_map.get_all_linear_in(previous_mode, held.0);
+            let input_values = self.input_map.get_all_linear_in(self.input_mode, held.0);
+            if previous_values.eq(input_values) {
+                continue;
+            }
+            self.handle_held_linear_input(previous_mode, held, released);
+            self.handle_held_linear_input(self.input_mode, held, activation);
+        }
+    }
+
+    fn set_text_input_active(&mut self, active: bool, cursor_area: Rect) {
+        if active {
+            self.data
+                .window
+                .set_ime_cursor_area(cursor_area.position, cursor_area.size);
+        }
+        if active == self.data.text_input_active {
+            return;
+        }
+        self.data.text_input_active = active;
+        self.data.window.set_ime_allowed(active);
+
+        if active {
+            // Keys released while typing won't reach the input map, so are released now
+            let released = input::LinearInputActivation::try_from(0.0).expect("from const");
+            let held_keys = self
+                .held_linear_inputs
+                .keys()
+                .filter(|(inputted, _)| {
+                    matches!(inputted, input::LinearInputType::KnownKeyboard(_))
+                })
+                .copied()
+                .collect::<Vec<_>>();
+            for held in held_keys {
+                self.held_linear_inputs.remove(&held);
+                self.handle_held_linear_input(self.input_mode, held, released);
+            }
+        }
+    }
+
+    fn set_input_map(&mut self, map: Box<dyn std::any::Any + Send>, press_held: bool) {
+        let Ok(map) = map.downcast::<InputMap<T::LinearInputType, T::VectorInputType>>() else {
+            log::error!(
+                "input map given to `GameData::set_input_map` is not for this game's input types"
+            );
+            return;
+        };
+
+        let released = input::LinearInputActivation::try_from(0.0).expect("from const");
+        for held in self.held_linear_inputs.keys().copied().collect::<Vec<_>>() {
+            self.handle_held_linear_input(self.input_mode, held, released);
+        }
+
+        self.input_map = *map;
+        if let Some(input_storage) = &self.input_storage {
+            input_storage.store(&self.input_map);
+        }
+
+        if !press_held {
+            // The old presses have been released, so the keys are only held again once pressed again
+            self.held_linear_inputs.clear();
+            return;
+        }
+        for (held, activation) in self.held_linear_inputs.clone() {
+            self.handle_held_linear_input(self.input_mode, held, activation);
+        }
+    }
+
     fn vector_input(
         &mut self,
         inputted: input::VectorInputType,
         activation: input::VectorInputActivation,
     ) {
-        if !self.input_mode.should_handle_input() {
+        if !self.focused || !self.input_map.handles_vector_in(self.input_mode, inputted) {
             return;
         }
-        let input_value = self.input_map.get_vector(inputted);
+        let activation = self.input_map.shape_vector(inputted, activation);
+        let input_value = self.input_map.get_vector_in(self.input_mode, inputted);
         if let Some(input_value) = input_value {
+            self.data.action_values.set_vector(
+                input_value,
+                inputted,
+                self.data.input_gamepad,
+                activation,
+            );
             self.game
                 .handle_vector_input(&self.data, input_value, activation)
         }
     }
 
+    /// Gives whether the device has been lost, telling the game and exiting when first noticed.
+    fn check_device_lost(&mut self, window_target: &EventLoopWindowTarget<()>) -> bool {
+        if let Ok((reason, message)) = self.device_lost_receiver.try_recv() {
+            log::error!("device lost: {message}");
+            self.device_lost = true;
+            self.game.device_lost(&self.data, reason, &message);
+            self.data.exit_flag.set();
+            window_target.exit();
+        }
+        self.device_lost
+    }
+
     fn request_exit(&mut self) {
         self.data.exit_flag.set();
         self.game.user_exit_requested(&self.data);
@@ -620,32 +2640,203 @@ impl<T: Game + 'static> GameState<T> {
             match cmd {
                 GameCommand::Exit => self.data.exit_flag.set(),
                 GameCommand::SetInputMode(input_mode) => {
-                    self.input_mode = input_mode;
-
-                    let should_show_cursor = !input_mode.should_hide_cursor();
-                    self.data.window.set_cursor_visible(should_show_cursor);
+                    let previous_mode = std::mem::replace(&mut self.input_mode, input_mode);
+
+                    self.update_cursor_visibility();
+                    self.update_cursor_grab();
+                    if !self.should_handle_input() {
+                        self.release_held_inputs(previous_mode);
+                    } else {
+                        self.remap_held_inputs(previous_mode);
+                    }
                 }
                 GameCommand::SetMouseSensitivity(new_sensitivity) => {
                     self.mouse_sensitivity = new_sensitivity;
                 }
+                GameCommand::SetScrollSensitivity(new_sensitivity) => {
+                    self.scroll_sensitivity = new_sensitivity;
+                }
+                GameCommand::SetInputMap { map, press_held } => self.set_input_map(map, press_held),
+                GameCommand::SetPaused(paused) => self.data.paused = paused,
+                GameCommand::SetTextInputActive {
+                    active,
+                    cursor_area,
+                } => self.set_text_input_active(active, cursor_area),
+                #[cfg(all(feature = "renderdoc", not(target_arch = "wasm32")))]
+                GameCommand::CaptureGpuFrame => self.request_frame_capture(),
+                #[cfg(not(feature = "renderdoc"))]
+                GameCommand::CaptureGpuFrame => {
+                    log::warn!("capturing gpu frames requires the `renderdoc` feature")
+                }
+                #[cfg(all(feature = "renderdoc", target_arch = "wasm32"))]
+                GameCommand::CaptureGpuFrame => {
+                    log::warn!("capturing gpu frames isn't supported on web")
+                }
+                GameCommand::SetSurfaceUsage(usage) => {
+                    let mut config = self.surface.target_config().clone();
+                    config.usage = usage | wgpu::TextureUsages::RENDER_ATTACHMENT;
+                    if let Err(err) = self.surface.request_config_change(config, &self.data.queue) {
+                        log::error!("failed to change surface usage: {err}");
+                    }
+                }
+                GameCommand::SetCursorConfine(confined) => {
+                    self.cursor_confined = confined;
+                    self.update_cursor_grab();
+                }
+                GameCommand::SetFullscreenOn { monitor, mode } => {
+                    self.data.window.set_fullscreen_on(&monitor, mode.as_ref())
+                }
+                GameCommand::BeginStickCalibration => self.begin_stick_calibration(),
+                GameCommand::EndStickCalibration => self.end_stick_calibration(),
+                GameCommand::SetGamepadRumble {
+                    gamepad,
+                    strong,
+                    weak,
+                    duration,
+                } => self.set_gamepad_rumble(gamepad, strong, weak, duration),
+                GameCommand::CloseWindow(window_id) => {
+                    // Dropping the window closes it
+                    self.extra_windows.remove(&window_id);
+                }
+            }
+        }
+    }
+
+    fn set_frame_delta(&mut self, frame_delta: Duration) {
+        // Time stands still while paused
+        let frame_delta = if self.data.paused {
+            Duration::ZERO
+        } else {
+            frame_delta
+        };
+        let max_frame_delta = T::max_frame_delta();
+        self.data.frame_delta = frame_delta.min(max_frame_delta);
+        self.data.frame_delta_clamped = frame_delta > max_frame_delta;
+    }
+
+    fn update(&mut self) {
+        if self.data.paused {
+            return;
+        }
+
+        if let Some(timestep) = T::fixed_timestep() {
+            self.fixed_update_accumulator += self.data.frame_delta;
+
+            let mut updates = 0;
+            while self.fixed_update_accumulator >= timestep {
+                if updates == MAX_FIXED_UPDATES_PER_FRAME {
+                    self.fixed_update_accumulator = Duration::ZERO;
+                    break;
+                }
+                self.fixed_update_accumulator -= timestep;
+                self.game.fixed_update(&self.data, timestep);
+                updates += 1;
+            }
+        }
+
+        self.game.update(&self.data);
+    }
+
+    /// Captures the next frame in RenderDoc, or logs a warning if the game isn't running under RenderDoc.
+    #[cfg(all(feature = "renderdoc", not(target_arch = "wasm32")))]
+    fn request_frame_capture(&mut self) {
+        if self.renderdoc.is_none() {
+            match renderdoc::RenderDoc::new() {
+                Ok(renderdoc) => self.renderdoc = Some(renderdoc),
+                Err(err) => {
+                    log::warn!(
+                        "not capturing gpu frame, as the game isn't running under RenderDoc: {err}"
+                    );
+                    return;
+                }
             }
         }
+        self.capture_next_frame = true;
     }
 
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        #[cfg(all(feature = "renderdoc", not(target_arch = "wasm32")))]
+        if std::mem::take(&mut self.capture_next_frame) {
+            if let Some(renderdoc) = &mut self.renderdoc {
+                log::info!("capturing gpu frame {}", self.frame_index);
+                // Null handles capture from whichever device and window RenderDoc is attached to
+                renderdoc.start_frame_capture(std::ptr::null(), std::ptr::null());
+                let res = self.render_frame();
+                if let Some(renderdoc) = &mut self.renderdoc {
+                    renderdoc.end_frame_capture(std::ptr::null(), std::ptr::null());
+                }
+                return res;
+            }
+        }
+
+        self.render_frame()
+    }
+
+    fn render_frame(&mut self) -> Result<(), wgpu::SurfaceError> {
         // If we are in the process of resizing, don't do anything
-        if let Some(surface) = self.surface.get(&self.data.device) {
+        if let Some(output) = self.surface.get_current_texture(&self.data.device) {
             let was_suboptimal = {
-                let output = surface.get_current_texture()?;
-                let view = output
-                    .texture
-                    .create_view(&wgpu::TextureViewDescriptor::default());
+                let output = output?;
 
-                self.game.render_to(&self.data, view);
+                let loading = !self.data.loading_progress.is_complete();
+                if self.loading && !loading {
+                    self.game.loading_finished(&self.data);
+                }
+                self.loading = loading;
+
+                // Loading frames are always drawn straight to the surface
+                self.data.render_size = if loading {
+                    self.data.size
+                } else {
+                    let stats = self.dynamic_resolution.stats();
+                    let scale = self.game.adjust_quality(&self.data, &stats);
+                    self.dynamic_resolution.request_scale(scale);
+                    self.dynamic_resolution.render_size(self.data.size)
+                };
+
+                self.frame_budget
+                    .begin_frame(&self.data.device, &self.data.queue);
+                let render_start = Clock::now();
+                let res = if loading {
+                    let progress = self.data.loading_progress.get();
+                    let view = output
+                        .texture
+                        .create_view(&wgpu::TextureViewDescriptor::default());
+                    self.game.loading_frame(&self.data, view, progress)
+                } else if self.data.render_size != self.data.size {
+                    self.render_scaled(&output)
+                } else {
+                    self.game.render_to_surface_texture(&self.data, &output)
+                };
+
+                let now = Clock::now();
+                let cpu_time = now - render_start;
+                self.frame_budget
+                    .end_frame(&self.data.device, &self.data.queue);
+                // Only the time spent on the frame, so that waiting for vsync or a frame rate limit doesn't
+                // look like the frame is too slow
+                self.dynamic_resolution
+                    .record_frame_time(cpu_time + self.frame_budget.gpu_time().unwrap_or_default());
+                if let Some(budget) = T::frame_budget() {
+                    let report = self.frame_budget.record(budget, cpu_time, now);
+                    if let Some(report) = report {
+                        self.game.frame_budget_exceeded(&self.data, report);
+                    }
+                }
 
                 let was_suboptimal = output.suboptimal;
 
-                output.present();
+                match res {
+                    Ok(()) => {
+                        // Lets the windowing system pace frames and measure latency
+                        self.data.window.pre_present_notify();
+                        output.present();
+                        self.game.frame_presented(&self.data, self.frame_index);
+                        self.frame_index += 1;
+                    }
+                    // Dropping the frame without presenting it discards whatever was partially drawn
+                    Err(err) => self.game.on_render_error(&self.data, err),
+                }
 
                 was_suboptimal
             };
@@ -658,7 +2849,79 @@ impl<T: Game + 'static> GameState<T> {
         Ok(())
     }
 
+    /// Renders the frame at [`GameData::render_size`], then upscales it onto the surface.
+    fn render_scaled(&mut self, output: &wgpu::SurfaceTexture) -> anyhow::Result<()> {
+        let view = self.dynamic_resolution.target_view(
+            &self.data.device,
+            self.data.surface_format,
+            self.data.render_size,
+        );
+        self.game.render_to(&self.data, view)?;
+
+        let output_view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        self.dynamic_resolution.upscale(
+            &self.data.device,
+            &self.data.queue,
+            self.data.surface_format,
+            &output_view,
+        );
+        Ok(())
+    }
+
+    fn update_window_geometry(&mut self) {
+        if let Some(geometry) = &mut self.window_geometry {
+            geometry.update(&self.data.window);
+            self.window_geometry_changed_at = Some(Clock::now());
+        }
+    }
+
+    /// When the window's geometry should next be stored, if it has changed since it was last stored.
+    fn next_window_geometry_store(&self) -> Option<web_time::Instant> {
+        self.window_geometry_changed_at
+            .map(|changed_at| changed_at + WINDOW_GEOMETRY_STORE_DELAY)
+    }
+
+    fn store_settled_window_geometry(&mut self) {
+        if self
+            .next_window_geometry_store()
+            .is_some_and(|at| Clock::now() >= at)
+        {
+            self.window_geometry_changed_at = None;
+            if let Some(geometry) = &self.window_geometry {
+                geometry.store();
+            }
+        }
+    }
+
     fn finished(self) {
-        self.game.finished(self.data)
+        if let Some(geometry) = &self.window_geometry {
+            geometry.store();
+        }
+        if let Some(gpu_errors) = &self.data.gpu_errors {
+            gpu_errors.persist_all();
+        }
+
+        // The loop can also exit without being asked to, for example if the GPU runs out of memory, so make sure
+        // spawned tasks know to finish
+        self.data.exit_flag.set();
+        #[cfg(not(target_arch = "wasm32"))]
+        let tasks = std::mem::take(&mut *self.data.tasks.lock().unwrap());
+
+        // Surfaces are torn down before their windows, which may be dropped by the game when it finishes
+        drop(self.extra_windows);
+        drop(self.surface);
+        #[cfg(target_arch = "wasm32")]
+        crate::wasm::shutdown();
+
+        self.game.finished(self.data);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        for task in tasks {
+            if task.join().is_err() {
+                log::error!("a spawned task panicked");
+            }
+        }
     }
 }
@@ -0,0 +1,220 @@
+//! Renders the game at a fraction of the surface's resolution when it can't keep up, as chosen by
+//! [`super::Game::adjust_quality`], then upscales each frame onto the surface.
+
+use std::{borrow::Cow, time::Duration};
+
+use crate::{
+    FragmentOnlyColorAttachment, FragmentOnlyRenderPassDescriptor, FragmentOnlyRenderPipeline,
+    FragmentOnlyRenderPipelineDescriptor, LfCommandEncoderExt, LfDeviceExt,
+};
+
+/// The smallest render scale allowed, below which frames are too blurry to be worth drawing.
+const MIN_RENDER_SCALE: f32 = 0.25;
+/// How far the render scale moves towards the requested scale each frame.
+const RENDER_SCALE_SMOOTHING: f32 = 0.1;
+/// Render sizes are picked from scales in steps of this, so that the render target isn't remade every frame
+/// while the scale is moving.
+const RENDER_SCALE_STEP: f32 = 0.05;
+/// How much each frame's time contributes to the average frame time.
+const FRAME_TIME_SMOOTHING: f64 = 0.1;
+
+/// Timings of recent frames, given to [`super::Game::adjust_quality`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameStats {
+    /// The time spent drawing the previous frame: the time spent on the CPU in the game's render callbacks,
+    /// including upscaling, plus the time the GPU spent on the most recent frame it has finished if the device has
+    /// [`wgpu::Features::TIMESTAMP_QUERY`]. Time spent waiting between frames, such as for vsync, a frame rate
+    /// limit, or while the window is unfocused, isn't included.
+    pub frame_time: Duration,
+    /// An exponential moving average of the frame time, which changes slowly enough to base quality decisions on.
+    pub average_frame_time: Duration,
+    /// The current fraction of the surface's resolution that frames are rendered at, in (0, 1].
+    pub render_scale: f32,
+}
+
+pub(super) struct DynamicResolution {
+    frame_time: Duration,
+    average_frame_time: Option<Duration>,
+    scale: f32,
+    target: Option<wgpu::Texture>,
+    blit: Option<Blit>,
+}
+
+struct Blit {
+    pipeline: FragmentOnlyRenderPipeline,
+    sampler: wgpu::Sampler,
+}
+
+impl DynamicResolution {
+    pub(super) fn new() -> Self {
+        Self {
+            frame_time: Duration::ZERO,
+            average_frame_time: None,
+            scale: 1.0,
+            target: None,
+            blit: None,
+        }
+    }
+
+    pub(super) fn record_frame_time(&mut self, frame_time: Duration) {
+        self.frame_time = frame_time;
+        self.average_frame_time = Some(match self.average_frame_time {
+            None => frame_time,
+            Some(average) => {
+                average.mul_f64(1.0 - FRAME_TIME_SMOOTHING)
+                    + frame_time.mul_f64(FRAME_TIME_SMOOTHING)
+            }
+        });
+    }
+
+    pub(super) fn stats(&self) -> FrameStats {
+        FrameStats {
+            frame_time: self.frame_time,
+            average_frame_time: self.average_frame_time.unwrap_or(self.frame_time),
+            render_scale: self.scale,
+        }
+    }
+
+    /// Moves the render scale towards the one requested, or straight back to full resolution if none is.
+    pub(super) fn request_scale(&mut self, requested: Option<f32>) {
+        let Some(requested) = requested.filter(|scale| scale.is_finite()) else {
+            self.scale = 1.0;
+            return;
+        };
+        let requested = requested.clamp(MIN_RENDER_SCALE, 1.0);
+        self.scale += (requested - self.scale) * RENDER_SCALE_SMOOTHING;
+        // Otherwise the smoothing only ever gets close to the ends
+        if (requested - self.scale).abs() < RENDER_SCALE_STEP / 2.0 {
+            self.scale = requested;
+        }
+    }
+
+    /// The size that frames should be rendered at, for a surface of the given size.
+    pub(super) fn render_size(
+        &self,
+        surface_size: winit::dpi::PhysicalSize<u32>,
+    ) -> winit::dpi::PhysicalSize<u32> {
+        let scale = (self.scale / RENDER_SCALE_STEP).round() * RENDER_SCALE_STEP;
+        if scale >= 1.0 {
+            return surface_size;
+        }
+        winit::dpi::PhysicalSize::new(
+            ((surface_size.width as f32 * scale) as u32).max(1),
+            ((surface_size.height as f32 * scale) as u32).max(1),
+        )
+    }
+
+    /// A view of a texture of the given size to render the frame to, before upscaling it with
+    /// [`DynamicResolution::upscale`].
+    pub(super) fn target_view(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> wgpu::TextureView {
+        let target = match &mut self.target {
+            Some(target) if target.width() == size.width && target.height() == size.height => {
+                target
+            }
+            target => target.insert(device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("dynamic resolution target"),
+                size: wgpu::Extent3d {
+                    width: size.width,
+                    height: size.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })),
+        };
+        target.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    /// Draws the frame rendered to the target onto the surface.
+    pub(super) fn upscale(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        output: &wgpu::TextureView,
+    ) {
+        let Some(target) = &self.target else {
+            return;
+        };
+        let blit = self.blit.get_or_insert_with(|| Blit::new(device, format));
+
+        let source = target.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("dynamic resolution upscale"),
+            layout: &blit.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&blit.sampler),
+                },
+            ],
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("dynamic resolution upscale"),
+        });
+        {
+            let mut pass =
+                encoder.begin_fragment_only_render_pass(&FragmentOnlyRenderPassDescriptor {
+                    label: Some("dynamic resolution upscale"),
+                    color_attachments: &[Some(FragmentOnlyColorAttachment::clear(
+                        output,
+                        wgpu::Color::BLACK,
+                    ))],
+                    stencil_attachment: None,
+                    timestamp_writes: None,
+                });
+            pass.set_pipeline(&blit.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.draw();
+        }
+        queue.submit([encoder.finish()]);
+    }
+}
+
+impl Blit {
+    fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("dynamic resolution upscale shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::from(include_str!("../shaders/blit.wgsl"))),
+        });
+        let pipeline =
+            device.create_fragment_only_render_pipeline(&FragmentOnlyRenderPipelineDescriptor {
+                label: Some("dynamic resolution upscale"),
+                layout: None,
+                multisample: wgpu::MultisampleState::default(),
+                fragment: wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                },
+                multiview: None,
+            });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("dynamic resolution upscale"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self { pipeline, sampler }
+    }
+}
@@ -0,0 +1,83 @@
+/// The features which were asked of the adapter when the device was created, and which of them were given.
+/// See [`super::GameData::feature_report`].
+#[derive(Debug, Clone, Default)]
+pub struct FeatureReport {
+    /// Every feature asked for, from [`super::Game::required_features`], [`super::Game::optional_features`], and
+    /// the features this crate enables whenever the adapter has them.
+    pub requested: wgpu::Features,
+    /// The features the device was created with.
+    pub granted: wgpu::Features,
+    /// The requested features which the adapter doesn't support, and so weren't given.
+    pub denied: wgpu::Features,
+    /// Why each feature that the game didn't ask for was enabled, such as mappable primary buffers on
+    /// integrated GPUs.
+    pub reasons: Vec<(wgpu::Features, String)>,
+}
+
+impl FeatureReport {
+    /// Works out which features to create a device with, given those the game requires and would like. Fails if
+    /// any required feature is missing from the adapter.
+    pub(crate) fn negotiate(
+        adapter: &wgpu::Adapter,
+        required: wgpu::Features,
+        optional: wgpu::Features,
+    ) -> anyhow::Result<Self> {
+        let available = adapter.features();
+
+        let missing = required.difference(available);
+        if !missing.is_empty() {
+            anyhow::bail!("adapter is missing required features: {missing:?}");
+        }
+
+        let mut report = Self {
+            requested: required | optional,
+            ..Self::default()
+        };
+
+        // Assume integrated and virtual GPUs, and CPUs, are UMA
+        let device_type = adapter.get_info().device_type;
+        if matches!(
+            device_type,
+            wgpu::DeviceType::IntegratedGpu | wgpu::DeviceType::Cpu | wgpu::DeviceType::VirtualGpu
+        ) {
+            report.opportunistic(
+                available,
+                wgpu::Features::MAPPABLE_PRIMARY_BUFFERS,
+                format!("mappable primary buffers enabled because adapter is {device_type:?}"),
+            );
+        }
+        // Things that are always helpful, including whichever texture compression formats the platform has
+        report.opportunistic(
+            available,
+            wgpu::Features::TIMESTAMP_QUERY | wgpu::Features::TIMESTAMP_QUERY_INSIDE_PASSES,
+            "timestamp queries enabled for profiling".to_owned(),
+        );
+        report.opportunistic(
+            available,
+            wgpu::Features::TEXTURE_COMPRESSION_BC
+                | wgpu::Features::TEXTURE_COMPRESSION_ETC2
+                | wgpu::Features::TEXTURE_COMPRESSION_ASTC,
+            "texture compression formats enabled because the platform has them".to_owned(),
+        );
+
+        report.granted = report.requested.intersection(available);
+        report.denied = report.requested.difference(available);
+
+        Ok(report)
+    }
+
+    /// Requests some features that the game didn't ask for, giving the reason for any which the game wouldn't
+    /// otherwise have had.
+    fn opportunistic(
+        &mut self,
+        available: wgpu::Features,
+        features: wgpu::Features,
+        reason: String,
+    ) {
+        let added = features.intersection(available).difference(self.requested);
+        if !added.is_empty() {
+            self.requested |= added;
+            self.reasons.push((added, reason));
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Reports frames which take longer to draw than [`super::Game::frame_budget`], with the time the GPU spent on
+//! frames when the device has timestamp queries. The GPU time is also given to [`super::FrameStats`].
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Frames over budget are reported at most this often, so that a game which is always over budget doesn't flood
+/// the log.
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The frames which went over [`super::Game::frame_budget`] since the last report, given to
+/// [`super::Game::frame_budget_exceeded`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameBudgetReport {
+    /// How many frames went over the budget.
+    pub frames_over_budget: u32,
+    /// The longest time spent on the CPU drawing one of those frames.
+    pub cpu_time: Duration,
+    /// The time the GPU spent on the most recent frame it has finished, if the device has
+    /// [`wgpu::Features::TIMESTAMP_QUERY`]. GPU timings are read back without waiting for the GPU, so this is
+    /// usually from a frame or two before the report.
+    pub gpu_time: Option<Duration>,
+}
+
+pub(super) struct FrameBudget {
+    gpu_timer: Option<GpuTimer>,
+    pending: Option<FrameBudgetReport>,
+    last_report: Option<web_time::Instant>,
+}
+
+impl FrameBudget {
+    pub(super) fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        features: wgpu::Features,
+    ) -> Self {
+        Self {
+            gpu_timer: features
+                .contains(wgpu::Features::TIMESTAMP_QUERY)
+                .then(|| GpuTimer::new(device, queue)),
+            pending: None,
+            last_report: None,
+        }
+    }
+
+    /// Called before a frame is drawn, to time the GPU work submitted while drawing it.
+    pub(super) fn begin_frame(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.begin_frame(device, queue);
+        }
+    }
+
+    /// Called after a frame is drawn, to read back the time the GPU spent on it once the GPU is done.
+    pub(super) fn end_frame(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.end_frame(device, queue);
+        }
+    }
+
+    /// The time the GPU spent on the most recent frame it has finished, if the device has timestamp queries.
+    pub(super) fn gpu_time(&self) -> Option<Duration> {
+        self.gpu_timer.as_ref().and_then(|timer| timer.last_time)
+    }
+
+    /// Records how long drawing a frame took on the CPU. Gives a report of the frames over budget once there are
+    /// some and the last report was long enough ago.
+    pub(super) fn record(
+        &mut self,
+        budget: Duration,
+        cpu_time: Duration,
+        now: web_time::Instant,
+    ) -> Option<FrameBudgetReport> {
+        if cpu_time > budget {
+            let pending = self.pending.get_or_insert(FrameBudgetReport {
+                frames_over_budget: 0,
+                cpu_time: Duration::ZERO,
+                gpu_time: None,
+            });
+            pending.frames_over_budget += 1;
+            pending.cpu_time = pending.cpu_time.max(cpu_time);
+        }
+
+        if self
+            .last_report
+            .is_some_and(|last_report| now.duration_since(last_report) < REPORT_INTERVAL)
+        {
+            return None;
+        }
+        let mut report = self.pending.take()?;
+        report.gpu_time = self.gpu_time();
+        self.last_report = Some(now);
+        Some(report)
+    }
+}
+
+/// Writes a timestamp before and after the work submitted while drawing a frame. Only one frame is timed at a
+/// time, so while a frame's timestamps are being read back, later frames aren't timed.
+struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    timestamp_period: f64,
+    timing: bool,
+    // Whether the readback buffer has finished mapping, and if it mapped successfully
+    readback: Option<Arc<Mutex<Option<bool>>>>,
+    last_time: Option<Duration>,
+}
+
+impl GpuTimer {
+    const SIZE: wgpu::BufferAddress = 2 * std::mem::size_of::<u64>() as wgpu::BufferAddress;
+
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("frame budget timestamps"),
+                ty: wgpu::QueryType::Timestamp,
+                count: 2,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("frame budget timestamp resolve"),
+                size: Self::SIZE,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("frame budget timestamp readback"),
+                size: Self::SIZE,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            timestamp_period: f64::from(queue.get_timestamp_period()),
+            timing: false,
+            readback: None,
+            last_time: None,
+        }
+    }
+
+    fn begin_frame(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if let Some(readback) = &self.readback {
+            device.poll(wgpu::Maintain::Poll);
+            let Some(mapped) = *readback.lock().unwrap() else {
+                return;
+            };
+            if mapped {
+                self.read_back();
+            }
+            self.readback = None;
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("frame budget start timestamp"),
+        });
+        encoder.write_timestamp(&self.query_set, 0);
+        queue.submit([encoder.finish()]);
+        self.timing = true;
+    }
+
+    fn end_frame(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if !std::mem::take(&mut self.timing) {
+            return;
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("frame budget end timestamp"),
+        });
+        encoder.write_timestamp(&self.query_set, 1);
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            Self::SIZE,
+        );
+        queue.submit([encoder.finish()]);
+
+        let readback = Arc::new(Mutex::new(None));
+        let mapped = Arc::clone(&readback);
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                *mapped.lock().unwrap() = Some(result.is_ok());
+            });
+        self.readback = Some(readback);
+    }
+
+    fn read_back(&mut self) {
+        {
+            let timestamps = self.readback_buffer.slice(..).get_mapped_range();
+            let start = u64::from_le_bytes(timestamps[0..8].try_into().expect("8 bytes"));
+            let end = u64::from_le_bytes(timestamps[8..16].try_into().expect("8 bytes"));
+            let nanos = end.saturating_sub(start) as f64 * self.timestamp_period;
+            self.last_time = Some(Duration::from_nanos(nanos as u64));
+        }
+        self.readback_buffer.unmap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_are_rate_limited() {
+        let mut frame_budget = FrameBudget {
+            gpu_timer: None,
+            pending: None,
+            last_report: None,
+        };
+        let budget = Duration::from_millis(16);
+        let start = web_time::Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        assert_eq!(
+            frame_budget.record(budget, Duration::from_millis(10), at(0)),
+            None
+        );
+        assert_eq!(
+            frame_budget.record(budget, Duration::from_millis(20), at(10)),
+            Some(FrameBudgetReport {
+                frames_over_budget: 1,
+                cpu_time: Duration::from_millis(20),
+                gpu_time: None,
+            })
+        );
+
+        // Later frames over budget are held back until a second has passed, then reported together
+        assert_eq!(
+            frame_budget.record(budget, Duration::from_millis(30), at(40)),
+            None
+        );
+        assert_eq!(
+            frame_budget.record(budget, Duration::from_millis(25), at(70)),
+            None
+        );
+        assert_eq!(
+            frame_budget.record(budget, Duration::from_millis(10), at(1010)),
+            Some(FrameBudgetReport {
+                frames_over_budget: 2,
+                cpu_time: Duration::from_millis(30),
+                gpu_time: None,
+            })
+        );
+        assert_eq!(
+            frame_budget.record(budget, Duration::from_millis(10), at(2020)),
+            None
+        );
+    }
+}
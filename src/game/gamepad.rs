@@ -0,0 +1,372 @@
+//! Gamepad input, read with gilrs. Buttons and triggers are given as they change. Sticks are corrected for drift and wear using calibrations which are
+//! recorded on request and kept in local storage, and then a deadzone is applied.
+//!
+//! Rumble is played with gilrs' force feedback, which is only available on some platforms. Notably, it isn't
+//! supported on the web, or on macOS, and isn't supported by every gamepad on other platforms. Rumble requested
+//! for a gamepad without force feedback is ignored.
+
+use std::{collections::HashMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use super::input::{GamepadButtonCode, GamepadId, GamepadStickCode};
+
+/// The local storage key that stick calibrations are kept under.
+const STICK_CALIBRATION_KEY: &str = "stick_calibration";
+
+/// Stick positions closer than this to the center, after calibration, are treated as the center.
+const STICK_DEADZONE: f32 = 0.1;
+
+/// The correction applied to the raw position of one stick of one gamepad, given by where it rests and the
+/// furthest it has been seen to move in each direction.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct StickCalibration {
+    center: [f32; 2],
+    min: [f32; 2],
+    max: [f32; 2],
+}
+
+impl Default for StickCalibration {
+    fn default() -> Self {
+        Self {
+            center: [0.0, 0.0],
+            min: [-1.0, -1.0],
+            max: [1.0, 1.0],
+        }
+    }
+}
+
+impl StickCalibration {
+    fn resting_at(position: [f32; 2]) -> Self {
+        Self {
+            center: position,
+            min: position,
+            max: position,
+        }
+    }
+
+    /// Extends the range of the stick to include the given position.
+    fn observe(&mut self, position: [f32; 2]) {
+        for ((min, max), value) in self.min.iter_mut().zip(&mut self.max).zip(position) {
+            *min = min.min(value);
+            *max = max.max(value);
+        }
+    }
+
+    /// Maps a raw position to one centered on the resting position, reaching 1.0 at the extremes, with the
+    /// deadzone removed.
+    fn apply(&self, position: [f32; 2]) -> [f32; 2] {
+        let corrected: [f32; 2] = std::array::from_fn(|axis| {
+            let offset = position[axis] - self.center[axis];
+            let range = if offset >= 0.0 {
+                self.max[axis] - self.center[axis]
+            } else {
+                self.center[axis] - self.min[axis]
+            };
+            if range > f32::EPSILON {
+                (offset / range).clamp(-1.0, 1.0)
+            } else {
+                0.0
+            }
+        });
+
+        let length = corrected[0].hypot(corrected[1]);
+        if length < STICK_DEADZONE {
+            return [0.0, 0.0];
+        }
+        let scale = ((length - STICK_DEADZONE) / (1.0 - STICK_DEADZONE)).min(1.0) / length;
+        [corrected[0] * scale, corrected[1] * scale]
+    }
+}
+
+fn stick_axes(stick: GamepadStickCode) -> [gilrs::Axis; 2] {
+    match stick {
+        GamepadStickCode::Left => [gilrs::Axis::LeftStickX, gilrs::Axis::LeftStickY],
+        GamepadStickCode::Right => [gilrs::Axis::RightStickX, gilrs::Axis::RightStickY],
+    }
+}
+
+fn stick_of_axis(axis: gilrs::Axis) -> Option<GamepadStickCode> {
+    match axis {
+        gilrs::Axis::LeftStickX | gilrs::Axis::LeftStickY => Some(GamepadStickCode::Left),
+        gilrs::Axis::RightStickX | gilrs::Axis::RightStickY => Some(GamepadStickCode::Right),
+        _ => None,
+    }
+}
+
+fn button_code(button: gilrs::Button) -> Option<GamepadButtonCode> {
+    Some(match button {
+        gilrs::Button::South => GamepadButtonCode::South,
+        gilrs::Button::East => GamepadButtonCode::East,
+        gilrs::Button::North => GamepadButtonCode::North,
+        gilrs::Button::West => GamepadButtonCode::West,
+        gilrs::Button::LeftTrigger => GamepadButtonCode::LeftBumper,
+        gilrs::Button::RightTrigger => GamepadButtonCode::RightBumper,
+        gilrs::Button::LeftTrigger2 => GamepadButtonCode::LeftTrigger,
+        gilrs::Button::RightTrigger2 => GamepadButtonCode::RightTrigger,
+        gilrs::Button::Select => GamepadButtonCode::Select,
+        gilrs::Button::Start => GamepadButtonCode::Start,
+        gilrs::Button::Mode => GamepadButtonCode::Mode,
+        gilrs::Button::LeftThumb => GamepadButtonCode::LeftStick,
+        gilrs::Button::RightThumb => GamepadButtonCode::RightStick,
+        gilrs::Button::DPadUp => GamepadButtonCode::DPadUp,
+        gilrs::Button::DPadDown => GamepadButtonCode::DPadDown,
+        gilrs::Button::DPadLeft => GamepadButtonCode::DPadLeft,
+        gilrs::Button::DPadRight => GamepadButtonCode::DPadRight,
+        gilrs::Button::C | gilrs::Button::Z | gilrs::Button::Unknown => return None,
+    })
+}
+
+/// A change to a gamepad's input, given by [`Gamepads::poll`].
+pub(crate) enum GamepadInput {
+    /// The corrected position of a stick, with `y` increasing downwards.
+    Stick {
+        gamepad: GamepadId,
+        stick: GamepadStickCode,
+        position: [f32; 2],
+    },
+    /// How far a button is pressed, from 0 to 1.
+    Button {
+        gamepad: GamepadId,
+        button: GamepadButtonCode,
+        value: f32,
+    },
+}
+
+/// Calibrations are kept per physical model of gamepad and per stick.
+fn calibration_key(gamepad: &gilrs::Gamepad, stick: GamepadStickCode) -> String {
+    let uuid: String = gamepad
+        .uuid()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("{uuid}:{stick:?}")
+}
+
+fn raw_position(gamepad: &gilrs::Gamepad, stick: GamepadStickCode) -> [f32; 2] {
+    let [x, y] = stick_axes(stick);
+    [gamepad.value(x), gamepad.value(y)]
+}
+
+pub(crate) struct Gamepads {
+    gilrs: gilrs::Gilrs,
+    calibrations: HashMap<String, StickCalibration>,
+    // While recording, calibrations are built up here, replacing the stored calibrations once finished
+    recording: Option<HashMap<String, StickCalibration>>,
+    // Effects are stopped when dropped, so are kept until replaced or the gamepad is disconnected
+    rumble: HashMap<gilrs::GamepadId, gilrs::ff::Effect>,
+}
+
+impl Gamepads {
+    /// Gives `None` if gamepads aren't supported on this platform.
+    pub(crate) fn new() -> Option<Self> {
+        // We apply our own deadzone after calibrating, so need the raw values
+        let gilrs = match gilrs::GilrsBuilder::new()
+            .with_default_filters(false)
+            .build()
+        {
+            Ok(gilrs) => gilrs,
+            Err(gilrs::Error::NotImplemented(_)) => {
+                log::info!("gamepads are not supported on this platform");
+                return None;
+            }
+            Err(err) => {
+                log::error!("failed to initialise gamepads: {err}");
+                return None;
+            }
+        };
+
+        let calibrations = crate::local_storage::load(STICK_CALIBRATION_KEY)
+            .and_then(|stored| match serde_json::from_str(&stored) {
+                Ok(calibrations) => Some(calibrations),
+                Err(err) => {
+                    log::error!("failed to read stored stick calibrations: {err}");
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        Some(Self {
+            gilrs,
+            calibrations,
+            recording: None,
+            rumble: HashMap::new(),
+        })
+    }
+
+    /// Begins recording calibrations. The sticks of every connected gamepad should be at rest when this is
+    /// called, then moved through their full range before calling [`Gamepads::end_calibration`].
+    pub(crate) fn begin_calibration(&mut self) {
+        let mut recording = HashMap::new();
+        for (_, gamepad) in self.gilrs.gamepads() {
+            for stick in [GamepadStickCode::Left, GamepadStickCode::Right] {
+                recording.insert(
+                    calibration_key(&gamepad, stick),
+                    StickCalibration::resting_at(raw_position(&gamepad, stick)),
+                );
+            }
+        }
+        self.recording = Some(recording);
+    }
+
+    /// Finishes recording calibrations, and stores them for future runs.
+    pub(crate) fn end_calibration(&mut self) {
+        let Some(recording) = self.recording.take() else {
+            return;
+        };
+        self.calibrations.extend(recording);
+
+        let serialized = serde_json::to_string(&self.calibrations)
+            .expect("calibrations are always serializable");
+        if let Err(err) = crate::local_storage::store(STICK_CALIBRATION_KEY, &serialized) {
+            log::error!("failed to store stick calibrations: {err}");
+        }
+    }
+
+    /// The ids of all currently connected gamepads.
+    pub(crate) fn connected(&self) -> Vec<GamepadId> {
+        self.gilrs
+            .gamepads()
+            .map(|(id, _)| GamepadId(id.into()))
+            .collect()
+    }
+
+    /// Rumbles a gamepad with the given strengths, from `0.0` to `1.0`, for the given time, replacing any rumble
+    /// already playing on that gamepad. The strong motor gives low frequency rumble, and the weak motor high
+    /// frequency rumble. Does nothing if the gamepad doesn't support force feedback.
+    pub(crate) fn set_rumble(
+        &mut self,
+        gamepad: GamepadId,
+        strong: f32,
+        weak: f32,
+        duration: Duration,
+    ) {
+        let Some((id, connected)) = self
+            .gilrs
+            .gamepads()
+            .find(|(id, _)| usize::from(*id) == gamepad.0)
+        else {
+            return;
+        };
+        if !connected.is_ff_supported() {
+            log::debug!("gamepad {} does not support rumble", connected.name());
+            return;
+        }
+
+        self.rumble.remove(&id);
+        if strong <= 0.0 && weak <= 0.0 {
+            return;
+        }
+
+        let magnitude = |strength: f32| (strength.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+        let ticks = gilrs::ff::Ticks::from_ms(duration.as_millis().min(u32::MAX as u128) as u32);
+        let scheduling = gilrs::ff::Replay {
+            play_for: ticks,
+            ..Default::default()
+        };
+        let effect = gilrs::ff::EffectBuilder::new()
+            .add_effect(gilrs::ff::BaseEffect {
+                kind: gilrs::ff::BaseEffectType::Strong {
+                    magnitude: magnitude(strong),
+                },
+                scheduling,
+                envelope: Default::default(),
+            })
+            .add_effect(gilrs::ff::BaseEffect {
+                kind: gilrs::ff::BaseEffectType::Weak {
+                    magnitude: magnitude(weak),
+                },
+                scheduling,
+                envelope: Default::default(),
+            })
+            .repeat(gilrs::ff::Repeat::For(ticks))
+            .gamepads(&[id])
+            .finish(&mut self.gilrs);
+
+        match effect.and_then(|effect| effect.play().map(|()| effect)) {
+            Ok(effect) => {
+                self.rumble.insert(id, effect);
+            }
+            Err(err) => log::error!("failed to rumble gamepad: {err}"),
+        }
+    }
+
+    fn stop_rumble(&mut self) {
+        for (_, effect) in self.rumble.drain() {
+            if let Err(err) = effect.stop() {
+                log::error!("failed to stop gamepad rumble: {err}");
+            }
+        }
+    }
+
+    /// Handles all pending gamepad events, giving every button that changed, in order, and then the latest
+    /// corrected position of every stick that moved. While recording calibrations, no stick positions are given.
+    pub(crate) fn poll(&mut self) -> Vec<GamepadInput> {
+        let mut changed = Vec::new();
+        let mut moved = Vec::new();
+        while let Some(gilrs::Event { id, event, .. }) = self.gilrs.next_event() {
+            let axis = match event {
+                gilrs::EventType::AxisChanged(axis, _, _) => axis,
+                gilrs::EventType::ButtonChanged(button, value, _) => {
+                    if let Some(button) = button_code(button) {
+                        changed.push(GamepadInput::Button {
+                            gamepad: GamepadId(id.into()),
+                            button,
+                            value,
+                        });
+                    }
+                    continue;
+                }
+                gilrs::EventType::Disconnected => {
+                    self.rumble.remove(&id);
+                    continue;
+                }
+                _ => continue,
+            };
+            let Some(stick) = stick_of_axis(axis) else {
+                continue;
+            };
+
+            let gamepad = self.gilrs.gamepad(id);
+            let key = calibration_key(&gamepad, stick);
+            let position = raw_position(&gamepad, stick);
+
+            if let Some(recording) = &mut self.recording {
+                recording
+                    .entry(key)
+                    .or_insert_with(|| StickCalibration::resting_at(position))
+                    .observe(position);
+                continue;
+            }
+
+            let calibration = self.calibrations.entry(key).or_default();
+            // Sticks which reach further than previously seen are taken to have a larger range
+            calibration.observe(position);
+            let [x, y] = calibration.apply(position);
+
+            let gamepad = GamepadId(id.into());
+            moved.retain(|(moved_gamepad, moved_stick, _)| {
+                (*moved_gamepad, *moved_stick) != (gamepad, stick)
+            });
+            moved.push((gamepad, stick, [x, -y]));
+        }
+
+        changed.extend(
+            moved
+                .into_iter()
+                .map(|(gamepad, stick, position)| GamepadInput::Stick {
+                    gamepad,
+                    stick,
+                    position,
+                }),
+        );
+        changed
+    }
+}
+
+impl Drop for Gamepads {
+    /// Rumble is stopped when the game exits, rather than left to finish.
+    fn drop(&mut self) {
+        self.stop_rumble();
+    }
+}
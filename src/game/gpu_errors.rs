@@ -0,0 +1,144 @@
+//! Keeps the most recent uncaptured wgpu errors in a ring buffer, persisting them to local storage at most once a
+//! second so that they survive a crash.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+// The local storage key that captured errors are persisted under
+const STORAGE_KEY: &str = "gpu_errors";
+// Captured errors are written at most this often, as a broken pipeline can give an error every frame
+const PERSIST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Configures capturing wgpu errors which weren't caught by an error scope, so that they can be shown to the user
+/// and included in bug reports. See [`super::RunConfig::gpu_error_capture`].
+#[derive(Debug, Clone)]
+pub struct GpuErrorCapture {
+    /// The most errors kept. Once full, the oldest error is dropped for each new one. Defaults to 32.
+    pub capacity: usize,
+    /// Whether captured errors are written to [`crate::local_storage`] as they happen, and read back when the game
+    /// next starts, so that errors which preceded a crash or a closed tab can still be reported. Errors are written
+    /// at most once a second, so the errors given in the second before a crash may be lost. Defaults to `true`.
+    pub persist: bool,
+}
+
+impl Default for GpuErrorCapture {
+    fn default() -> Self {
+        Self {
+            capacity: 32,
+            persist: true,
+        }
+    }
+}
+
+/// The most recent uncaptured errors given by a device.
+#[derive(Clone)]
+pub(crate) struct GpuErrorLog {
+    errors: Arc<Mutex<CapturedErrors>>,
+}
+
+struct CapturedErrors {
+    errors: VecDeque<String>,
+    // Whether errors have been captured since they were last persisted
+    unsaved: bool,
+    last_persisted: Option<web_time::Instant>,
+}
+
+impl CapturedErrors {
+    /// Persists the errors if any are unsaved and they haven't been persisted too recently.
+    fn persist_debounced(&mut self) {
+        if self
+            .last_persisted
+            .is_some_and(|last_persisted| last_persisted.elapsed() < PERSIST_INTERVAL)
+        {
+            return;
+        }
+        self.persist_now();
+    }
+
+    fn persist_now(&mut self) {
+        if !self.unsaved {
+            return;
+        }
+        let serialized =
+            serde_json::to_string(&self.errors).expect("strings are always serializable");
+        if let Err(err) = crate::local_storage::store(STORAGE_KEY, &serialized) {
+            log::warn!("failed to store gpu errors: {err}");
+        }
+        self.unsaved = false;
+        self.last_persisted = Some(web_time::Instant::now());
+    }
+}
+
+impl GpuErrorLog {
+    /// Replaces the device's uncaptured error handler with one that logs each error and adds it to this log,
+    /// rather than panicking.
+    pub(crate) fn install(device: &wgpu::Device, config: GpuErrorCapture) -> Self {
+        let capacity = config.capacity.max(1);
+
+        let mut previous = VecDeque::new();
+        if config.persist {
+            if let Some(stored) = crate::local_storage::load(STORAGE_KEY) {
+                match serde_json::from_str::<VecDeque<String>>(&stored) {
+                    Ok(stored) => previous = stored,
+                    Err(err) => log::warn!("failed to read stored gpu errors: {err}"),
+                }
+            }
+        }
+        while previous.len() > capacity {
+            previous.pop_front();
+        }
+
+        let errors = Arc::new(Mutex::new(CapturedErrors {
+            errors: previous,
+            unsaved: false,
+            last_persisted: None,
+        }));
+        let handler_errors = Arc::clone(&errors);
+        device.on_uncaptured_error(Box::new(move |error| {
+            let message = error.to_string();
+            log::error!("uncaptured wgpu error: {message}");
+
+            let mut errors = handler_errors.lock().unwrap();
+            if errors.errors.len() >= capacity {
+                errors.errors.pop_front();
+            }
+            errors.errors.push_back(message);
+
+            if config.persist {
+                errors.unsaved = true;
+                errors.persist_debounced();
+            }
+        }));
+
+        Self { errors }
+    }
+
+    /// Persists errors which were captured too soon after the last were persisted to be written straight away.
+    /// Called every frame.
+    pub(crate) fn persist_pending(&self) {
+        self.errors.lock().unwrap().persist_debounced();
+    }
+
+    /// Persists any errors not yet persisted, however recently errors were last persisted. Called on exit.
+    pub(crate) fn persist_all(&self) {
+        self.errors.lock().unwrap().persist_now();
+    }
+
+    /// The captured errors, oldest first.
+    pub(crate) fn recent(&self) -> Vec<String> {
+        self.errors.lock().unwrap().errors.iter().cloned().collect()
+    }
+
+    /// Forgets every captured error, including any persisted.
+    pub(crate) fn clear(&self) {
+        let mut errors = self.errors.lock().unwrap();
+        errors.errors.clear();
+        if crate::local_storage::load(STORAGE_KEY).is_some() {
+            errors.unsaved = true;
+            errors.persist_now();
+        }
+    }
+}
@@ -1,6 +1,17 @@
-use std::collections::HashMap;
+mod toml_format;
 
-use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt,
+    str::FromStr,
+};
+
+use serde::{
+    de::{DeserializeOwned, IntoDeserializer},
+    Deserialize, Serialize,
+};
+
+use crate::InputMode;
 
 /// A value between 0 and 1 that some input has been activated
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
@@ -21,6 +32,149 @@ impl LinearInputActivation {
     pub fn clamp(val: f32) -> Self {
         Self(val.clamp(0.0, 1.0))
     }
+
+    /// The sum of two activations, clamped to stay at most 1.
+    pub fn saturating_add(self, other: Self) -> Self {
+        Self::clamp(self.0 + other.0)
+    }
+
+    /// Scales the activation, clamping the result between 0 and 1.
+    pub fn scale(self, factor: f32) -> Self {
+        Self::clamp(self.0 * factor)
+    }
+
+    /// Linearly interpolates between two activations, with `t` clamped between 0 and 1.
+    pub fn lerp(self, other: Self, t: f32) -> Self {
+        Self::clamp(crate::math::lerp(self.0, other.0, t.clamp(0.0, 1.0)))
+    }
+}
+
+/// The latest activation given for each of a game's actions, so that they can be polled from
+/// [`crate::GameData`]. The maps are type erased, as `GameData` isn't generic over the game's action types.
+///
+/// Activations are kept for each input bound to an action, and for each gamepad giving the input, so that an action
+/// bound to several inputs stays activated until all of them are released, reporting the strongest.
+pub(crate) struct ActionValues {
+    linear: Box<dyn std::any::Any + Send + Sync>,
+    vector: Box<dyn std::any::Any + Send + Sync>,
+}
+
+type LinearValues<TLinear> =
+    HashMap<TLinear, HashMap<(LinearInputType, Option<GamepadId>), LinearInputActivation>>;
+type VectorValues<TVector> =
+    HashMap<TVector, HashMap<(VectorInputType, Option<GamepadId>), VectorInputActivation>>;
+
+impl ActionValues {
+    pub(crate) fn new<TLinear, TVector>() -> Self
+    where
+        TLinear: std::hash::Hash + Eq + Send + Sync + 'static,
+        TVector: std::hash::Hash + Eq + Send + Sync + 'static,
+    {
+        Self {
+            linear: Box::new(LinearValues::<TLinear>::new()),
+            vector: Box::new(VectorValues::<TVector>::new()),
+        }
+    }
+
+    pub(crate) fn linear<TLinear: std::hash::Hash + Eq + 'static>(
+        &self,
+        action: &TLinear,
+    ) -> LinearInputActivation {
+        self.linear
+            .downcast_ref::<LinearValues<TLinear>>()
+            .and_then(|values| values.get(action))
+            .and_then(|inputs| {
+                inputs
+                    .values()
+                    .copied()
+                    .reduce(|a, b| if b.0 > a.0 { b } else { a })
+            })
+            .unwrap_or(LinearInputActivation(0.0))
+    }
+
+    pub(crate) fn vector<TVector: std::hash::Hash + Eq + 'static>(
+        &self,
+        action: &TVector,
+    ) -> VectorInputActivation {
+        let magnitude = |activation: &VectorInputActivation| activation.0.hypot(activation.1);
+        self.vector
+            .downcast_ref::<VectorValues<TVector>>()
+            .and_then(|values| values.get(action))
+            .and_then(|inputs| {
+                inputs
+                    .values()
+                    .copied()
+                    .reduce(|a, b| if magnitude(&b) > magnitude(&a) { b } else { a })
+            })
+            .unwrap_or(VectorInputActivation(0.0, 0.0))
+    }
+
+    pub(crate) fn set_linear<TLinear: std::hash::Hash + Eq + Clone + 'static>(
+        &mut self,
+        action: &TLinear,
+        input: LinearInputType,
+        gamepad: Option<GamepadId>,
+        activation: LinearInputActivation,
+    ) {
+        let Some(values) = self.linear.downcast_mut::<LinearValues<TLinear>>() else {
+            return;
+        };
+        if activation.0 > 0.0 {
+            values
+                .entry(action.clone())
+                .or_default()
+                .insert((input, gamepad), activation);
+        } else if let Some(inputs) = values.get_mut(action) {
+            inputs.remove(&(input, gamepad));
+            if inputs.is_empty() {
+                values.remove(action);
+            }
+        }
+    }
+
+    pub(crate) fn set_vector<TVector: std::hash::Hash + Eq + Clone + 'static>(
+        &mut self,
+        action: &TVector,
+        input: VectorInputType,
+        gamepad: Option<GamepadId>,
+        activation: VectorInputActivation,
+    ) {
+        let Some(values) = self.vector.downcast_mut::<VectorValues<TVector>>() else {
+            return;
+        };
+        if activation != VectorInputActivation(0.0, 0.0) {
+            values
+                .entry(action.clone())
+                .or_default()
+                .insert((input, gamepad), activation);
+        } else if let Some(inputs) = values.get_mut(action) {
+            inputs.remove(&(input, gamepad));
+            if inputs.is_empty() {
+                values.remove(action);
+            }
+        }
+    }
+
+    /// Forgets the activations given by one-off inputs, such as mouse movement, which are never released. Called
+    /// once each frame, so that these activations are only seen by the frame they happened before.
+    pub(crate) fn clear_one_off<TLinear, TVector>(&mut self)
+    where
+        TLinear: std::hash::Hash + Eq + 'static,
+        TVector: std::hash::Hash + Eq + 'static,
+    {
+        if let Some(values) = self.linear.downcast_mut::<LinearValues<TLinear>>() {
+            values.retain(|_, inputs| {
+                inputs.retain(|(input, _), _| !input.is_one_off());
+                !inputs.is_empty()
+            });
+        }
+        if let Some(values) = self.vector.downcast_mut::<VectorValues<TVector>>() {
+            values.retain(|_, inputs| {
+                inputs.retain(|(input, _), _| !input.is_one_off());
+                !inputs.is_empty()
+            });
+        }
+    }
 }
 
 /// A 2d value with both components between -1 and 1 that some input has been activated
@@ -42,10 +196,166 @@ impl VectorInputActivation {
     pub fn clamp(x: f32, y: f32) -> VectorInputActivation {
         Self(x.clamp(-1.0, 1.0), y.clamp(-1.0, 1.0))
     }
+
+    /// The component-wise sum of two activations, with each component clamped between -1 and 1.
+    ///
+    /// ```
+    /// # use lf_gfx::input::VectorInputActivation;
+    /// let a = VectorInputActivation::clamp(0.75, -0.5);
+    /// let b = VectorInputActivation::clamp(0.5, 0.25);
+    /// assert_eq!(a.saturating_add(b).get(), (1.0, -0.25));
+    /// ```
+    pub fn saturating_add(self, other: Self) -> Self {
+        Self::clamp(self.0 + other.0, self.1 + other.1)
+    }
+
+    /// Scales both components, clamping each between -1 and 1.
+    pub fn scale(self, factor: f32) -> Self {
+        Self::clamp(self.0 * factor, self.1 * factor)
+    }
+
+    /// Linearly interpolates between two activations, with `t` clamped between 0 and 1.
+    pub fn lerp(self, other: Self, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        Self::clamp(
+            crate::math::lerp(self.0, other.0, t),
+            crate::math::lerp(self.1, other.1, t),
+        )
+    }
+}
+
+/// Reshapes how strongly an input activates its action, applied to every activation of the input before it reaches
+/// the game. Set for an input with [`InputMap::set_linear_curve`] or [`InputMap::set_vector_curve`], and stored
+/// with the rest of the map. Vector inputs have the curve applied to their length, keeping their direction.
+///
+/// ```
+/// use lf_gfx::input::ResponseCurve;
+///
+/// // Squaring gives finer control near the center, while still reaching full activation
+/// let curve = ResponseCurve::Power(2.0);
+/// assert_eq!(curve.apply(0.5), 0.25);
+/// assert_eq!(curve.apply(1.0), 1.0);
+///
+/// // A table can give a dead zone, then a linear response
+/// let curve = ResponseCurve::Custom(vec![0.0, 0.0, 0.5, 1.0]);
+/// assert_eq!(curve.apply(0.2), 0.0);
+/// assert_eq!(curve.apply(0.5), 0.25);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum ResponseCurve {
+    /// Activations are passed on unchanged.
+    #[default]
+    Linear,
+    /// Activations are raised to the given power. Powers above 1 give finer control for small activations, and
+    /// powers below 1 make small activations stronger. Powers of 0 or below, which would activate inputs at rest,
+    /// are treated as 1.
+    Power(f32),
+    /// Activations are looked up in a table of outputs for evenly spaced activations from 0 to 1, interpolating
+    /// linearly between entries. An empty table passes activations on unchanged. Inputs at rest stay at rest,
+    /// whatever the table's first entry.
+    Custom(Vec<f32>),
+}
+
+impl ResponseCurve {
+    /// Maps an activation between 0 and 1 to the reshaped activation, clamped between 0 and 1. An activation of 0
+    /// always maps to 0, so that released inputs are released whatever the curve.
+    pub fn apply(&self, value: f32) -> f32 {
+        let value = value.clamp(0.0, 1.0);
+        if value == 0.0 {
+            return 0.0;
+        }
+        let output = match self {
+            Self::Linear => value,
+            Self::Power(power) if *power > 0.0 => value.powf(*power),
+            Self::Power(_) => value,
+            Self::Custom(table) => match table.as_slice() {
+                [] => value,
+                [only] => *only,
+                table => {
+                    let position = value * (table.len() - 1) as f32;
+                    let index = (position as usize).min(table.len() - 2);
+                    crate::math::lerp(table[index], table[index + 1], position - index as f32)
+                }
+            },
+        };
+        output.clamp(0.0, 1.0)
+    }
+
+    fn apply_linear(&self, activation: LinearInputActivation) -> LinearInputActivation {
+        LinearInputActivation::clamp(self.apply(activation.get()))
+    }
+
+    fn apply_vector(&self, activation: VectorInputActivation) -> VectorInputActivation {
+        let (x, y) = activation.get();
+        let length = x.hypot(y);
+        if length == 0.0 {
+            return activation;
+        }
+        let scale = self.apply(length.min(1.0)) / length;
+        VectorInputActivation::clamp(x * scale, y * scale)
+    }
 }
 
-/// A key on a keyboard
-#[derive(Debug, Hash, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+/// Given when parsing the name of an input, or of an [`InputMode`], which doesn't exist.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown name `{0}`")]
+pub struct UnknownNameError(pub String);
+
+/// Names inputs by their variant names, such as `KeyW` or `ScrollUp`. These are the names used by
+/// [`InputMap::to_pretty_toml`], and are the same as the names they are serialized with.
+macro_rules! impl_names {
+    ($($name:ty),* $(,)?) => {$(
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(self, f)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = UnknownNameError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Self::deserialize(IntoDeserializer::<serde::de::value::Error>::into_deserializer(s))
+                    .map_err(|_| UnknownNameError(s.to_owned()))
+            }
+        }
+    )*};
+}
+
+impl_names!(KeyCode, GamepadButtonCode, GamepadStickCode, InputMode);
+
+/// Names mouse inputs by their variant names, with other buttons named by their number, such as `ButtonOther5`.
+impl fmt::Display for MouseInputType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ButtonOther(button) => write!(f, "ButtonOther{button}"),
+            _ => fmt::Debug::fmt(self, f),
+        }
+    }
+}
+
+impl FromStr for MouseInputType {
+    type Err = UnknownNameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(button) = s.strip_prefix("ButtonOther") {
+            return button
+                .parse()
+                .map(Self::ButtonOther)
+                .map_err(|_| UnknownNameError(s.to_owned()));
+        }
+        Self::deserialize(IntoDeserializer::<serde::de::value::Error>::into_deserializer(s))
+            .map_err(|_| UnknownNameError(s.to_owned()))
+    }
+}
+
+// Returned for inputs without a curve
+static LINEAR_CURVE: ResponseCurve = ResponseCurve::Linear;
+
+/// A key on a keyboard. Keys are ordered as they are declared, which groups them by the area of the keyboard
+/// they are in, such as the letter keys, the arrow keys, or the numpad. See [`LinearInputType`] for how the
+/// ordering of inputs is kept stable.
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone, Copy)]
 pub enum KeyCode {
     /// <kbd>`</kbd> on a US keyboard. This is also called a backtick or grave.
     /// This is the <kbd>半角</kbd>/<kbd>全角</kbd>/<kbd>漢字</kbd>
@@ -490,9 +800,13 @@ pub enum KeyCode {
     F35,
 }
 
-impl From<winit::keyboard::KeyCode> for KeyCode {
-    fn from(value: winit::keyboard::KeyCode) -> Self {
-        match value {
+/// Winit adds key codes in new releases, so the conversion gives back any key code that this crate doesn't know,
+/// rather than failing to handle the key at all.
+impl TryFrom<winit::keyboard::KeyCode> for KeyCode {
+    type Error = winit::keyboard::KeyCode;
+
+    fn try_from(value: winit::keyboard::KeyCode) -> Result<Self, Self::Error> {
+        Ok(match value {
             winit::keyboard::KeyCode::Backquote => Self::Backquote,
             winit::keyboard::KeyCode::Backslash => Self::Backslash,
             winit::keyboard::KeyCode::BracketLeft => Self::BracketLeft,
@@ -687,25 +1001,122 @@ impl From<winit::keyboard::KeyCode> for KeyCode {
             winit::keyboard::KeyCode::F33 => Self::F33,
             winit::keyboard::KeyCode::F34 => Self::F34,
             winit::keyboard::KeyCode::F35 => Self::F35,
-            _ => todo!(),
-        }
+            unknown => return Err(unknown),
+        })
     }
 }
 
-#[derive(Debug, Hash, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone, Copy)]
 pub enum MouseInputType {
     MoveLeft,
     MoveRight,
     MoveUp,
     MoveDown,
+    /// Scrolling up, with an activation proportional to how far was scrolled. See
+    /// [`crate::GameCommand::SetScrollSensitivity`]. Each scroll is given once, and is never released, in the same
+    /// way as mouse movement.
     ScrollUp,
+    /// Scrolling down, with an activation proportional to how far was scrolled.
     ScrollDown,
+    ButtonLeft,
+    ButtonRight,
+    ButtonMiddle,
+    /// Any other mouse button, by its number. The back and forward buttons are 3 and 4, matching the numbering
+    /// used by browsers.
+    ButtonOther(u16),
+}
+
+impl From<winit::event::MouseButton> for MouseInputType {
+    fn from(value: winit::event::MouseButton) -> Self {
+        match value {
+            winit::event::MouseButton::Left => Self::ButtonLeft,
+            winit::event::MouseButton::Right => Self::ButtonRight,
+            winit::event::MouseButton::Middle => Self::ButtonMiddle,
+            winit::event::MouseButton::Back => Self::ButtonOther(3),
+            winit::event::MouseButton::Forward => Self::ButtonOther(4),
+            winit::event::MouseButton::Other(button) => Self::ButtonOther(button),
+        }
+    }
+}
+
+/// A button on a gamepad, named by its position on a standard controller layout. The analog triggers are given as
+/// how far they are pulled, from 0 to 1, and other buttons as either 0 or 1.
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone, Copy)]
+pub enum GamepadButtonCode {
+    /// The bottom face button, such as <kbd>A</kbd> on Xbox controllers or <kbd>✕</kbd> on PlayStation controllers.
+    South,
+    /// The right face button, such as <kbd>B</kbd> or <kbd>○</kbd>.
+    East,
+    /// The top face button, such as <kbd>Y</kbd> or <kbd>△</kbd>.
+    North,
+    /// The left face button, such as <kbd>X</kbd> or <kbd>□</kbd>.
+    West,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    /// The button in the center of the controller, such as the Xbox or PlayStation button.
+    Mode,
+    /// Pressing in the left stick.
+    LeftStick,
+    /// Pressing in the right stick.
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
 }
 
-#[derive(Debug, Hash, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+/// An input which is either activated or not, or somewhere in between.
+///
+/// Inputs are ordered as their variants are declared: keys, then the mouse, then gamepad buttons, and within each
+/// by the order of the inner type's variants. This allows inputs to be kept in a `BTreeMap`, such as to list
+/// bindings in a consistent order in a settings screen. New variants are only added after existing ones, so
+/// the ordering stays the same between versions of this crate. Serialized input maps don't depend on the
+/// ordering, as they are sorted by the serialized names of inputs, so changes to it wouldn't alter stored maps.
+///
+/// ```
+/// use lf_gfx::input::{GamepadButtonCode, KeyCode, LinearInputType, MouseInputType};
+///
+/// let mut inputs = vec![
+///     LinearInputType::from(GamepadButtonCode::South),
+///     LinearInputType::from(MouseInputType::ButtonLeft),
+///     LinearInputType::from(KeyCode::KeyW),
+///     LinearInputType::from(KeyCode::KeyA),
+/// ];
+/// inputs.sort();
+/// assert_eq!(inputs, [
+///     LinearInputType::from(KeyCode::KeyA),
+///     LinearInputType::from(KeyCode::KeyW),
+///     LinearInputType::from(MouseInputType::ButtonLeft),
+///     LinearInputType::from(GamepadButtonCode::South),
+/// ]);
+/// ```
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone, Copy)]
 pub enum LinearInputType {
     KnownKeyboard(KeyCode),
     Mouse(MouseInputType),
+    GamepadButton(GamepadButtonCode),
+}
+
+impl LinearInputType {
+    /// Whether the input happens once, rather than being held and later released, such as mouse movement and
+    /// scrolling.
+    pub(crate) fn is_one_off(&self) -> bool {
+        matches!(
+            self,
+            Self::Mouse(
+                MouseInputType::MoveLeft
+                    | MouseInputType::MoveRight
+                    | MouseInputType::MoveUp
+                    | MouseInputType::MoveDown
+                    | MouseInputType::ScrollUp
+                    | MouseInputType::ScrollDown
+            )
+        )
+    }
 }
 
 impl From<KeyCode> for LinearInputType {
@@ -720,19 +1131,178 @@ impl From<MouseInputType> for LinearInputType {
     }
 }
 
-#[derive(Debug, Hash, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+impl From<GamepadButtonCode> for LinearInputType {
+    fn from(value: GamepadButtonCode) -> Self {
+        Self::GamepadButton(value)
+    }
+}
+
+/// One of the two analog sticks on a gamepad. Stick positions are given with `y` increasing downwards, as
+/// with mouse movement.
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone, Copy)]
+pub enum GamepadStickCode {
+    Left,
+    Right,
+}
+
+/// Identifies a connected gamepad for as long as it stays connected. The ids of gamepads which are connected are
+/// given by [`crate::GameData::connected_gamepads`], and the gamepad that the input being handled came from is
+/// given by [`crate::GameData::input_gamepad`].
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub struct GamepadId(pub(crate) usize);
+
+/// An input which moves in two dimensions. Ordered as its variants are declared, in the same way as
+/// [`LinearInputType`].
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone, Copy)]
 pub enum VectorInputType {
     MouseMove,
+    GamepadStick(GamepadStickCode),
+}
+
+impl VectorInputType {
+    /// Whether the input happens once, rather than being held, such as mouse movement. Gamepad sticks are held,
+    /// and return to the center when let go.
+    pub(crate) fn is_one_off(&self) -> bool {
+        matches!(self, Self::MouseMove)
+    }
+}
+
+impl From<GamepadStickCode> for VectorInputType {
+    fn from(value: GamepadStickCode) -> Self {
+        Self::GamepadStick(value)
+    }
+}
+
+/// Maps are stored as lists of entries sorted by their serialized input, so that serializing the same
+/// map always gives the same string, regardless of hash map iteration order. This also allows inputs
+/// that aren't strings to be stored in formats like JSON, which only allow string keys in maps.
+mod canonical_map {
+    use std::{collections::HashMap, hash::Hash, marker::PhantomData};
+
+    use serde::{
+        de::{MapAccess, SeqAccess, Visitor},
+        ser::Error,
+        Deserialize, Deserializer, Serialize, Serializer,
+    };
+
+    pub(super) fn serialize<S, K, V>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        K: Serialize,
+        V: Serialize,
+    {
+        let mut entries = map
+            .iter()
+            .map(|(input, value)| Ok((serde_json::to_string(input)?, input, value)))
+            .collect::<Result<Vec<_>, serde_json::Error>>()
+            .map_err(S::Error::custom)?;
+        entries.sort_by(|(lhs, _, _), (rhs, _, _)| lhs.cmp(rhs));
+
+        serializer.collect_seq(entries.into_iter().map(|(_, input, value)| (input, value)))
+    }
+
+    /// Reads the sorted entries given by [`serialize`], or a map, which is how maps were stored before their
+    /// serialization was canonical.
+    pub(super) fn deserialize<'de, D, K, V>(deserializer: D) -> Result<HashMap<K, V>, D::Error>
+    where
+        D: Deserializer<'de>,
+        K: Deserialize<'de> + Hash + Eq,
+        V: Deserialize<'de>,
+    {
+        deserializer.deserialize_any(EntriesVisitor(PhantomData))
+    }
+
+    struct EntriesVisitor<K, V>(PhantomData<(K, V)>);
+
+    impl<'de, K, V> Visitor<'de> for EntriesVisitor<K, V>
+    where
+        K: Deserialize<'de> + Hash + Eq,
+        V: Deserialize<'de>,
+    {
+        type Value = HashMap<K, V>;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a list of entries or a map")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut map = HashMap::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some((key, value)) = seq.next_element()? {
+                map.insert(key, value);
+            }
+            Ok(map)
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut entries: A) -> Result<Self::Value, A::Error> {
+            let mut map = HashMap::with_capacity(entries.size_hint().unwrap_or(0));
+            while let Some((key, value)) = entries.next_entry()? {
+                map.insert(key, value);
+            }
+            Ok(map)
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "TLinear: Serialize, TVector: Serialize",
+    deserialize = "TLinear: Deserialize<'de>, TVector: Deserialize<'de>"
+))]
 struct InputMapInner<TLinear, TVector> {
+    #[serde(with = "canonical_map")]
     linear_map: HashMap<LinearInputType, TLinear>,
+    #[serde(with = "canonical_map")]
     vector_map: HashMap<VectorInputType, TVector>,
+    // Actions bound to an input after the first, which is in `linear_map`. Defaulted so that maps stored before
+    // inputs could have several actions still load.
+    #[serde(default, with = "canonical_map")]
+    additional_linear_map: HashMap<LinearInputType, Vec<TLinear>>,
+    // Entries which only apply in one input mode, taking precedence over the untagged entries above. Defaulted so
+    // that maps stored before modes could be tagged still load.
+    #[serde(default, with = "canonical_map")]
+    mode_linear_map: HashMap<(InputMode, LinearInputType), TLinear>,
+    #[serde(default, with = "canonical_map")]
+    mode_vector_map: HashMap<(InputMode, VectorInputType), TVector>,
+    #[serde(default, with = "canonical_map")]
+    linear_curves: HashMap<LinearInputType, ResponseCurve>,
+    #[serde(default, with = "canonical_map")]
+    vector_curves: HashMap<VectorInputType, ResponseCurve>,
+}
+
+impl<TLinear, TVector> InputMapInner<TLinear, TVector> {
+    /// Maps written by hand can list actions after the first for inputs without a first action. The first of
+    /// those actions becomes the input's first action, so that every listed action is given to the game.
+    fn promote_orphaned_actions(&mut self) {
+        let linear_map = &mut self.linear_map;
+        self.additional_linear_map.retain(|input, actions| {
+            if let Entry::Vacant(entry) = linear_map.entry(*input) {
+                if actions.is_empty() {
+                    return false;
+                }
+                entry.insert(actions.remove(0));
+            }
+            !actions.is_empty()
+        });
+    }
 }
 
 /// Maps between physical inputs providable by the user, and whatever action representation
 /// your game uses.
+///
+/// Entries can be tagged with the [`InputMode`] they apply to, so that the same input can mean different things
+/// during gameplay and in menus without swapping maps. Untagged entries apply in every mode, unless the current
+/// mode has its own entry for the input:
+///
+/// ```
+/// use lf_gfx::{input::{InputMap, KeyCode}, InputMode};
+///
+/// let mut map = InputMap::<&str, ()>::empty();
+/// map.assign_linear(KeyCode::KeyA, "move left");
+/// map.assign_linear_in(InputMode::UI, KeyCode::KeyA, "menu left");
+///
+/// assert_eq!(map.get_linear_in(InputMode::Unified, KeyCode::KeyA), Some(&"move left"));
+/// assert_eq!(map.get_linear_in(InputMode::UI, KeyCode::KeyA), Some(&"menu left"));
+/// ```
 pub struct InputMap<TLinear, TVector> {
     inner: InputMapInner<TLinear, TVector>,
 }
@@ -743,25 +1313,90 @@ impl<TLinear, TVector> InputMap<TLinear, TVector> {
             inner: InputMapInner {
                 linear_map: HashMap::new(),
                 vector_map: HashMap::new(),
+                additional_linear_map: HashMap::new(),
+                mode_linear_map: HashMap::new(),
+                mode_vector_map: HashMap::new(),
+                linear_curves: HashMap::new(),
+                vector_curves: HashMap::new(),
             },
         }
     }
 
+    /// Binds the input to the action, replacing any actions already bound to the input.
     #[inline]
     pub fn assign_linear(&mut self, input: impl Into<LinearInputType>, value: TLinear) {
-        self.inner.linear_map.insert(input.into(), value);
+        let input = input.into();
+        self.inner.additional_linear_map.remove(&input);
+        self.inner.linear_map.insert(input, value);
     }
 
+    /// Binds the input to the action, as well as to any actions already bound to the input, so that one press
+    /// is given to the game as each action in turn. Actions already bound to the input aren't bound again:
+    ///
+    /// ```
+    /// use lf_gfx::input::{InputMap, KeyCode};
+    ///
+    /// let mut map = InputMap::<&str, ()>::empty();
+    /// map.assign_linear(KeyCode::Space, "jump");
+    /// map.assign_linear_additional(KeyCode::Space, "menu confirm");
+    ///
+    /// assert_eq!(map.get_linear(KeyCode::Space), Some(&"jump"));
+    /// assert_eq!(map.get_all_linear(KeyCode::Space).collect::<Vec<_>>(), [&"jump", &"menu confirm"]);
+    ///
+    /// map.assign_linear_additional(KeyCode::Space, "jump");
+    /// assert_eq!(map.get_all_linear(KeyCode::Space).count(), 2);
+    /// ```
+    pub fn assign_linear_additional(&mut self, input: impl Into<LinearInputType>, value: TLinear)
+    where
+        TLinear: PartialEq,
+    {
+        let input = input.into();
+        match self.inner.linear_map.entry(input) {
+            Entry::Occupied(first) => {
+                if *first.get() == value {
+                    return;
+                }
+                let additional = self.inner.additional_linear_map.entry(input).or_default();
+                if !additional.contains(&value) {
+                    additional.push(value);
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+            }
+        }
+    }
+
+    /// Removes every action bound to the input.
     #[inline]
     pub fn unassign_linear(&mut self, input: impl Into<LinearInputType>) {
-        self.inner.linear_map.remove(&input.into());
+        let input = input.into();
+        self.inner.linear_map.remove(&input);
+        self.inner.additional_linear_map.remove(&input);
     }
 
+    /// Gives the first action bound to the input. See [`InputMap::get_all_linear`] for inputs bound to several
+    /// actions.
     #[inline]
     pub fn get_linear(&self, input: impl Into<LinearInputType>) -> Option<&TLinear> {
         self.inner.linear_map.get(&input.into())
     }
 
+    /// Gives every action bound to the input, in the order they were assigned.
+    pub fn get_all_linear(
+        &self,
+        input: impl Into<LinearInputType>,
+    ) -> impl Iterator<Item = &TLinear> {
+        let input = input.into();
+        self.inner.linear_map.get(&input).into_iter().chain(
+            self.inner
+                .additional_linear_map
+                .get(&input)
+                .into_iter()
+                .flatten(),
+        )
+    }
+
     #[inline]
     pub fn assign_vector(&mut self, input: impl Into<VectorInputType>, value: TVector) {
         self.inner.vector_map.insert(input.into(), value);
@@ -777,26 +1412,622 @@ impl<TLinear, TVector> InputMap<TLinear, TVector> {
         self.inner.vector_map.get(&input.into())
     }
 
-    /// For each entry in `other`, replaces the entry in this, or adds a new entry.
+    /// Assigns an input which only applies in the given mode, taking precedence over any untagged entry for the
+    /// same input while in that mode. To apply in several modes, assign the input once for each.
+    #[inline]
+    pub fn assign_linear_in(
+        &mut self,
+        mode: InputMode,
+        input: impl Into<LinearInputType>,
+        value: TLinear,
+    ) {
+        self.inner
+            .mode_linear_map
+            .insert((mode, input.into()), value);
+    }
+
+    /// Removes an entry added by [`InputMap::assign_linear_in`], leaving any untagged entry for the input.
+    #[inline]
+    pub fn unassign_linear_in(&mut self, mode: InputMode, input: impl Into<LinearInputType>) {
+        self.inner.mode_linear_map.remove(&(mode, input.into()));
+    }
+
+    /// Gives the action that the input is mapped to in the given mode, which is the entry tagged with the mode if
+    /// there is one, or otherwise the untagged entry.
+    #[inline]
+    pub fn get_linear_in(
+        &self,
+        mode: InputMode,
+        input: impl Into<LinearInputType>,
+    ) -> Option<&TLinear> {
+        let input = input.into();
+        self.inner
+            .mode_linear_map
+            .get(&(mode, input))
+            .or_else(|| self.inner.linear_map.get(&input))
+    }
+
+    /// Gives every action that the input is mapped to in the given mode, which is the entry tagged with the mode
+    /// if there is one, or otherwise every untagged action. See [`InputMap::get_linear_in`].
+    pub fn get_all_linear_in(
+        &self,
+        mode: InputMode,
+        input: impl Into<LinearInputType>,
+    ) -> impl Iterator<Item = &TLinear> {
+        let input = input.into();
+        let tagged = self.inner.mode_linear_map.get(&(mode, input));
+        let untagged = tagged.is_none().then(|| self.get_all_linear(input));
+        tagged.into_iter().chain(untagged.into_iter().flatten())
+    }
+
+    /// Whether the input reaches the game in the given mode. Modes which pass input on to the game, such as
+    /// [`InputMode::UI`], only handle inputs with an entry tagged with the mode, so that menus can still have
+    /// bindings of their own. Other modes handle every input.
+    pub(crate) fn handles_linear_in(&self, mode: InputMode, input: LinearInputType) -> bool {
+        mode.should_handle_input() || self.inner.mode_linear_map.contains_key(&(mode, input))
+    }
+
+    /// Whether the input reaches the game in the given mode. See [`InputMap::handles_linear_in`].
+    pub(crate) fn handles_vector_in(&self, mode: InputMode, input: VectorInputType) -> bool {
+        mode.should_handle_input() || self.inner.mode_vector_map.contains_key(&(mode, input))
+    }
+
+    /// Assigns an input which only applies in the given mode, taking precedence over any untagged entry for the
+    /// same input while in that mode. To apply in several modes, assign the input once for each.
+    #[inline]
+    pub fn assign_vector_in(
+        &mut self,
+        mode: InputMode,
+        input: impl Into<VectorInputType>,
+        value: TVector,
+    ) {
+        self.inner
+            .mode_vector_map
+            .insert((mode, input.into()), value);
+    }
+
+    /// Removes an entry added by [`InputMap::assign_vector_in`], leaving any untagged entry for the input.
+    #[inline]
+    pub fn unassign_vector_in(&mut self, mode: InputMode, input: impl Into<VectorInputType>) {
+        self.inner.mode_vector_map.remove(&(mode, input.into()));
+    }
+
+    /// Gives the action that the input is mapped to in the given mode, which is the entry tagged with the mode if
+    /// there is one, or otherwise the untagged entry.
+    #[inline]
+    pub fn get_vector_in(
+        &self,
+        mode: InputMode,
+        input: impl Into<VectorInputType>,
+    ) -> Option<&TVector> {
+        let input = input.into();
+        self.inner
+            .mode_vector_map
+            .get(&(mode, input))
+            .or_else(|| self.inner.vector_map.get(&input))
+    }
+
+    /// Sets the curve applied to every activation of the input, in every mode. Inputs start with
+    /// [`ResponseCurve::Linear`].
+    pub fn set_linear_curve(&mut self, input: impl Into<LinearInputType>, curve: ResponseCurve) {
+        // Linear curves are kept, so that they replace other curves in a union
+        self.inner.linear_curves.insert(input.into(), curve);
+    }
+
+    pub fn linear_curve(&self, input: impl Into<LinearInputType>) -> &ResponseCurve {
+        self.inner
+            .linear_curves
+            .get(&input.into())
+            .unwrap_or(&LINEAR_CURVE)
+    }
+
+    /// Sets the curve applied to the length of every activation of the input, in every mode. Inputs start with
+    /// [`ResponseCurve::Linear`].
+    pub fn set_vector_curve(&mut self, input: impl Into<VectorInputType>, curve: ResponseCurve) {
+        // Linear curves are kept, so that they replace other curves in a union
+        self.inner.vector_curves.insert(input.into(), curve);
+    }
+
+    pub fn vector_curve(&self, input: impl Into<VectorInputType>) -> &ResponseCurve {
+        self.inner
+            .vector_curves
+            .get(&input.into())
+            .unwrap_or(&LINEAR_CURVE)
+    }
+
+    /// Applies the input's curve to an activation of it.
+    pub(crate) fn shape_linear(
+        &self,
+        input: LinearInputType,
+        activation: LinearInputActivation,
+    ) -> LinearInputActivation {
+        self.linear_curve(input).apply_linear(activation)
+    }
+
+    /// Applies the input's curve to an activation of it.
+    pub(crate) fn shape_vector(
+        &self,
+        input: VectorInputType,
+        activation: VectorInputActivation,
+    ) -> VectorInputActivation {
+        self.vector_curve(input).apply_vector(activation)
+    }
+
+    /// For each entry and curve in `other`, replaces the entry or curve in this, or adds a new one. Where both maps
+    /// bind the same input, `other` wins, so this is used to lay the user's stored preferences over a game's
+    /// defaults. Inputs only bound in this map keep their bindings.
+    ///
+    /// ```
+    /// use lf_gfx::input::{InputMap, KeyCode};
+    ///
+    /// let mut defaults = InputMap::<&str, ()>::empty();
+    /// defaults.assign_linear(KeyCode::KeyW, "forward");
+    /// defaults.assign_linear(KeyCode::Space, "jump");
+    ///
+    /// let mut preferences = InputMap::<&str, ()>::empty();
+    /// preferences.assign_linear(KeyCode::Space, "crouch");
+    ///
+    /// defaults.union(preferences);
+    /// assert_eq!(defaults.get_linear(KeyCode::KeyW), Some(&"forward"));
+    /// assert_eq!(defaults.get_linear(KeyCode::Space), Some(&"crouch"));
+    /// ```
     pub fn union(&mut self, other: Self) {
-        for (input, value) in other.inner.linear_map {
-            self.assign_linear(input, value);
+        // Inputs bound in the other map keep only the actions it binds them to
+        for input in other.inner.linear_map.keys() {
+            self.inner.additional_linear_map.remove(input);
         }
-        for (input, value) in other.inner.vector_map {
-            self.assign_vector(input, value);
+        self.inner.linear_map.extend(other.inner.linear_map);
+        self.inner
+            .additional_linear_map
+            .extend(other.inner.additional_linear_map);
+        self.inner.vector_map.extend(other.inner.vector_map);
+        self.inner
+            .mode_linear_map
+            .extend(other.inner.mode_linear_map);
+        self.inner
+            .mode_vector_map
+            .extend(other.inner.mode_vector_map);
+        self.inner.linear_curves.extend(other.inner.linear_curves);
+        self.inner.vector_curves.extend(other.inner.vector_curves);
+    }
+}
+
+impl<TLinear: PartialEq, TVector: PartialEq> InputMap<TLinear, TVector> {
+    /// Gives every binding which differs between this map and the other, such as to show which bindings the user
+    /// has changed from the defaults. Inputs bound in only one of the maps are included, with no action on the
+    /// other side.
+    ///
+    /// ```
+    /// use lf_gfx::input::{InputMap, KeyCode, LinearInputType};
+    ///
+    /// let mut defaults = InputMap::<&str, ()>::empty();
+    /// defaults.assign_linear(KeyCode::KeyW, "forward");
+    /// defaults.assign_linear(KeyCode::KeyS, "back");
+    ///
+    /// let mut preferences = InputMap::<&str, ()>::empty();
+    /// preferences.assign_linear(KeyCode::KeyW, "forward");
+    /// preferences.assign_linear(KeyCode::KeyS, "jump");
+    ///
+    /// let diff = defaults.diff(&preferences);
+    /// assert_eq!(diff.linear.len(), 1);
+    /// assert_eq!(diff.linear[0].input, LinearInputType::from(KeyCode::KeyS));
+    /// assert_eq!(diff.linear[0].old_action, Some(&"back"));
+    /// assert_eq!(diff.linear[0].new_action, Some(&"jump"));
+    /// assert!(diff.vector.is_empty());
+    /// ```
+    pub fn diff<'a>(&'a self, other: &'a Self) -> InputMapDiff<'a, TLinear, TVector> {
+        InputMapDiff {
+            linear: diff_maps(&self.inner.linear_map, &other.inner.linear_map),
+            vector: diff_maps(&self.inner.vector_map, &other.inner.vector_map),
+            additional_linear: diff_maps(
+                &self.inner.additional_linear_map,
+                &other.inner.additional_linear_map,
+            ),
+            mode_linear: diff_maps(&self.inner.mode_linear_map, &other.inner.mode_linear_map),
+            mode_vector: diff_maps(&self.inner.mode_vector_map, &other.inner.mode_vector_map),
         }
     }
+
+    /// Gives every input bound to the action, sorted by the ordering of [`LinearInputType`], such as to show the
+    /// current bindings on a rebinding screen. Entries tagged with an [`InputMode`] aren't included.
+    ///
+    /// ```
+    /// use lf_gfx::input::{InputMap, KeyCode, LinearInputType};
+    ///
+    /// let mut map = InputMap::<&str, ()>::empty();
+    /// map.assign_linear(KeyCode::KeyW, "forward");
+    /// map.assign_linear(KeyCode::ArrowUp, "forward");
+    /// map.assign_linear(KeyCode::KeyS, "back");
+    ///
+    /// assert_eq!(
+    ///     map.bound_linear_inputs(&"forward"),
+    ///     [LinearInputType::from(KeyCode::KeyW), LinearInputType::from(KeyCode::ArrowUp)],
+    /// );
+    /// assert!(map.bound_linear_inputs(&"jump").is_empty());
+    /// ```
+    pub fn bound_linear_inputs(&self, action: &TLinear) -> Vec<LinearInputType> {
+        let mut inputs = bound_inputs(&self.inner.linear_map, action);
+        inputs.extend(
+            self.inner
+                .additional_linear_map
+                .iter()
+                .filter(|(input, actions)| actions.contains(action) && !inputs.contains(input))
+                .map(|(input, _)| *input)
+                .collect::<Vec<_>>(),
+        );
+        inputs.sort();
+        inputs
+    }
+
+    /// Gives every input bound to the action, sorted by the ordering of [`VectorInputType`]. Entries tagged with an
+    /// [`InputMode`] aren't included. See [`InputMap::bound_linear_inputs`].
+    pub fn bound_vector_inputs(&self, action: &TVector) -> Vec<VectorInputType> {
+        let mut inputs = bound_inputs(&self.inner.vector_map, action);
+        inputs.sort();
+        inputs
+    }
+}
+
+fn bound_inputs<I: Copy, T: PartialEq>(map: &HashMap<I, T>, action: &T) -> Vec<I> {
+    map.iter()
+        .filter(|(_, bound)| *bound == action)
+        .map(|(input, _)| *input)
+        .collect()
+}
+
+fn diff_maps<'a, I: Copy + Eq + std::hash::Hash, T: PartialEq>(
+    old: &'a HashMap<I, T>,
+    new: &'a HashMap<I, T>,
+) -> Vec<InputBindingChange<I, &'a T>> {
+    let changed = old
+        .iter()
+        .filter(|(input, action)| new.get(*input) != Some(*action))
+        .map(|(input, action)| InputBindingChange {
+            input: *input,
+            old_action: Some(action),
+            new_action: new.get(input),
+        });
+    let added = new
+        .iter()
+        .filter(|(input, _)| !old.contains_key(*input))
+        .map(|(input, action)| InputBindingChange {
+            input: *input,
+            old_action: None,
+            new_action: Some(action),
+        });
+    changed.chain(added).collect()
+}
+
+/// A binding which differs between two input maps, given by [`InputMap::diff`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputBindingChange<I, T> {
+    pub input: I,
+    /// The action bound to the input in the map that [`InputMap::diff`] was called on, if any.
+    pub old_action: Option<T>,
+    /// The action bound to the input in the map given to [`InputMap::diff`], if any.
+    pub new_action: Option<T>,
+}
+
+/// Every binding which differs between two input maps, in no particular order, given by [`InputMap::diff`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputMapDiff<'a, TLinear, TVector> {
+    pub linear: Vec<InputBindingChange<LinearInputType, &'a TLinear>>,
+    pub vector: Vec<InputBindingChange<VectorInputType, &'a TVector>>,
+    /// Changes to the actions bound to inputs after the first, given by [`InputMap::assign_linear_additional`].
+    pub additional_linear: Vec<InputBindingChange<LinearInputType, &'a Vec<TLinear>>>,
+    /// Changes to entries tagged with the [`InputMode`] they apply to.
+    pub mode_linear: Vec<InputBindingChange<(InputMode, LinearInputType), &'a TLinear>>,
+    pub mode_vector: Vec<InputBindingChange<(InputMode, VectorInputType), &'a TVector>>,
+}
+
+impl<TLinear, TVector> InputMapDiff<'_, TLinear, TVector> {
+    /// Whether the two maps have exactly the same bindings.
+    pub fn is_empty(&self) -> bool {
+        self.linear.is_empty()
+            && self.vector.is_empty()
+            && self.additional_linear.is_empty()
+            && self.mode_linear.is_empty()
+            && self.mode_vector.is_empty()
+    }
 }
 
 impl<TLinear: Serialize, TVector: Serialize> InputMap<TLinear, TVector> {
+    /// Gives a string representation of this map. The same map always serializes to the same string:
+    ///
+    /// ```
+    /// use lf_gfx::input::{InputMap, KeyCode};
+    ///
+    /// let mut a = InputMap::<&str, ()>::empty();
+    /// a.assign_linear(KeyCode::KeyW, "forward");
+    /// a.assign_linear(KeyCode::KeyS, "back");
+    ///
+    /// let mut b = InputMap::<&str, ()>::empty();
+    /// b.assign_linear(KeyCode::KeyS, "back");
+    /// b.assign_linear(KeyCode::KeyW, "forward");
+    ///
+    /// assert_eq!(a.serialize(), b.serialize());
+    /// ```
     pub fn serialize(&self) -> String {
         serde_json::to_string_pretty(&self.inner).expect("input map serialization failed")
     }
 }
 
+impl<TLinear: Serialize + Clone, TVector: Serialize + Clone> InputMap<TLinear, TVector> {
+    /// Gives this map in a form meant for editing by hand, such as for sharing control schemes. Bindings are
+    /// grouped into tables by device, and named by the input's [`Display`](fmt::Display) implementation, such as
+    /// `KeyW` for [`KeyCode::KeyW`]. The same map always gives the same string, and the file begins with comments
+    /// describing its layout. Read maps back with [`InputMap::from_toml`].
+    ///
+    /// Fails if an action can't be written as TOML, such as the unit type.
+    ///
+    /// ```
+    /// use lf_gfx::input::{InputMap, KeyCode};
+    ///
+    /// let mut map = InputMap::<String, String>::empty();
+    /// map.assign_linear(KeyCode::KeyW, "forward".to_owned());
+    /// map.assign_linear(KeyCode::Space, "jump".to_owned());
+    ///
+    /// let toml = map.to_pretty_toml().unwrap();
+    /// assert!(toml.contains("[linear.keyboard]\nKeyW = \"forward\"\nSpace = \"jump\"\n"));
+    /// ```
+    pub fn to_pretty_toml(&self) -> Result<String, toml::ser::Error> {
+        toml_format::to_pretty_toml(self)
+    }
+}
+
+impl<TLinear: DeserializeOwned, TVector: DeserializeOwned> InputMap<TLinear, TVector> {
+    /// Reads a map given by [`InputMap::to_pretty_toml`], or written by hand in the same layout. Errors give the
+    /// line and column of the problem, including for input names which don't exist and tables that aren't part of
+    /// the layout.
+    ///
+    /// ```
+    /// use lf_gfx::{input::{InputMap, KeyCode, MouseInputType}, InputMode};
+    ///
+    /// let map = InputMap::<String, String>::from_toml(r#"
+    ///     [linear.keyboard]
+    ///     KeyW = "forward"
+    ///     ArrowUp = "forward"
+    ///
+    ///     [modes.UI.linear.mouse]
+    ///     ScrollUp = "previous page"
+    /// "#).unwrap();
+    /// assert_eq!(map.get_linear(KeyCode::ArrowUp), Some(&"forward".to_owned()));
+    /// assert_eq!(
+    ///     map.get_linear_in(InputMode::UI, MouseInputType::ScrollUp),
+    ///     Some(&"previous page".to_owned()),
+    /// );
+    ///
+    /// let error = InputMap::<String, String>::from_toml("[linear.keyboard]\nKeyQQ = \"jump\"\n").err().unwrap();
+    /// assert!(error.to_string().contains("line 2"));
+    /// assert!(error.to_string().contains("unknown name `KeyQQ`"));
+    /// ```
+    pub fn from_toml(s: &str) -> Result<Self, toml::de::Error> {
+        toml_format::from_toml(s)
+    }
+}
+
 impl<'a, TLinear: Deserialize<'a>, TVector: Deserialize<'a>> InputMap<TLinear, TVector> {
+    /// Reads a map from a string given by [`InputMap::serialize`]. Maps stored by earlier versions of the crate,
+    /// without mode-specific entries or response curves, are read with none.
+    ///
+    /// ```
+    /// use lf_gfx::input::{InputMap, KeyCode};
+    ///
+    /// let mut map = InputMap::<String, ()>::empty();
+    /// map.assign_linear(KeyCode::KeyW, "forward".to_owned());
+    ///
+    /// let serialized = map.serialize();
+    /// let deserialized = InputMap::<String, ()>::deserialize(&serialized).unwrap();
+    /// assert!(map.diff(&deserialized).is_empty());
+    /// ```
     pub fn deserialize(s: &'a str) -> Result<Self, serde_json::Error> {
-        let inner = serde_json::from_str::<InputMapInner<TLinear, TVector>>(&s)?;
+        let mut inner = serde_json::from_str::<InputMapInner<TLinear, TVector>>(&s)?;
+        inner.promote_orphaned_actions();
         Ok(Self { inner })
     }
 }
+
+/// Keeps a game's input map in [`crate::local_storage`] as the user's input preferences between runs. Given by
+/// [`crate::Game::input_storage`], which defaults to not storing inputs, as only maps whose actions can be
+/// serialized can be stored.
+pub struct InputMapStorage<TLinear, TVector> {
+    key: String,
+    serialize: fn(&InputMap<TLinear, TVector>) -> String,
+    deserialize: fn(&str) -> Result<InputMap<TLinear, TVector>, serde_json::Error>,
+}
+
+impl<TLinear, TVector> InputMapStorage<TLinear, TVector>
+where
+    TLinear: Serialize + DeserializeOwned,
+    TVector: Serialize + DeserializeOwned,
+{
+    /// Stores input maps under the given local storage key.
+    pub fn new(key: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            serialize: InputMap::serialize,
+            deserialize: |s| InputMap::deserialize(s),
+        }
+    }
+}
+
+impl<TLinear, TVector> InputMapStorage<TLinear, TVector> {
+    /// Combines the stored preferences with the given defaults, then stores the result if it differs from what
+    /// was stored. Serialization is canonical, so unchanged preferences aren't written back.
+    pub(crate) fn load_over(&self, defaults: &mut InputMap<TLinear, TVector>) {
+        let stored = crate::local_storage::load(&self.key);
+        if let Some(user_preferences) = &stored {
+            match (self.deserialize)(user_preferences) {
+                Ok(user_preferences) => defaults.union(user_preferences),
+                Err(err) => log::error!("failed to read stored input preferences: {err}"),
+            }
+        }
+        let serialized = (self.serialize)(defaults);
+        if stored.as_ref() != Some(&serialized) {
+            self.store_serialized(&serialized);
+        }
+    }
+
+    pub(crate) fn store(&self, input_map: &InputMap<TLinear, TVector>) {
+        self.store_serialized(&(self.serialize)(input_map));
+    }
+
+    fn store_serialized(&self, serialized: &str) {
+        if let Err(err) = crate::local_storage::store(&self.key, serialized) {
+            log::error!("failed to store input preferences: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ui_mode_only_handles_ui_bindings() {
+        let mut map = InputMap::<&str, &str>::empty();
+        map.assign_linear(KeyCode::KeyA, "move left");
+        map.assign_linear_in(InputMode::UI, KeyCode::KeyA, "menu left");
+        map.assign_linear(KeyCode::KeyD, "move right");
+        map.assign_vector_in(InputMode::UI, GamepadStickCode::Left, "menu cursor");
+        map.assign_vector(GamepadStickCode::Right, "look");
+
+        let key_a = LinearInputType::from(KeyCode::KeyA);
+        assert!(map.handles_linear_in(InputMode::UI, key_a));
+        assert_eq!(
+            map.get_all_linear_in(InputMode::UI, key_a)
+                .collect::<Vec<_>>(),
+            [&"menu left"]
+        );
+        assert!(!map.handles_linear_in(InputMode::UI, KeyCode::KeyD.into()));
+
+        assert!(map.handles_vector_in(InputMode::UI, GamepadStickCode::Left.into()));
+        assert!(!map.handles_vector_in(InputMode::UI, GamepadStickCode::Right.into()));
+    }
+
+    #[test]
+    fn action_values_stay_held_until_every_input_is_released() {
+        let mut values = ActionValues::new::<&str, &str>();
+        let pressed = LinearInputActivation::clamp(1.0);
+        let released = LinearInputActivation::clamp(0.0);
+
+        values.set_linear(&"forward", KeyCode::KeyW.into(), None, pressed);
+        values.set_linear(&"forward", KeyCode::ArrowUp.into(), None, pressed);
+        values.set_linear(&"forward", KeyCode::KeyW.into(), None, released);
+        assert_eq!(values.linear(&"forward"), pressed);
+
+        values.set_linear(&"forward", KeyCode::ArrowUp.into(), None, released);
+        assert_eq!(values.linear(&"forward"), released);
+    }
+
+    #[test]
+    fn action_values_keep_each_gamepad_separately() {
+        let mut values = ActionValues::new::<&str, &str>();
+        let pressed = LinearInputActivation::clamp(1.0);
+        let released = LinearInputActivation::clamp(0.0);
+        let button = GamepadButtonCode::South.into();
+
+        values.set_linear(&"jump", button, Some(GamepadId(0)), pressed);
+        values.set_linear(&"jump", button, Some(GamepadId(1)), pressed);
+        values.set_linear(&"jump", button, Some(GamepadId(0)), released);
+        assert_eq!(values.linear(&"jump"), pressed);
+
+        values.set_linear(&"jump", button, Some(GamepadId(1)), released);
+        assert_eq!(values.linear(&"jump"), released);
+    }
+
+    #[test]
+    fn action_values_forget_one_off_inputs() {
+        let mut values = ActionValues::new::<&str, &str>();
+        let scrolled = LinearInputActivation::clamp(0.5);
+        let held = LinearInputActivation::clamp(1.0);
+
+        values.set_linear(&"zoom", MouseInputType::ScrollUp.into(), None, scrolled);
+        values.set_linear(&"zoom", KeyCode::Equal.into(), None, held);
+        values.set_vector(
+            &"look",
+            VectorInputType::MouseMove,
+            None,
+            VectorInputActivation::clamp(0.2, 0.1),
+        );
+        values.clear_one_off::<&str, &str>();
+
+        assert_eq!(values.linear(&"zoom"), held);
+        assert_eq!(
+            values.vector(&"look"),
+            VectorInputActivation::clamp(0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn other_modes_handle_every_binding() {
+        let mut map = InputMap::<&str, ()>::empty();
+        map.assign_linear(KeyCode::KeyA, "move left");
+        map.assign_linear_in(InputMode::UI, KeyCode::KeyA, "menu left");
+
+        for mode in [InputMode::Exclusive, InputMode::Unified] {
+            assert!(map.handles_linear_in(mode, KeyCode::KeyA.into()));
+            assert!(map.handles_linear_in(mode, KeyCode::KeyD.into()));
+            assert_eq!(
+                map.get_all_linear_in(mode, KeyCode::KeyA)
+                    .collect::<Vec<_>>(),
+                [&"move left"]
+            );
+        }
+    }
+
+    #[test]
+    fn response_curves_keep_inputs_at_rest() {
+        for curve in [
+            ResponseCurve::Linear,
+            ResponseCurve::Power(0.0),
+            ResponseCurve::Power(-2.0),
+            ResponseCurve::Custom(vec![0.5]),
+            ResponseCurve::Custom(vec![0.3, 1.0]),
+        ] {
+            assert_eq!(curve.apply(0.0), 0.0, "{curve:?}");
+        }
+        assert_eq!(ResponseCurve::Power(-2.0).apply(0.5), 0.5);
+    }
+
+    #[test]
+    fn union_replaces_curves_with_linear() {
+        let mut defaults = InputMap::<&str, ()>::empty();
+        defaults.set_linear_curve(KeyCode::KeyW, ResponseCurve::Power(2.0));
+
+        let mut preferences = InputMap::<&str, ()>::empty();
+        preferences.set_linear_curve(KeyCode::KeyW, ResponseCurve::Linear);
+
+        defaults.union(preferences);
+        assert_eq!(defaults.linear_curve(KeyCode::KeyW), &ResponseCurve::Linear);
+    }
+
+    #[test]
+    fn orphaned_additional_actions_are_promoted() {
+        let map = InputMap::<String, String>::from_toml(
+            "[additional.keyboard]\nSpace = ['jump', 'menu confirm']\nKeyE = []\n",
+        )
+        .unwrap();
+        assert_eq!(
+            map.get_all_linear(KeyCode::Space).collect::<Vec<_>>(),
+            ["jump", "menu confirm"]
+        );
+        assert_eq!(map.get_all_linear(KeyCode::KeyE).count(), 0);
+        assert!(!map
+            .inner
+            .additional_linear_map
+            .contains_key(&KeyCode::KeyE.into()));
+    }
+
+    #[test]
+    fn maps_stored_before_canonical_serialization_still_load() {
+        let map = InputMap::<String, String>::deserialize(
+            r#"{"linear_map": {}, "vector_map": {"MouseMove": "look"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            map.get_vector(VectorInputType::MouseMove),
+            Some(&"look".to_owned())
+        );
+    }
+}
@@ -0,0 +1,374 @@
+//! The human-editable form of an [`InputMap`], given by [`InputMap::to_pretty_toml`]. Bindings are grouped into
+//! tables by the device they come from, keyed by input names given by each input's [`std::fmt::Display`]
+//! implementation, so that files stay readable and give small diffs when a single binding changes.
+use std::{collections::HashMap, fmt::Display, hash::Hash, str::FromStr};
+
+use serde::{
+    de::{DeserializeOwned, Error as _},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use super::{
+    GamepadButtonCode, GamepadStickCode, InputMap, InputMapInner, KeyCode, LinearInputType,
+    MouseInputType, ResponseCurve, VectorInputType,
+};
+use crate::InputMode;
+
+// Written above the bindings, so that people editing the file by hand know what goes where
+const HEADER: &str = "\
+# Input bindings. Each entry maps an input to the action it triggers.
+#
+# [linear.keyboard]  keys, by their position on a US keyboard, such as `KeyW` or `Space`
+# [linear.mouse]     mouse movement, scrolling and buttons, such as `ScrollUp` or `ButtonLeft`
+# [linear.gamepad]   gamepad buttons, such as `South` or `LeftTrigger`
+# [vector]           `mouse` for mouse movement
+# [vector.gamepad]   gamepad sticks, `Left` or `Right`
+#
+# Inputs which trigger several actions list the actions after the first under [additional], such as
+# [additional.keyboard] with `Space = ['menu confirm']`. Inputs only listed under [additional] take the first
+# action listed as their first action.
+#
+# Bindings under [modes.Exclusive], [modes.UI] or [modes.Unified] only apply in that input mode, and entries under
+# [curves] give the response curves for inputs.
+
+";
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+    default,
+    deny_unknown_fields,
+    bound(serialize = "T: Serialize", deserialize = "T: DeserializeOwned")
+)]
+struct LinearTable<T> {
+    #[serde(
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_named",
+        deserialize_with = "deserialize_named"
+    )]
+    keyboard: HashMap<KeyCode, T>,
+    #[serde(
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_named",
+        deserialize_with = "deserialize_named"
+    )]
+    mouse: HashMap<MouseInputType, T>,
+    #[serde(
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_named",
+        deserialize_with = "deserialize_named"
+    )]
+    gamepad: HashMap<GamepadButtonCode, T>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+    default,
+    deny_unknown_fields,
+    bound(serialize = "T: Serialize", deserialize = "T: DeserializeOwned")
+)]
+struct VectorTable<T> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mouse: Option<T>,
+    #[serde(
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_named",
+        deserialize_with = "deserialize_named"
+    )]
+    gamepad: HashMap<GamepadStickCode, T>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+    default,
+    deny_unknown_fields,
+    bound(
+        serialize = "TLinear: Serialize, TVector: Serialize",
+        deserialize = "TLinear: DeserializeOwned, TVector: DeserializeOwned"
+    )
+)]
+struct Bindings<TLinear, TVector> {
+    #[serde(skip_serializing_if = "LinearTable::is_empty")]
+    linear: LinearTable<TLinear>,
+    #[serde(skip_serializing_if = "VectorTable::is_empty")]
+    vector: VectorTable<TVector>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(
+    default,
+    deny_unknown_fields,
+    bound(
+        serialize = "TLinear: Serialize, TVector: Serialize",
+        deserialize = "TLinear: DeserializeOwned, TVector: DeserializeOwned"
+    )
+)]
+struct TomlInputMap<TLinear, TVector> {
+    #[serde(skip_serializing_if = "LinearTable::is_empty")]
+    linear: LinearTable<TLinear>,
+    #[serde(skip_serializing_if = "VectorTable::is_empty")]
+    vector: VectorTable<TVector>,
+    #[serde(
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_named",
+        deserialize_with = "deserialize_named"
+    )]
+    modes: HashMap<InputMode, Bindings<TLinear, TVector>>,
+    #[serde(skip_serializing_if = "LinearTable::is_empty")]
+    additional: LinearTable<Vec<TLinear>>,
+    #[serde(skip_serializing_if = "Bindings::is_empty")]
+    curves: Bindings<ResponseCurve, ResponseCurve>,
+}
+
+impl<T> Default for LinearTable<T> {
+    fn default() -> Self {
+        Self {
+            keyboard: HashMap::new(),
+            mouse: HashMap::new(),
+            gamepad: HashMap::new(),
+        }
+    }
+}
+
+impl<T> LinearTable<T> {
+    fn is_empty(&self) -> bool {
+        self.keyboard.is_empty() && self.mouse.is_empty() && self.gamepad.is_empty()
+    }
+
+    fn insert(&mut self, input: LinearInputType, value: T) {
+        match input {
+            LinearInputType::KnownKeyboard(key) => self.keyboard.insert(key, value),
+            LinearInputType::Mouse(mouse) => self.mouse.insert(mouse, value),
+            LinearInputType::GamepadButton(button) => self.gamepad.insert(button, value),
+        };
+    }
+
+    fn from_map<'a>(map: impl IntoIterator<Item = (&'a LinearInputType, &'a T)>) -> Self
+    where
+        T: Clone + 'a,
+    {
+        let mut table = Self::default();
+        for (input, value) in map {
+            table.insert(*input, value.clone());
+        }
+        table
+    }
+
+    fn into_map(self) -> impl Iterator<Item = (LinearInputType, T)> {
+        let keyboard = self
+            .keyboard
+            .into_iter()
+            .map(|(key, value)| (key.into(), value));
+        let mouse = self
+            .mouse
+            .into_iter()
+            .map(|(mouse, value)| (mouse.into(), value));
+        let gamepad = self
+            .gamepad
+            .into_iter()
+            .map(|(button, value)| (button.into(), value));
+        keyboard.chain(mouse).chain(gamepad)
+    }
+}
+
+impl<T> Default for VectorTable<T> {
+    fn default() -> Self {
+        Self {
+            mouse: None,
+            gamepad: HashMap::new(),
+        }
+    }
+}
+
+impl<T> VectorTable<T> {
+    fn is_empty(&self) -> bool {
+        self.mouse.is_none() && self.gamepad.is_empty()
+    }
+
+    fn insert(&mut self, input: VectorInputType, value: T) {
+        match input {
+            VectorInputType::MouseMove => self.mouse = Some(value),
+            VectorInputType::GamepadStick(stick) => {
+                self.gamepad.insert(stick, value);
+            }
+        }
+    }
+
+    fn from_map<'a>(map: impl IntoIterator<Item = (&'a VectorInputType, &'a T)>) -> Self
+    where
+        T: Clone + 'a,
+    {
+        let mut table = Self::default();
+        for (input, value) in map {
+            table.insert(*input, value.clone());
+        }
+        table
+    }
+
+    fn into_map(self) -> impl Iterator<Item = (VectorInputType, T)> {
+        let mouse = self
+            .mouse
+            .into_iter()
+            .map(|value| (VectorInputType::MouseMove, value));
+        let gamepad = self
+            .gamepad
+            .into_iter()
+            .map(|(stick, value)| (stick.into(), value));
+        mouse.chain(gamepad)
+    }
+}
+
+impl<TLinear, TVector> Default for Bindings<TLinear, TVector> {
+    fn default() -> Self {
+        Self {
+            linear: LinearTable::default(),
+            vector: VectorTable::default(),
+        }
+    }
+}
+
+impl<TLinear, TVector> Bindings<TLinear, TVector> {
+    fn is_empty(&self) -> bool {
+        self.linear.is_empty() && self.vector.is_empty()
+    }
+}
+
+impl<TLinear, TVector> Default for TomlInputMap<TLinear, TVector> {
+    fn default() -> Self {
+        Self {
+            linear: LinearTable::default(),
+            vector: VectorTable::default(),
+            modes: HashMap::new(),
+            additional: LinearTable::default(),
+            curves: Bindings::default(),
+        }
+    }
+}
+
+impl<'a, TLinear: Clone, TVector: Clone> From<&'a InputMap<TLinear, TVector>>
+    for TomlInputMap<TLinear, TVector>
+{
+    fn from(map: &'a InputMap<TLinear, TVector>) -> Self {
+        let inner = &map.inner;
+
+        let mut modes = HashMap::<InputMode, Bindings<TLinear, TVector>>::new();
+        for ((mode, input), value) in &inner.mode_linear_map {
+            let bindings = modes.entry(*mode).or_default();
+            bindings.linear.insert(*input, value.clone());
+        }
+        for ((mode, input), value) in &inner.mode_vector_map {
+            let bindings = modes.entry(*mode).or_default();
+            bindings.vector.insert(*input, value.clone());
+        }
+
+        Self {
+            linear: LinearTable::from_map(&inner.linear_map),
+            vector: VectorTable::from_map(&inner.vector_map),
+            modes,
+            additional: LinearTable::from_map(&inner.additional_linear_map),
+            curves: Bindings {
+                linear: LinearTable::from_map(&inner.linear_curves),
+                vector: VectorTable::from_map(&inner.vector_curves),
+            },
+        }
+    }
+}
+
+impl<TLinear, TVector> From<TomlInputMap<TLinear, TVector>> for InputMap<TLinear, TVector> {
+    fn from(map: TomlInputMap<TLinear, TVector>) -> Self {
+        let mut mode_linear_map = HashMap::new();
+        let mut mode_vector_map = HashMap::new();
+        for (mode, bindings) in map.modes {
+            mode_linear_map.extend(
+                bindings
+                    .linear
+                    .into_map()
+                    .map(|(input, value)| ((mode, input), value)),
+            );
+            mode_vector_map.extend(
+                bindings
+                    .vector
+                    .into_map()
+                    .map(|(input, value)| ((mode, input), value)),
+            );
+        }
+
+        let mut inner = InputMapInner {
+            linear_map: map.linear.into_map().collect(),
+            vector_map: map.vector.into_map().collect(),
+            additional_linear_map: map.additional.into_map().collect(),
+            mode_linear_map,
+            mode_vector_map,
+            linear_curves: map.curves.linear.into_map().collect(),
+            vector_curves: map.curves.vector.into_map().collect(),
+        };
+        inner.promote_orphaned_actions();
+        Self { inner }
+    }
+}
+
+/// Writes a map keyed by input names, sorted by name so that the same map always gives the same file.
+fn serialize_named<S, K, V>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    K: Display,
+    V: Serialize,
+{
+    let mut entries = map
+        .iter()
+        .map(|(input, value)| (input.to_string(), value))
+        .collect::<Vec<_>>();
+    entries.sort_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs));
+
+    serializer.collect_map(entries)
+}
+
+/// Reads a map keyed by input names, failing on names which aren't known.
+fn deserialize_named<'de, D, K, V>(deserializer: D) -> Result<HashMap<K, V>, D::Error>
+where
+    D: Deserializer<'de>,
+    K: FromStr + Hash + Eq,
+    K::Err: Display,
+    V: Deserialize<'de>,
+{
+    let entries = HashMap::<Named<K>, V>::deserialize(deserializer)?;
+    Ok(entries
+        .into_iter()
+        .map(|(Named(input), value)| (input, value))
+        .collect())
+}
+
+// Parses names as they are read, rather than after the whole table has been, so that errors point at the name
+#[derive(PartialEq, Eq, Hash)]
+struct Named<K>(K);
+
+impl<'de, K: FromStr> Deserialize<'de> for Named<K>
+where
+    K::Err: Display,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map(Self).map_err(D::Error::custom)
+    }
+}
+
+pub(super) fn to_pretty_toml<TLinear, TVector>(
+    map: &InputMap<TLinear, TVector>,
+) -> Result<String, toml::ser::Error>
+where
+    TLinear: Serialize + Clone,
+    TVector: Serialize + Clone,
+{
+    let body = toml::to_string_pretty(&TomlInputMap::from(map))?;
+    Ok(format!("{HEADER}{body}"))
+}
+
+pub(super) fn from_toml<TLinear, TVector>(
+    s: &str,
+) -> Result<InputMap<TLinear, TVector>, toml::de::Error>
+where
+    TLinear: DeserializeOwned,
+    TVector: DeserializeOwned,
+{
+    let map = toml::from_str::<TomlInputMap<TLinear, TVector>>(s)?;
+    Ok(map.into())
+}
@@ -0,0 +1,67 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+/// A cloneable handle used to report how far through loading the game is, so that a loading screen can be shown
+/// while assets load in the background.
+///
+/// Progress starts complete, so games which load everything in [`super::Game::init`] are unaffected. To show a
+/// loading screen, set the progress to `0.0` in `init`, then clone this handle into the threads or tasks doing the
+/// loading and advance it to `1.0`. Until it reaches `1.0`, [`super::Game::loading_frame`] is drawn instead of
+/// [`super::Game::render_to`].
+#[derive(Clone)]
+pub struct LoadingProgress {
+    // The `f32` progress, stored as its bits
+    inner: Arc<AtomicU32>,
+}
+
+impl LoadingProgress {
+    pub(crate) fn new() -> Self {
+        Self {
+            inner: Arc::new(AtomicU32::new(1.0f32.to_bits())),
+        }
+    }
+
+    /// The current progress, from `0.0` to `1.0`.
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.inner.load(Ordering::SeqCst))
+    }
+
+    /// Sets the current progress, clamped to be from `0.0` to `1.0`. Progress which isn't finite, such as from
+    /// dividing by zero items to load, is ignored and logged.
+    pub fn set(&self, progress: f32) {
+        if !progress.is_finite() {
+            log::warn!("ignoring loading progress of {progress}");
+            return;
+        }
+        let progress = progress.clamp(0.0, 1.0);
+        self.inner.store(progress.to_bits(), Ordering::SeqCst)
+    }
+
+    /// Marks loading as complete.
+    pub fn finish(&self) {
+        self.set(1.0)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.get() >= 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_finite_progress_is_ignored() {
+        let progress = LoadingProgress::new();
+        progress.set(0.5);
+        progress.set(f32::NAN);
+        progress.set(f32::INFINITY);
+        assert_eq!(progress.get(), 0.5);
+
+        progress.finish();
+        assert!(progress.is_complete());
+    }
+}
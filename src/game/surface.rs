@@ -1,7 +1,37 @@
-//! On web, we want to be able to resize/recreate the surface locklessly. We do this here.
+//! On web, we want to be able to resize/recreate the surface locklessly. We do this here. Every change to the
+//! surface's configuration, including resizes, goes through the same deferred state machine, so that competing
+//! changes can't race each other.
 
 use std::sync::{atomic::AtomicU32, Arc};
 
+/// The number of times we reconfigure the surface and try again within a single frame when told it is outdated.
+const MAX_OUTDATED_RETRIES: usize = 1;
+
+/// The number of frames we wait for submitted work to finish before resizing. If the queue never reports the
+/// work as done, for example because nothing more is submitted, the resize is applied anyway after this many frames
+/// so that we don't stop drawing forever.
+const MAX_QUEUED_RESIZE_FRAMES: u32 = 3;
+
+/// Why a requested surface configuration can't be applied, given the capabilities of the surface.
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum SurfaceConfigError {
+    #[error("surface format {0:?} is not supported")]
+    UnsupportedFormat(wgpu::TextureFormat),
+    #[error("surface usages {0:?} are not supported")]
+    UnsupportedUsage(wgpu::TextureUsages),
+    #[error("present mode {0:?} is not supported")]
+    UnsupportedPresentMode(wgpu::PresentMode),
+    #[error("alpha mode {0:?} is not supported")]
+    UnsupportedAlphaMode(wgpu::CompositeAlphaMode),
+    #[error("view format {view_format:?} differs from surface format {format:?} by more than being sRGB")]
+    InvalidViewFormat {
+        format: wgpu::TextureFormat,
+        view_format: wgpu::TextureFormat,
+    },
+    #[error("surface size must not be zero")]
+    ZeroSize,
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 enum ResizableSurfaceState {
     Active,
@@ -28,76 +58,276 @@ impl ResizableSurfaceState {
     }
 }
 
+/// What the surface should do before drawing a frame, given by [`ReconfigureState::poll`].
+#[derive(Debug, PartialEq)]
+enum SurfaceAction<C> {
+    /// A change is still waiting on submitted work, so no frame should be drawn.
+    Wait,
+    /// The surface should be configured again, with the new configuration if there is one, before drawing.
+    Reconfigure(Option<C>),
+    /// The surface can be drawn to as it is.
+    Draw,
+}
+
+/// Tracks when a queued configuration change can be applied. Kept apart from the surface so that the transitions
+/// can be checked without a GPU.
+struct ReconfigureState<C> {
+    state: Arc<AtomicU32>,
+    // The configuration applied once submitted work has finished
+    pending_config: Option<C>,
+    // The number of frames we have waited for submitted work to finish since the last change was queued
+    queued_frames: u32,
+}
+
+impl<C> ReconfigureState<C> {
+    fn new() -> Self {
+        Self {
+            state: Arc::new(AtomicU32::new(ResizableSurfaceState::Active.encode())),
+            pending_config: None,
+            queued_frames: 0,
+        }
+    }
+
+    fn pending_config(&self) -> Option<&C> {
+        self.pending_config.as_ref()
+    }
+
+    /// Queues a change, giving the callback to invoke once work submitted so far has finished.
+    fn queue(&mut self, config: C) -> impl FnOnce() + Send + 'static {
+        self.state.store(
+            ResizableSurfaceState::ResizingQueued.encode(),
+            std::sync::atomic::Ordering::SeqCst,
+        );
+        self.pending_config = Some(config);
+        self.queued_frames = 0;
+
+        let state = Arc::clone(&self.state);
+        move || {
+            let _ = state.compare_exchange(
+                ResizableSurfaceState::ResizingQueued.encode(),
+                ResizableSurfaceState::Inactive.encode(),
+                std::sync::atomic::Ordering::SeqCst,
+                std::sync::atomic::Ordering::SeqCst,
+            );
+        }
+    }
+
+    /// Called before each frame, to find whether the surface can be drawn to.
+    fn poll(&mut self) -> SurfaceAction<C> {
+        let state = self.state.load(std::sync::atomic::Ordering::SeqCst);
+        match ResizableSurfaceState::decode(state) {
+            ResizableSurfaceState::Active => SurfaceAction::Draw,
+            ResizableSurfaceState::ResizingQueued
+                if self.queued_frames < MAX_QUEUED_RESIZE_FRAMES =>
+            {
+                self.queued_frames += 1;
+                SurfaceAction::Wait
+            }
+            state => {
+                if state == ResizableSurfaceState::ResizingQueued {
+                    log::debug!(
+                        "submitted work wasn't reported as done, reconfiguring surface anyway"
+                    );
+                }
+                // If the callback fires after this then it finds us active, and leaves us be
+                self.state.store(
+                    ResizableSurfaceState::Active.encode(),
+                    std::sync::atomic::Ordering::SeqCst,
+                );
+                SurfaceAction::Reconfigure(self.pending_config.take())
+            }
+        }
+    }
+}
+
 pub(super) struct ResizableSurface<'window> {
     surface: wgpu::Surface<'window>,
 
     config: wgpu::SurfaceConfiguration,
+    // Queried once, as querying can be slow on some platforms
+    capabilities: wgpu::SurfaceCapabilities,
 
-    state: Arc<AtomicU32>,
-    new_size: winit::dpi::PhysicalSize<u32>,
+    reconfigure: ReconfigureState<wgpu::SurfaceConfiguration>,
 }
 
 impl<'window> ResizableSurface<'window> {
     pub(super) fn new(
         surface: wgpu::Surface<'window>,
+        adapter: &wgpu::Adapter,
         device: &wgpu::Device,
         config: wgpu::SurfaceConfiguration,
     ) -> Self {
         surface.configure(&device, &config);
+        let capabilities = surface.get_capabilities(adapter);
         Self {
             surface,
             config,
-            state: Arc::new(AtomicU32::new(ResizableSurfaceState::Active.encode())),
-            new_size: winit::dpi::PhysicalSize::new(0, 0),
+            capabilities,
+            reconfigure: ReconfigureState::new(),
         }
     }
 
+    /// The configuration that the surface will have once any queued change is applied. Changes should be made to
+    /// this, rather than the current configuration, so that they don't undo changes that are still queued.
+    pub(super) fn target_config(&self) -> &wgpu::SurfaceConfiguration {
+        self.reconfigure.pending_config().unwrap_or(&self.config)
+    }
+
+    /// Changes only the size of the surface, keeping the rest of the target configuration.
     pub(super) fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>, queue: &wgpu::Queue) {
-        self.state.store(
-            ResizableSurfaceState::ResizingQueued.encode(),
-            std::sync::atomic::Ordering::SeqCst,
+        let mut config = self.target_config().clone();
+        config.width = new_size.width;
+        config.height = new_size.height;
+        self.queue_config_change(config, queue);
+    }
+
+    /// Queues a change to the surface's configuration, which is applied once work submitted so far has finished,
+    /// as with resizing. Fails, leaving the target configuration unchanged, if the surface doesn't support the
+    /// configuration.
+    pub(super) fn request_config_change(
+        &mut self,
+        new_config: wgpu::SurfaceConfiguration,
+        queue: &wgpu::Queue,
+    ) -> Result<(), SurfaceConfigError> {
+        self.validate(&new_config)?;
+        self.queue_config_change(new_config, queue);
+        Ok(())
+    }
+
+    fn validate(&self, config: &wgpu::SurfaceConfiguration) -> Result<(), SurfaceConfigError> {
+        let capabilities = &self.capabilities;
+        if config.width == 0 || config.height == 0 {
+            return Err(SurfaceConfigError::ZeroSize);
+        }
+        if !capabilities.formats.contains(&config.format) {
+            return Err(SurfaceConfigError::UnsupportedFormat(config.format));
+        }
+        if !capabilities.usages.contains(config.usage) {
+            return Err(SurfaceConfigError::UnsupportedUsage(
+                config.usage.difference(capabilities.usages),
+            ));
+        }
+        // The automatic modes fall back to one which is always supported
+        let is_auto_present_mode = matches!(
+            config.present_mode,
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync
         );
-        self.new_size = new_size;
+        if !is_auto_present_mode && !capabilities.present_modes.contains(&config.present_mode) {
+            return Err(SurfaceConfigError::UnsupportedPresentMode(
+                config.present_mode,
+            ));
+        }
+        if config.alpha_mode != wgpu::CompositeAlphaMode::Auto
+            && !capabilities.alpha_modes.contains(&config.alpha_mode)
+        {
+            return Err(SurfaceConfigError::UnsupportedAlphaMode(config.alpha_mode));
+        }
+        for view_format in &config.view_formats {
+            if view_format.remove_srgb_suffix() != config.format.remove_srgb_suffix() {
+                return Err(SurfaceConfigError::InvalidViewFormat {
+                    format: config.format,
+                    view_format: *view_format,
+                });
+            }
+        }
+        Ok(())
+    }
 
-        let state_clone = Arc::clone(&self.state);
-        queue.on_submitted_work_done(move || {
-            let _ = state_clone.compare_exchange(
-                ResizableSurfaceState::ResizingQueued.encode(),
-                ResizableSurfaceState::Inactive.encode(),
-                std::sync::atomic::Ordering::SeqCst,
-                std::sync::atomic::Ordering::SeqCst,
-            );
-        })
+    fn queue_config_change(&mut self, new_config: wgpu::SurfaceConfiguration, queue: &wgpu::Queue) {
+        queue.on_submitted_work_done(self.reconfigure.queue(new_config));
     }
 
     pub(super) fn get(&mut self, device: &wgpu::Device) -> Option<&wgpu::Surface> {
-        let state = self.state.load(std::sync::atomic::Ordering::SeqCst);
-        let state = ResizableSurfaceState::decode(state);
-
-        // Still waiting on resizing
-        match state {
-            ResizableSurfaceState::ResizingQueued => {
-                return None;
-            }
-            ResizableSurfaceState::Inactive => {
-                self.config.width = self.new_size.width;
-                self.config.height = self.new_size.height;
+        match self.reconfigure.poll() {
+            SurfaceAction::Wait => None,
+            SurfaceAction::Reconfigure(config) => {
+                if let Some(config) = config {
+                    self.config = config;
+                }
                 self.surface.configure(device, &self.config);
+                Some(&self.surface)
+            }
+            SurfaceAction::Draw => Some(&self.surface),
+        }
+    }
 
-                let res = self.state.compare_exchange(
-                    ResizableSurfaceState::Inactive.encode(),
-                    ResizableSurfaceState::Active.encode(),
-                    std::sync::atomic::Ordering::SeqCst,
-                    std::sync::atomic::Ordering::SeqCst,
-                );
+    /// Gets the next texture to draw to, or `None` if we are in the process of resizing. Some platforms keep
+    /// reporting a surface as outdated until it is reconfigured, so we do that here rather than waiting on
+    /// a full resize.
+    pub(super) fn get_current_texture(
+        &mut self,
+        device: &wgpu::Device,
+    ) -> Option<Result<wgpu::SurfaceTexture, wgpu::SurfaceError>> {
+        let surface = self.get(device)?;
 
-                if res.is_ok() {
-                    Some(&self.surface)
-                } else {
-                    None
-                }
+        let mut res = surface.get_current_texture();
+        for _ in 0..MAX_OUTDATED_RETRIES {
+            if !matches!(res, Err(wgpu::SurfaceError::Outdated)) {
+                break;
             }
-            ResizableSurfaceState::Active => Some(&self.surface),
+
+            log::debug!("surface outdated, reconfiguring");
+            self.surface.configure(device, &self.config);
+            res = self.surface.get_current_texture();
         }
+
+        Some(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_wait_for_submitted_work() {
+        let mut state = ReconfigureState::new();
+        assert_eq!(state.poll(), SurfaceAction::Draw);
+
+        let work_done = state.queue(1);
+        assert_eq!(state.pending_config(), Some(&1));
+        assert_eq!(state.poll(), SurfaceAction::Wait);
+
+        work_done();
+        assert_eq!(state.poll(), SurfaceAction::Reconfigure(Some(1)));
+        assert_eq!(state.pending_config(), None);
+        assert_eq!(state.poll(), SurfaceAction::Draw);
+    }
+
+    #[test]
+    fn changes_apply_anyway_if_work_is_never_reported_done() {
+        let mut state = ReconfigureState::new();
+        let _work_done = state.queue(1);
+        for _ in 0..MAX_QUEUED_RESIZE_FRAMES {
+            assert_eq!(state.poll(), SurfaceAction::Wait);
+        }
+        assert_eq!(state.poll(), SurfaceAction::Reconfigure(Some(1)));
+        assert_eq!(state.poll(), SurfaceAction::Draw);
+    }
+
+    #[test]
+    fn late_callbacks_leave_the_surface_active() {
+        let mut state = ReconfigureState::new();
+        let work_done = state.queue(1);
+        for _ in 0..MAX_QUEUED_RESIZE_FRAMES {
+            state.poll();
+        }
+        assert_eq!(state.poll(), SurfaceAction::Reconfigure(Some(1)));
+
+        work_done();
+        assert_eq!(state.poll(), SurfaceAction::Draw);
+    }
+
+    #[test]
+    fn later_changes_replace_earlier_ones() {
+        let mut state = ReconfigureState::new();
+        let first_done = state.queue(1);
+        assert_eq!(state.poll(), SurfaceAction::Wait);
+
+        let second_done = state.queue(2);
+        assert_eq!(state.pending_config(), Some(&2));
+        first_done();
+        second_done();
+        assert_eq!(state.poll(), SurfaceAction::Reconfigure(Some(2)));
     }
 }
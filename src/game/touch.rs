@@ -0,0 +1,77 @@
+//! Recognises two-finger gestures on touchscreens, which winit only gives as the positions of individual touches.
+
+use winit::{dpi::PhysicalPosition, event::TouchPhase};
+
+use super::Gesture;
+
+/// Where the two fingers making a gesture were, as of the last gesture given.
+#[derive(Debug, Clone, Copy)]
+struct TwoFingers {
+    centroid: PhysicalPosition<f64>,
+    distance: f64,
+}
+
+/// Tracks the touches on the screen, giving pinches and pans made with the first two fingers down.
+#[derive(Default)]
+pub(super) struct TouchTracker {
+    // In the order the fingers were put down
+    touches: Vec<(u64, PhysicalPosition<f64>)>,
+    // Reset whenever a finger is added or removed, so the gesture continues from the new fingers without jumping
+    last: Option<TwoFingers>,
+}
+
+impl TouchTracker {
+    /// Records a touch, giving the gestures it completes.
+    pub(super) fn touch(&mut self, touch: &winit::event::Touch) -> Vec<Gesture> {
+        match touch.phase {
+            TouchPhase::Started => {
+                self.touches.push((touch.id, touch.location));
+                self.last = self.two_fingers();
+                Vec::new()
+            }
+            TouchPhase::Moved => {
+                let Some((_, location)) = self.touches.iter_mut().find(|(id, _)| *id == touch.id)
+                else {
+                    return Vec::new();
+                };
+                *location = touch.location;
+                self.moved()
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.touches.retain(|(id, _)| *id != touch.id);
+                self.last = self.two_fingers();
+                Vec::new()
+            }
+        }
+    }
+
+    fn two_fingers(&self) -> Option<TwoFingers> {
+        let [(_, a), (_, b), ..] = self.touches.as_slice() else {
+            return None;
+        };
+        Some(TwoFingers {
+            centroid: PhysicalPosition::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0),
+            distance: (a.x - b.x).hypot(a.y - b.y),
+        })
+    }
+
+    fn moved(&mut self) -> Vec<Gesture> {
+        let (Some(last), Some(current)) = (self.last, self.two_fingers()) else {
+            return Vec::new();
+        };
+        self.last = Some(current);
+
+        let mut gestures = Vec::new();
+        if last.distance > 0.0 && current.distance != last.distance {
+            gestures.push(Gesture::Pinch {
+                delta: current.distance / last.distance - 1.0,
+            });
+        }
+        let dx = current.centroid.x - last.centroid.x;
+        let dy = current.centroid.y - last.centroid.y;
+        if dx != 0.0 || dy != 0.0 {
+            gestures.push(Gesture::Pan { dx, dy });
+        }
+        gestures
+    }
+}
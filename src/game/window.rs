@@ -1,7 +1,113 @@
 use std::sync::Arc;
 
+use serde::{Deserialize, Serialize};
 use wgpu::Device;
-use winit::{event_loop::EventLoopWindowTarget, window::WindowBuilder};
+use winit::{
+    dpi::{LogicalPosition, PhysicalPosition, PhysicalSize},
+    event_loop::EventLoopWindowTarget,
+    monitor::{MonitorHandle, VideoMode},
+    window::{Fullscreen, UserAttentionType, WindowBuilder},
+};
+
+/// The local storage key that the window's size and position are kept under.
+const WINDOW_GEOMETRY_KEY: &str = "window_geometry";
+
+/// The size and placement of the window, remembered between runs when [`super::Game::remember_window_geometry`]
+/// is enabled. The size and position are those of the window when it was last neither maximized nor fullscreen,
+/// so that restoring from a maximized window gives back the size the user chose.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct WindowGeometry {
+    size: PhysicalSize<u32>,
+    position: Option<PhysicalPosition<i32>>,
+    maximized: bool,
+    fullscreen: bool,
+}
+
+impl WindowGeometry {
+    pub(crate) fn of(window: &winit::window::Window) -> Self {
+        Self {
+            size: window.inner_size(),
+            position: window.outer_position().ok(),
+            maximized: window.is_maximized(),
+            fullscreen: window.fullscreen().is_some(),
+        }
+    }
+
+    fn load() -> Option<Self> {
+        let stored = crate::local_storage::load(WINDOW_GEOMETRY_KEY)?;
+        match serde_json::from_str(&stored) {
+            Ok(geometry) => Some(geometry),
+            Err(err) => {
+                log::error!("failed to read stored window geometry: {err}");
+                None
+            }
+        }
+    }
+
+    pub(crate) fn store(&self) {
+        let serialized = serde_json::to_string(self).expect("geometry is always serializable");
+        if let Err(err) = crate::local_storage::store(WINDOW_GEOMETRY_KEY, &serialized) {
+            log::error!("failed to store window geometry: {err}");
+        }
+    }
+
+    /// Tracks a change in the window's size or position. Changes while maximized or fullscreen only update
+    /// those flags, keeping the size and position to restore to.
+    pub(crate) fn update(&mut self, window: &winit::window::Window) {
+        let current = Self::of(window);
+        self.maximized = current.maximized;
+        self.fullscreen = current.fullscreen;
+        if !current.maximized
+            && !current.fullscreen
+            && current.size.width > 0
+            && current.size.height > 0
+        {
+            self.size = current.size;
+            self.position = current.position;
+        }
+    }
+
+    /// Keeps the window on a monitor that is still connected. If the remembered position isn't on any monitor,
+    /// it is dropped and the OS chooses where to place the window. The size is shrunk to fit the monitor.
+    fn clamp_to_monitors(mut self, monitors: impl Iterator<Item = MonitorHandle>) -> Self {
+        let Some(position) = self.position else {
+            return self;
+        };
+
+        let monitor = monitors.into_iter().find(|monitor| {
+            let monitor_position = monitor.position();
+            let monitor_size = monitor.size();
+            position.x >= monitor_position.x
+                && position.y >= monitor_position.y
+                && position.x < monitor_position.x + monitor_size.width as i32
+                && position.y < monitor_position.y + monitor_size.height as i32
+        });
+
+        match monitor {
+            Some(monitor) => {
+                let monitor_size = monitor.size();
+                self.size.width = self.size.width.min(monitor_size.width);
+                self.size.height = self.size.height.min(monitor_size.height);
+            }
+            None => self.position = None,
+        }
+
+        self
+    }
+
+    fn apply_to(&self, mut builder: WindowBuilder) -> WindowBuilder {
+        builder = builder
+            .with_inner_size(self.size)
+            .with_maximized(self.maximized);
+        if let Some(position) = self.position {
+            builder = builder.with_position(position);
+        }
+        if self.fullscreen {
+            builder = builder.with_fullscreen(Some(Fullscreen::Borderless(None)));
+        }
+        builder
+    }
+}
 
 pub struct GameWindow {
     window: Arc<winit::window::Window>,
@@ -12,7 +118,20 @@ pub struct GameWindow {
 
 impl GameWindow {
     pub(super) fn new<T: super::Game>(window_target: &EventLoopWindowTarget<()>) -> Self {
-        let builder = WindowBuilder::new().with_title(T::title());
+        let builder = WindowBuilder::new()
+            .with_title(T::title())
+            .with_visible(!T::start_hidden());
+        // The canvas is sized and placed by the page on web, so there is nothing to restore
+        #[cfg(not(target_arch = "wasm32"))]
+        let builder = match T::remember_window_geometry()
+            .then(WindowGeometry::load)
+            .flatten()
+        {
+            Some(geometry) => geometry
+                .clamp_to_monitors(window_target.available_monitors())
+                .apply_to(builder),
+            None => builder,
+        };
         #[cfg(target_arch = "wasm32")]
         let canvas = crate::wasm::get_canvas();
         #[cfg(target_arch = "wasm32")]
@@ -26,13 +145,142 @@ impl GameWindow {
         let window = builder.build(window_target).unwrap();
         let window = Arc::new(window);
 
-        Self {
+        let window = Self {
             window,
             #[cfg(target_arch = "wasm32")]
             canvas,
+        };
+        if T::start_hidden() {
+            window.set_window_visible(false);
+        }
+        window
+    }
+
+    /// Shows or hides the window. On web, the canvas is hidden using its `visibility` style, so that it keeps
+    /// its place in the page layout.
+    pub(crate) fn set_window_visible(&self, visible: bool) {
+        self.window.set_visible(visible);
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            use web_sys::HtmlElement;
+
+            let visibility = if visible { "visible" } else { "hidden" };
+            let res = AsRef::<HtmlElement>::as_ref(&self.canvas)
+                .style()
+                .set_property("visibility", visibility);
+            if res.is_err() {
+                log::error!("failed to set canvas visibility");
+            }
         }
     }
 
+    /// The size of the primary monitor. On web, this is the size of the screen that the page is on.
+    pub fn primary_monitor_size(&self) -> Option<PhysicalSize<u32>> {
+        #[cfg(target_arch = "wasm32")]
+        return crate::wasm::screen_size();
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.window.primary_monitor().map(|monitor| monitor.size());
+    }
+
+    /// All of the monitors connected to the system. On web, where monitors can't be enumerated, this is just
+    /// the screen that the page is on.
+    pub fn available_monitors(&self) -> Vec<MonitorInfo> {
+        #[cfg(target_arch = "wasm32")]
+        return crate::wasm::screen_size()
+            .map(|size| MonitorInfo {
+                id: MonitorId {
+                    name: None,
+                    position: PhysicalPosition::new(0, 0),
+                },
+                name: None,
+                size,
+                position: PhysicalPosition::new(0, 0),
+                scale_factor: self.window.scale_factor(),
+                video_modes: Vec::new(),
+            })
+            .into_iter()
+            .collect();
+        #[cfg(not(target_arch = "wasm32"))]
+        return self
+            .window
+            .available_monitors()
+            .map(MonitorInfo::from)
+            .collect();
+    }
+
+    /// Makes the window fullscreen on the given monitor, or on the monitor the window is on if the given monitor
+    /// is no longer connected. With a video mode, the monitor is switched to the mode that best matches it, unless
+    /// the monitor has no modes to switch between, such as on web, in which case the window is made borderless.
+    pub(crate) fn set_fullscreen_on(&self, monitor: &MonitorId, mode: Option<&VideoModeSelector>) {
+        let handle = self
+            .window
+            .available_monitors()
+            .find(|handle| monitor.matches_exactly(handle))
+            .or_else(|| {
+                self.window
+                    .available_monitors()
+                    .find(|handle| monitor.name.is_some() && handle.name() == monitor.name)
+            });
+        let handle = match handle {
+            Some(handle) => Some(handle),
+            None => {
+                log::warn!("monitor {monitor:?} isn't connected, so going fullscreen on the current monitor");
+                self.window.current_monitor()
+            }
+        };
+
+        let video_mode = mode.zip(handle.as_ref()).and_then(|(mode, handle)| {
+            let best = mode.best_match(handle.video_modes());
+            if best.is_none() {
+                log::warn!(
+                    "monitor {monitor:?} has no video modes, so going borderless fullscreen"
+                );
+            }
+            best
+        });
+        let fullscreen = match video_mode {
+            Some(video_mode) => Fullscreen::Exclusive(video_mode),
+            None => Fullscreen::Borderless(handle),
+        };
+        self.window.set_fullscreen(Some(fullscreen));
+    }
+
+    /// Converts a position in logical pixels, such as the CSS pixels given by events from elsewhere on a web page,
+    /// into the physical pixels that the surface is drawn in, which are also used by
+    /// [`crate::GameData::cursor_position`]. On web, the scale factor is the page's `devicePixelRatio`.
+    pub fn to_surface_position(&self, position: LogicalPosition<f64>) -> PhysicalPosition<f64> {
+        self.physical_to_surface_position(position.to_physical(self.window.scale_factor()))
+    }
+
+    /// Maps a position in the window's physical pixels, as winit gives cursor and touch positions, onto the
+    /// surface.
+    pub(crate) fn physical_to_surface_position(
+        &self,
+        position: PhysicalPosition<f64>,
+    ) -> PhysicalPosition<f64> {
+        window_to_surface(position, self.window.inner_size(), self.surface_size())
+    }
+
+    /// The size of the main surface. On web, the canvas is sized to match the surface.
+    fn surface_size(&self) -> PhysicalSize<u32> {
+        #[cfg(target_arch = "wasm32")]
+        return PhysicalSize::new(self.canvas.width(), self.canvas.height());
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.window.inner_size();
+    }
+
+    /// Requests the user's attention while the window isn't focused, such as when a background task finishes, or
+    /// cancels the request when given `None`. Does nothing if the window is focused. On native, this flashes the
+    /// window in the taskbar, or bounces the dock icon on macOS. On web, the page title is marked until the page
+    /// is focused, and critical requests flash the mark.
+    pub fn request_attention(&self, kind: Option<UserAttentionType>) {
+        #[cfg(target_arch = "wasm32")]
+        crate::wasm::request_attention(kind);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.window.request_user_attention(kind);
+    }
+
     #[cfg(target_arch = "wasm32")]
     pub fn canvas(&self) -> web_sys::HtmlCanvasElement {
         self.canvas.clone()
@@ -42,10 +290,38 @@ impl GameWindow {
         &self,
         instance: &wgpu::Instance,
     ) -> Result<wgpu::Surface<'static>, wgpu::CreateSurfaceError> {
-        instance.create_surface(Arc::clone(&self.window))
+        create_surface(instance, &self.window)
     }
 }
 
+/// Maps a position in the window's physical pixels onto a surface of the given size. On native, the surface is the
+/// size of the window, so positions are unchanged. On web, winit scales positions from CSS pixels by
+/// `devicePixelRatio`, while the surface is sized to the canvas's device pixels, which can differ from the scaled
+/// CSS size by rounding.
+fn window_to_surface(
+    position: PhysicalPosition<f64>,
+    window_size: PhysicalSize<u32>,
+    surface_size: PhysicalSize<u32>,
+) -> PhysicalPosition<f64> {
+    if window_size == surface_size || window_size.width == 0 || window_size.height == 0 {
+        return position;
+    }
+    PhysicalPosition::new(
+        position.x * f64::from(surface_size.width) / f64::from(window_size.width),
+        position.y * f64::from(surface_size.height) / f64::from(window_size.height),
+    )
+}
+
+/// Creates a surface for drawing to a window. Every surface is made here, so that each holds an `Arc` of its
+/// window, keeping the window alive for as long as the surface exists. This makes the surface `'static` without
+/// any unsafe borrowing, however the game loop's state is torn down.
+pub(crate) fn create_surface(
+    instance: &wgpu::Instance,
+    window: &Arc<winit::window::Window>,
+) -> Result<wgpu::Surface<'static>, wgpu::CreateSurfaceError> {
+    instance.create_surface(Arc::clone(window))
+}
+
 impl std::ops::Deref for GameWindow {
     type Target = winit::window::Window;
 
@@ -54,7 +330,177 @@ impl std::ops::Deref for GameWindow {
     }
 }
 
+/// Gives the handle of the game window, for passing to libraries which draw into or overlay the window, such as
+/// video decoders. The handle borrows the window, so can't outlive it, but any raw handle taken from it with
+/// [`WindowHandle::as_raw`](winit::raw_window_handle::WindowHandle::as_raw) is only valid until the window is
+/// dropped, after [`super::Game::finished`]. Libraries given a raw handle must be torn down before then.
+impl winit::raw_window_handle::HasWindowHandle for GameWindow {
+    fn window_handle(
+        &self,
+    ) -> Result<winit::raw_window_handle::WindowHandle<'_>, winit::raw_window_handle::HandleError>
+    {
+        self.window.window_handle()
+    }
+}
+
+/// Gives the handle of the display that the game window is on. Raw handles taken from it are valid for as long as
+/// the window, as with [`GameWindow::window_handle`](winit::raw_window_handle::HasWindowHandle::window_handle).
+impl winit::raw_window_handle::HasDisplayHandle for GameWindow {
+    fn display_handle(
+        &self,
+    ) -> Result<winit::raw_window_handle::DisplayHandle<'_>, winit::raw_window_handle::HandleError>
+    {
+        self.window.display_handle()
+    }
+}
+
+/// A description of a monitor connected to the system, for example for choosing a default window size or the
+/// monitor to go fullscreen on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorInfo {
+    /// Identifies the monitor to [`super::GameCommand::SetFullscreenOn`].
+    pub id: MonitorId,
+    pub name: Option<String>,
+    pub size: PhysicalSize<u32>,
+    pub position: PhysicalPosition<i32>,
+    pub scale_factor: f64,
+    /// The video modes that the monitor can be switched to for exclusive fullscreen. Empty on platforms without
+    /// exclusive fullscreen, such as web.
+    pub video_modes: Vec<VideoModeInfo>,
+}
+
+impl From<MonitorHandle> for MonitorInfo {
+    fn from(monitor: MonitorHandle) -> Self {
+        Self {
+            id: MonitorId::from(&monitor),
+            name: monitor.name(),
+            size: monitor.size(),
+            position: monitor.position(),
+            scale_factor: monitor.scale_factor(),
+            video_modes: monitor.video_modes().map(VideoModeInfo::from).collect(),
+        }
+    }
+}
+
+/// Identifies a monitor by its name and where it is placed on the desktop, which stay the same between runs, so
+/// that a chosen monitor can be kept in the game's settings. If no monitor has both the name and the position,
+/// such as after the monitors have been rearranged, a monitor with the same name is used.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MonitorId {
+    name: Option<String>,
+    position: PhysicalPosition<i32>,
+}
+
+impl MonitorId {
+    fn matches_exactly(&self, monitor: &MonitorHandle) -> bool {
+        monitor.name() == self.name && monitor.position() == self.position
+    }
+}
+
+impl From<&MonitorHandle> for MonitorId {
+    fn from(monitor: &MonitorHandle) -> Self {
+        Self {
+            name: monitor.name(),
+            position: monitor.position(),
+        }
+    }
+}
+
+/// A video mode that a monitor can be switched to for exclusive fullscreen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct VideoModeInfo {
+    pub size: PhysicalSize<u32>,
+    pub bit_depth: u16,
+    pub refresh_rate_millihertz: u32,
+}
+
+impl From<VideoMode> for VideoModeInfo {
+    fn from(mode: VideoMode) -> Self {
+        Self {
+            size: mode.size(),
+            bit_depth: mode.bit_depth(),
+            refresh_rate_millihertz: mode.refresh_rate_millihertz(),
+        }
+    }
+}
+
+/// Describes the video mode wanted for exclusive fullscreen with [`super::GameCommand::SetFullscreenOn`]. Of a
+/// monitor's video modes, the one closest in size is used, then the closest in refresh rate, then the deepest
+/// color. Properties left as `None` aren't considered, and default to `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct VideoModeSelector {
+    pub size: Option<PhysicalSize<u32>>,
+    pub refresh_rate_millihertz: Option<u32>,
+    pub bit_depth: Option<u16>,
+}
+
+impl VideoModeSelector {
+    fn best_match(&self, modes: impl Iterator<Item = VideoMode>) -> Option<VideoMode> {
+        modes.min_by_key(|mode| {
+            let size_difference = self.size.map_or(0, |size| {
+                mode.size().width.abs_diff(size.width) as u64
+                    + mode.size().height.abs_diff(size.height) as u64
+            });
+            let refresh_rate_difference = self.refresh_rate_millihertz.map_or(0, |refresh_rate| {
+                mode.refresh_rate_millihertz().abs_diff(refresh_rate)
+            });
+            let bit_depth_difference = self
+                .bit_depth
+                .map_or(0, |bit_depth| mode.bit_depth().abs_diff(bit_depth));
+            (
+                size_difference,
+                refresh_rate_difference,
+                bit_depth_difference,
+                std::cmp::Reverse(mode.bit_depth()),
+            )
+        })
+    }
+}
+
+/// A window created at runtime with [`super::GameData::create_window`], in addition to the main game window.
+pub(super) struct ExtraWindow {
+    pub(super) window: Arc<winit::window::Window>,
+    pub(super) surface: super::surface::ResizableSurface<'static>,
+}
+
 /// Something that needs remaking/resizing whenever the game window is resized
 pub trait WindowSizeDependent {
     fn on_window_resize(&mut self, device: &Device, new_size: winit::dpi::PhysicalSize<u32>);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_are_unchanged_when_the_surface_fills_the_window() {
+        let size = PhysicalSize::new(800, 600);
+        let position = PhysicalPosition::new(123.5, 456.25);
+        assert_eq!(window_to_surface(position, size, size), position);
+    }
+
+    #[test]
+    fn css_positions_map_onto_device_pixels() {
+        // A canvas 400 CSS pixels wide at a `devicePixelRatio` of 1.5, which the browser gives 601 device pixels
+        let window_size = PhysicalSize::new(600, 300);
+        let surface_size = PhysicalSize::new(601, 300);
+
+        // The right edge of the canvas is the right edge of the surface
+        let position = window_to_surface(
+            LogicalPosition::new(400.0, 100.0).to_physical(1.5),
+            window_size,
+            surface_size,
+        );
+        assert!((position.x - 601.0).abs() < 1e-9, "{position:?}");
+        assert_eq!(position.y, 150.0);
+    }
+
+    #[test]
+    fn empty_windows_leave_positions_unchanged() {
+        let position = PhysicalPosition::new(10.0, 20.0);
+        assert_eq!(
+            window_to_surface(position, PhysicalSize::new(0, 0), PhysicalSize::new(1, 1)),
+            position
+        );
+    }
+}
@@ -1,30 +1,79 @@
 #![doc = include_str!("../README.md")]
 #![warn(unused_extern_crates)]
 
+mod adapter;
+mod clipboard;
+mod clock;
+mod file_drop;
 mod fragment_only;
+mod frame_uniform;
 mod game;
 mod limits;
+mod post_process;
+mod render_to_texture;
 
 #[cfg(target_arch = "wasm32")]
 mod wasm;
 
+pub use adapter::adapter_report;
+pub use adapter::AdapterPreference;
+pub use adapter::AdapterQuery;
+pub use adapter::AdapterRejection;
+pub use adapter::AdapterRejectionReason;
+pub use adapter::DeviceRequestError;
+pub use clipboard::ClipboardImage;
+pub use clipboard::PasteData;
+pub use clock::Clock;
+pub use file_drop::DroppedFile;
+pub use fragment_only::BundleCompatibilityError;
+pub use fragment_only::FragmentOnlyColorAttachment;
+pub use fragment_only::FragmentOnlyRenderBundle;
 pub use fragment_only::FragmentOnlyRenderBundleEncoder;
 pub use fragment_only::FragmentOnlyRenderBundleEncoderDescriptor;
 pub use fragment_only::FragmentOnlyRenderPass;
 pub use fragment_only::FragmentOnlyRenderPassDescriptor;
 pub use fragment_only::FragmentOnlyRenderPipeline;
 pub use fragment_only::FragmentOnlyRenderPipelineDescriptor;
+pub use frame_uniform::FrameUniform;
 pub use game::window::GameWindow;
+pub use game::window::MonitorId;
+pub use game::window::MonitorInfo;
+pub use game::window::VideoModeInfo;
+pub use game::window::VideoModeSelector;
 pub use game::window::WindowSizeDependent;
+pub use game::CommandSender;
 pub use game::ExitFlag;
+pub use game::FeatureReport;
+pub use game::FrameBudgetReport;
+pub use game::FrameStats;
 pub use game::Game;
 pub use game::GameCommand;
 pub use game::GameData;
+pub use game::Gesture;
+pub use game::GpuErrorCapture;
 pub use game::InputMode;
+pub use game::LoadingProgress;
+pub use game::PointerDeltaSpace;
+pub use game::Rect;
+pub use game::RenderMode;
+pub use game::RunConfig;
+pub use limits::LimitsBuilder;
+pub use limits::LimitsProfiles;
+pub use post_process::PostProcessChain;
+pub use render_to_texture::RenderToTexture;
+
+// Re-exported for constructing the events returned from `Game::process_raw_event`
+pub use smallvec;
+// Re-exported so that handles given by `GameData::raw_window_handle` can be used without depending on the same
+// version of `raw-window-handle` as winit
+pub use winit::raw_window_handle;
 pub mod input {
     pub use crate::game::input::*;
 }
 pub mod local_storage;
+pub mod math;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 
 // Resolve https://github.com/rust-lang/rustc-hash/issues/14 by wrapping `rustc_hash::FxHasher`.
 pub struct FastHashState {
@@ -114,6 +163,9 @@ mod pretty_alert {
         }
     }
 
+    /// The id of dialogs shown by [`try_pretty_alert`], which are removed by [`crate::shutdown_web`].
+    pub(crate) const ALERT_ID: &str = "lf-alert";
+
     pub(super) fn try_pretty_alert(msg: &str) -> Result<(), PrettyAlertFailure> {
         use wasm_bindgen::JsCast;
 
@@ -124,7 +176,7 @@ mod pretty_alert {
         let body = document.body().expect("DOM has body");
 
         let dialog = document.create_element("dialog")?;
-        dialog.set_id("lf-alert");
+        dialog.set_id(ALERT_ID);
         dialog.set_attribute("style", r#"font-family: mono; max-width: 50%;"#)?;
         {
             let text_div = document.create_element("div")?;
@@ -157,7 +209,22 @@ mod pretty_alert {
     }
 }
 
+/// Removes the event listeners, observers and timers that the crate has added to the page, along with any
+/// dialogues shown by [`alert_dialogue`], so that the game can be started again in the same page, such as when
+/// a single-page app navigates away from and back to the game, without them building up. The canvas is provided
+/// by the page, so is left in place.
+///
+/// This is done automatically when the game finishes, after the window's surface is dropped and before
+/// [`Game::finished`] is invoked, so only needs calling directly to tear down alerts shown afterwards, or if the
+/// game loop was never started.
+#[cfg(target_arch = "wasm32")]
+pub fn shutdown_web() {
+    wasm::shutdown()
+}
+
 /// Produces a dialogue box with an `okay` response. Good for quick and dirty errors when something has gone very wrong.
+///
+/// On native, the dialogue box requires the `native-dialogs` feature. Without it, the message is logged as an error instead.
 pub fn alert_dialogue(msg: &str) {
     #[cfg(target_arch = "wasm32")]
     {
@@ -169,7 +236,7 @@ pub fn alert_dialogue(msg: &str) {
                 .expect("all browsers should have alert");
         }
     }
-    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(all(not(target_arch = "wasm32"), feature = "native-dialogs"))]
     {
         use dialog::DialogBox;
         dialog::Message::new(msg)
@@ -177,6 +244,10 @@ pub fn alert_dialogue(msg: &str) {
             .show()
             .expect("dialog box unavailable")
     }
+    #[cfg(all(not(target_arch = "wasm32"), not(feature = "native-dialogs")))]
+    {
+        log::error!("{msg}");
+    }
 }
 
 /// Some operations care about alignment in such a way that it is often easier to simply round all buffer sizes up to the nearest
@@ -206,6 +277,9 @@ mod sealed {
     pub trait SealedQueue {}
     impl SealedQueue for wgpu::Queue {}
 
+    pub trait SealedTexture {}
+    impl SealedTexture for wgpu::Texture {}
+
     pub trait SealedBindGroupLayoutEntry {}
     impl SealedBindGroupLayoutEntry for wgpu::BindGroupLayoutEntry {}
 
@@ -224,11 +298,174 @@ pub struct PaddedBufferInitDescriptor<'a> {
     pub usage: wgpu::BufferUsages,
 }
 
+/// How the colour values stored in a texture are interpreted when sampled.
+///
+/// Most 8-bit colour images (PNGs, JPEGs, etc.) are sRGB-encoded, and sampling them as linear gives washed-out
+/// colours. Data which isn't colour, like normal maps, height maps or masks, is almost always linear.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// Values are sampled exactly as they are stored.
+    Linear,
+    /// Values are stored sRGB-encoded, and are converted to linear when sampled.
+    Srgb,
+}
+
+impl ColorSpace {
+    /// Gives the variant of a format which samples in this colour space. Formats without an sRGB variant
+    /// are given unchanged.
+    ///
+    /// ```
+    /// use lf_gfx::ColorSpace;
+    /// use wgpu::TextureFormat;
+    ///
+    /// assert_eq!(ColorSpace::Srgb.apply_to(TextureFormat::Rgba8Unorm), TextureFormat::Rgba8UnormSrgb);
+    /// assert_eq!(ColorSpace::Srgb.apply_to(TextureFormat::Rgba8UnormSrgb), TextureFormat::Rgba8UnormSrgb);
+    /// assert_eq!(ColorSpace::Linear.apply_to(TextureFormat::Bgra8UnormSrgb), TextureFormat::Bgra8Unorm);
+    /// assert_eq!(ColorSpace::Srgb.apply_to(TextureFormat::R32Float), TextureFormat::R32Float);
+    /// ```
+    pub fn apply_to(self, format: wgpu::TextureFormat) -> wgpu::TextureFormat {
+        match self {
+            ColorSpace::Linear => format.remove_srgb_suffix(),
+            ColorSpace::Srgb => format.add_srgb_suffix(),
+        }
+    }
+}
+
+/// Converts an 8-bit sRGB color, such as one picked in an image editor or given as a hex code, to the linear
+/// [`wgpu::Color`] that wgpu expects for clear colors. Alpha is always linear, so is only rescaled.
+///
+/// Clear colors are written to sRGB targets, like most surfaces, by converting them from linear, so passing
+/// sRGB values straight through gives colors which are too bright. Use this for sRGB targets, and raw
+/// [`wgpu::Color`] values when the color is already linear, such as when it was computed. For targets which may
+/// or may not be sRGB, use [`srgb_color_for_format`].
+///
+/// ```
+/// let color = lf_gfx::srgb_color(255, 128, 0, 255);
+/// assert_eq!(color.r, 1.0);
+/// assert!((color.g - 0.2158).abs() < 0.0001);
+/// assert_eq!(color.b, 0.0);
+/// assert_eq!(color.a, 1.0);
+/// ```
+pub fn srgb_color(r: u8, g: u8, b: u8, a: u8) -> wgpu::Color {
+    fn to_linear(value: u8) -> f64 {
+        let value = value as f64 / 255.0;
+        if value <= 0.04045 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    }
+
+    wgpu::Color {
+        r: to_linear(r),
+        g: to_linear(g),
+        b: to_linear(b),
+        a: a as f64 / 255.0,
+    }
+}
+
+/// Converts an 8-bit sRGB color to the clear color which displays it when drawn to a target of the given format.
+/// For sRGB formats this is [`srgb_color`], and for other formats, which store values as they are given, the
+/// values are only rescaled.
+pub fn srgb_color_for_format(
+    format: wgpu::TextureFormat,
+    r: u8,
+    g: u8,
+    b: u8,
+    a: u8,
+) -> wgpu::Color {
+    if format.is_srgb() {
+        return srgb_color(r, g, b, a);
+    }
+
+    wgpu::Color {
+        r: r as f64 / 255.0,
+        g: g as f64 / 255.0,
+        b: b as f64 / 255.0,
+        a: a as f64 / 255.0,
+    }
+}
+
+/// A shader which failed to compile, given by [`LfDeviceExt::create_shader_module_checked`].
+#[derive(Debug, thiserror::Error)]
+#[error("shader failed to compile: {message}")]
+pub struct ShaderError {
+    /// The full error reported by wgpu, including the location of the error in the shader source.
+    pub message: String,
+}
+
+/// A compressed texture which couldn't be created, given by [`LfDeviceExt::create_compressed_texture`].
+#[derive(Debug, thiserror::Error)]
+pub enum CompressedTextureError {
+    #[error("{0:?} is not a compressed texture format")]
+    NotCompressed(wgpu::TextureFormat),
+    #[error("{format:?} textures require the device feature {feature:?}, which isn't enabled")]
+    MissingFeature {
+        format: wgpu::TextureFormat,
+        feature: wgpu::Features,
+    },
+    #[error("expected {expected} bytes of compressed texture data, but {actual} were given")]
+    DataSize { expected: usize, actual: usize },
+}
+
+/// The number of bytes required to fill every layer and mip level of a texture, tightly packed.
+fn texture_data_size(desc: &wgpu::TextureDescriptor) -> usize {
+    let block_size = desc.format.block_copy_size(None).unwrap_or(4);
+    let (block_width, block_height) = desc.format.block_dimensions();
+
+    let mut size = 0;
+    for _ in 0..desc.array_layer_count() {
+        for mip in 0..desc.mip_level_count {
+            let mut mip_size = desc
+                .mip_level_size(mip)
+                .expect("mip level is within mip level count");
+            if desc.dimension != wgpu::TextureDimension::D3 {
+                mip_size.depth_or_array_layers = 1;
+            }
+            let mip_physical = mip_size.physical_size(desc.format);
+
+            let width_blocks = mip_physical.width / block_width;
+            let height_blocks = mip_physical.height / block_height;
+            size += (width_blocks * block_size * height_blocks * mip_size.depth_or_array_layers)
+                as usize;
+        }
+    }
+    size
+}
+
 /// Extensions to [`wgpu::Device`].
 pub trait LfDeviceExt: sealed::SealedDevice {
     fn create_buffer_padded(&self, desc: wgpu::BufferDescriptor) -> wgpu::Buffer;
     fn create_buffer_init_padded(&self, desc: PaddedBufferInitDescriptor) -> wgpu::Buffer;
 
+    /// Creates a texture and uploads data to it using [`wgpu::util::DeviceExt::create_texture_with_data`], but extends
+    /// the data with zeros to fill every layer and mip level. Layers are expected before mip levels in the data.
+    ///
+    /// The format given in the descriptor is switched to the variant which samples in the given colour space, so
+    /// `Rgba8Unorm` with [`ColorSpace::Srgb`] creates an `Rgba8UnormSrgb` texture. A view of the whole texture,
+    /// in the same format, is also given.
+    fn create_texture_with_data_padded(
+        &self,
+        queue: &wgpu::Queue,
+        desc: &wgpu::TextureDescriptor,
+        color_space: ColorSpace,
+        data: Vec<u8>,
+    ) -> (wgpu::Texture, wgpu::TextureView);
+
+    /// Creates a texture in a block compressed format (BCn, ETC2 or ASTC) and uploads already compressed data to
+    /// it, with every layer and mip level tightly packed as blocks, layers before mip levels. A view of the whole
+    /// texture is also given.
+    ///
+    /// Each family of formats needs its own device feature, which [`Game`]s enable whenever the adapter supports
+    /// it. Desktop GPUs generally support BCn, and mobile GPUs ETC2 and ASTC, so games should ship their textures
+    /// in more than one format and pick whichever the device's features allow.
+    fn create_compressed_texture(
+        &self,
+        queue: &wgpu::Queue,
+        desc: &wgpu::TextureDescriptor,
+        data: &[u8],
+    ) -> Result<(wgpu::Texture, wgpu::TextureView), CompressedTextureError>;
+
     fn create_fragment_only_render_bundle_encoder(
         &self,
         desc: &FragmentOnlyRenderBundleEncoderDescriptor,
@@ -247,8 +484,28 @@ pub trait LfDeviceExt: sealed::SealedDevice {
         desc: wgpu::ShaderModuleDescriptor,
     ) -> wgpu::ShaderModule;
 
+    /// Creates a module, giving back any compilation error rather than reporting it to the device's uncaptured
+    /// error handler, which panics by default. This allows shaders from untrusted sources, such as mods or
+    /// live-coding, to fail gracefully.
+    ///
+    /// Errors are only known once the device has processed the module, so the result is given asynchronously.
+    fn create_shader_module_checked(
+        &self,
+        desc: wgpu::ShaderModuleDescriptor,
+    ) -> impl std::future::Future<Output = Result<wgpu::ShaderModule, ShaderError>>;
+
     /// Pops an error scope and asserts that it isn't an error.
     fn assert_pop_error_scope(&self, msg: impl Into<String>);
+
+    /// Blocks and reads the entirety of each of the given buffers, giving the bytes contained in the same order as
+    /// the buffers. All of the copies are made in a single submission, so this is much faster than calling
+    /// [`LfBufferExt::debug_read_blocking`] on each buffer. Panics on error, or if any buffer was not created with
+    /// `wgpu::BufferUsages::COPY_SRC`.
+    fn debug_read_many_blocking(
+        &self,
+        queue: &wgpu::Queue,
+        buffers: &[&wgpu::Buffer],
+    ) -> Vec<Vec<u8>>;
 }
 
 impl LfDeviceExt for wgpu::Device {
@@ -269,6 +526,68 @@ impl LfDeviceExt for wgpu::Device {
         })
     }
 
+    fn create_texture_with_data_padded(
+        &self,
+        queue: &wgpu::Queue,
+        desc: &wgpu::TextureDescriptor,
+        color_space: ColorSpace,
+        mut data: Vec<u8>,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let mut desc = desc.clone();
+        desc.format = color_space.apply_to(desc.format);
+
+        let required_len = texture_data_size(&desc);
+        if data.len() < required_len {
+            data.resize(required_len, 0u8);
+        }
+
+        let texture = self.create_texture_with_data(
+            queue,
+            &desc,
+            wgpu::util::TextureDataOrder::LayerMajor,
+            &data,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        (texture, view)
+    }
+
+    fn create_compressed_texture(
+        &self,
+        queue: &wgpu::Queue,
+        desc: &wgpu::TextureDescriptor,
+        data: &[u8],
+    ) -> Result<(wgpu::Texture, wgpu::TextureView), CompressedTextureError> {
+        if !desc.format.is_compressed() {
+            return Err(CompressedTextureError::NotCompressed(desc.format));
+        }
+        let feature = desc.format.required_features();
+        if !self.features().contains(feature) {
+            return Err(CompressedTextureError::MissingFeature {
+                format: desc.format,
+                feature,
+            });
+        }
+        let expected = texture_data_size(desc);
+        if data.len() != expected {
+            return Err(CompressedTextureError::DataSize {
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        // Rows of blocks are laid out from the format's block size and dimensions
+        let texture = self.create_texture_with_data(
+            queue,
+            desc,
+            wgpu::util::TextureDataOrder::LayerMajor,
+            data,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Ok((texture, view))
+    }
+
     fn create_fragment_only_render_bundle_encoder(
         &self,
         desc: &FragmentOnlyRenderBundleEncoderDescriptor,
@@ -297,6 +616,24 @@ impl LfDeviceExt for wgpu::Device {
         }
     }
 
+    fn create_shader_module_checked(
+        &self,
+        desc: wgpu::ShaderModuleDescriptor,
+    ) -> impl std::future::Future<Output = Result<wgpu::ShaderModule, ShaderError>> {
+        self.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = self.create_shader_module(desc);
+        let error = self.pop_error_scope();
+
+        async move {
+            match error.await {
+                None => Ok(module),
+                Some(err) => Err(ShaderError {
+                    message: err.to_string(),
+                }),
+            }
+        }
+    }
+
     fn assert_pop_error_scope(&self, msg: impl Into<String>) {
         let f = self.pop_error_scope();
         async fn check_device_scope(
@@ -310,6 +647,99 @@ impl LfDeviceExt for wgpu::Device {
         }
         block_on(check_device_scope(f, msg.into()));
     }
+
+    fn debug_read_many_blocking(
+        &self,
+        queue: &wgpu::Queue,
+        buffers: &[&wgpu::Buffer],
+    ) -> Vec<Vec<u8>> {
+        let mut cmd = self.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("debug-read-cmd-encoder"),
+        });
+        let stagings: Vec<wgpu::Buffer> = buffers
+            .iter()
+            .map(|buffer| {
+                assert!(buffer.usage().contains(wgpu::BufferUsages::COPY_SRC));
+
+                let staging = self.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("debug-read-staging"),
+                    size: buffer.size(),
+                    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                    mapped_at_creation: false,
+                });
+                cmd.copy_buffer_to_buffer(buffer, 0, &staging, 0, buffer.size());
+
+                staging
+            })
+            .collect();
+
+        queue.submit(vec![cmd.finish()]);
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        for staging in &stagings {
+            let sender = sender.clone();
+            staging.slice(..).map_async(wgpu::MapMode::Read, move |e| {
+                sender.send(e).expect("failed to send result of map");
+            });
+        }
+
+        self.poll(wgpu::Maintain::Wait);
+
+        for _ in &stagings {
+            receiver
+                .recv()
+                .expect("failed to get result of map")
+                .expect("failed to read buffer");
+        }
+
+        stagings
+            .iter()
+            .map(|staging| staging.slice(..).get_mapped_range().to_vec())
+            .collect()
+    }
+}
+
+/// Extensions to [`wgpu::Instance`].
+pub trait LfInstanceExt: sealed::SealedInstance {
+    /// Finds the most preferred adapter which meets the query, giving `None` if none do.
+    fn request_powerful_adapter<'a>(
+        &'a self,
+        query: &'a AdapterQuery<'a>,
+    ) -> impl std::future::Future<Output = Option<wgpu::Adapter>> + 'a;
+
+    /// Finds the most preferred adapter which meets the query and supports all of the required features, then
+    /// creates a device on it with those features. The device is given as many of the target limits as the
+    /// adapter supports.
+    ///
+    /// On error, every adapter which was found but didn't qualify is given, along with why.
+    fn request_device_for<'a>(
+        &'a self,
+        query: &'a AdapterQuery<'a>,
+        required_features: wgpu::Features,
+        target_limits: &'a wgpu::Limits,
+    ) -> impl std::future::Future<
+        Output = Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue), DeviceRequestError>,
+    > + 'a;
+}
+
+impl LfInstanceExt for wgpu::Instance {
+    fn request_powerful_adapter<'a>(
+        &'a self,
+        query: &'a AdapterQuery<'a>,
+    ) -> impl std::future::Future<Output = Option<wgpu::Adapter>> + 'a {
+        adapter::request_powerful_adapter(self, query)
+    }
+
+    fn request_device_for<'a>(
+        &'a self,
+        query: &'a AdapterQuery<'a>,
+        required_features: wgpu::Features,
+        target_limits: &'a wgpu::Limits,
+    ) -> impl std::future::Future<
+        Output = Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue), DeviceRequestError>,
+    > + 'a {
+        adapter::request_device_for(self, query, required_features, target_limits)
+    }
 }
 
 /// Extensions to [`wgpu::CommandEncoder`].
@@ -335,6 +765,9 @@ pub trait LfLimitsExt: sealed::SealedLimits {
     fn intersection<'a>(&self, other: &wgpu::Limits) -> wgpu::Limits;
     /// Gets the set of limits supported by either this ot the other limits.
     fn union<'a>(&self, other: &wgpu::Limits) -> wgpu::Limits;
+    /// Whether every limit given by this is also supported by the other limits, i.e. a device created
+    /// with these limits can be requested from an adapter with the other limits.
+    fn is_subset_of(&self, other: &wgpu::Limits) -> bool;
 }
 
 impl LfLimitsExt for wgpu::Limits {
@@ -346,6 +779,10 @@ impl LfLimitsExt for wgpu::Limits {
     fn union<'a>(&self, other: &wgpu::Limits) -> wgpu::Limits {
         crate::limits::limits_union(self, other)
     }
+    /// Whether every limit given by this is also supported by the other limits.
+    fn is_subset_of(&self, other: &wgpu::Limits) -> bool {
+        crate::limits::limits_is_subset_of(self, other)
+    }
 }
 
 /// Extensions to [`wgpu::Queue`].
@@ -362,6 +799,23 @@ pub trait LfQueueExt: sealed::SealedQueue {
         offset: wgpu::BufferAddress,
         data: Vec<u8>,
     );
+
+    /// Writes tightly packed pixels into a region of the first mip level of a texture, copying the rows into
+    /// staging with each row's length padded to [`wgpu::COPY_BYTES_PER_ROW_ALIGNMENT`], so that regions of any
+    /// width can be written, such as a glyph into an atlas.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` isn't exactly `size.width * size.height * size.depth_or_array_layers * bytes_per_pixel`
+    /// bytes long.
+    fn write_texture_padded(
+        &self,
+        texture: &wgpu::Texture,
+        origin: wgpu::Origin3d,
+        size: wgpu::Extent3d,
+        data: &[u8],
+        bytes_per_pixel: u32,
+    );
 }
 
 impl LfQueueExt for wgpu::Queue {
@@ -381,6 +835,53 @@ impl LfQueueExt for wgpu::Queue {
 
         self.write_buffer(buffer, offset, &data)
     }
+
+    fn write_texture_padded(
+        &self,
+        texture: &wgpu::Texture,
+        origin: wgpu::Origin3d,
+        size: wgpu::Extent3d,
+        data: &[u8],
+        bytes_per_pixel: u32,
+    ) {
+        let row_len = (size.width * bytes_per_pixel) as usize;
+        let rows = (size.height * size.depth_or_array_layers) as usize;
+        assert_eq!(
+            data.len(),
+            row_len * rows,
+            "texture data should be tightly packed rows of the given size"
+        );
+
+        let padded_row_len = next_multiple_of(
+            row_len as wgpu::BufferAddress,
+            wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as wgpu::BufferAddress,
+        ) as usize;
+        let mut padded = vec![0u8; padded_row_len * rows];
+        if row_len > 0 {
+            for (padded_row, row) in padded
+                .chunks_exact_mut(padded_row_len)
+                .zip(data.chunks_exact(row_len))
+            {
+                padded_row[..row_len].copy_from_slice(row);
+            }
+        }
+
+        self.write_texture(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &padded,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_len as u32),
+                rows_per_image: Some(size.height),
+            },
+            size,
+        )
+    }
 }
 
 /// Extensions to [`wgpu::Buffer`].
@@ -394,36 +895,85 @@ pub trait LfBufferExt: sealed::SealedBuffer {
 
 impl LfBufferExt for wgpu::Buffer {
     fn debug_read_blocking(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Vec<u8> {
-        assert!(self.usage().contains(wgpu::BufferUsages::COPY_SRC));
-
-        let staging = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("debug-read-staging"),
-            size: self.size(),
-            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
-            mapped_at_creation: false,
-        });
+        device
+            .debug_read_many_blocking(queue, &[self])
+            .pop()
+            .expect("one buffer read gives one result")
+    }
+}
 
-        let mut cmd = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("debug-read-cmd-encoder"),
-        });
-        cmd.copy_buffer_to_buffer(self, 0, &staging, 0, self.size());
+/// Extensions to [`wgpu::Texture`].
+pub trait LfTextureExt: sealed::SealedTexture {
+    /// Creates a 2D view of a single layer of an array texture, for example to render one face of a cube map.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the layer is not within the texture.
+    fn view_layer(&self, layer: u32) -> wgpu::TextureView;
 
-        queue.submit(vec![cmd.finish()]);
+    /// Creates a 2D array view of a range of layers of an array texture.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is empty or not within the texture.
+    fn view_layers(&self, layers: std::ops::Range<u32>) -> wgpu::TextureView;
 
-        let (sender, receiver) = std::sync::mpsc::channel();
-        staging.slice(..).map_async(wgpu::MapMode::Read, move |e| {
-            sender.send(e).expect("failed to send result of map");
-        });
+    /// Creates a cube view of the first six layers of an array texture, in the order `+X`, `-X`, `+Y`, `-Y`, `+Z`, `-Z`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the texture has fewer than six layers.
+    fn cube_view(&self) -> wgpu::TextureView;
+}
 
-        device.poll(wgpu::Maintain::Wait);
+impl LfTextureExt for wgpu::Texture {
+    fn view_layer(&self, layer: u32) -> wgpu::TextureView {
+        assert!(
+            layer < self.depth_or_array_layers(),
+            "layer {layer} is out of bounds for a texture with {} layers",
+            self.depth_or_array_layers()
+        );
+
+        self.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("texture layer view"),
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            base_array_layer: layer,
+            array_layer_count: Some(1),
+            ..Default::default()
+        })
+    }
 
-        receiver
-            .recv()
-            .expect("failed to get result of map")
-            .expect("failed to read buffer");
+    fn view_layers(&self, layers: std::ops::Range<u32>) -> wgpu::TextureView {
+        assert!(!layers.is_empty(), "cannot view an empty range of layers");
+        assert!(
+            layers.end <= self.depth_or_array_layers(),
+            "layers {layers:?} are out of bounds for a texture with {} layers",
+            self.depth_or_array_layers()
+        );
+
+        self.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("texture layers view"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            base_array_layer: layers.start,
+            array_layer_count: Some(layers.end - layers.start),
+            ..Default::default()
+        })
+    }
 
-        let slice = staging.slice(..).get_mapped_range();
-        slice.to_vec()
+    fn cube_view(&self) -> wgpu::TextureView {
+        assert!(
+            self.depth_or_array_layers() >= 6,
+            "cube views require at least 6 layers, but the texture has {}",
+            self.depth_or_array_layers()
+        );
+
+        self.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("texture cube view"),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            base_array_layer: 0,
+            array_layer_count: Some(6),
+            ..Default::default()
+        })
     }
 }
 
@@ -468,6 +1018,9 @@ pub trait LfGameExt: sealed::SealedGame {
 
     /// Runs the game.
     fn run(init: Self::InitData);
+
+    /// Runs the game, with options for the game loop given by the config.
+    fn run_with_config(init: Self::InitData, config: RunConfig);
 }
 
 impl<T: Game + 'static> LfGameExt for T {
@@ -475,6 +1028,63 @@ impl<T: Game + 'static> LfGameExt for T {
 
     /// Runs the game. Must be executed on the main thread on Web, and will not block the main thread (although will loop until the game is completed).
     fn run(init: T::InitData) {
-        game::GameState::<T>::run(init);
+        Self::run_with_config(init, RunConfig::default());
+    }
+
+    /// Runs the game. Must be executed on the main thread on Web, and will not block the main thread (although will loop until the game is completed).
+    fn run_with_config(init: T::InitData, config: RunConfig) {
+        game::GameState::<T>::run(init, config);
+    }
+}
+
+#[cfg(all(test, feature = "test-utils", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn textures_are_created_in_the_requested_color_space() {
+        let Some((device, queue)) = test_utils::headless_device() else {
+            eprintln!("skipping, as there is no adapter");
+            return;
+        };
+
+        for (format, color_space, expected) in [
+            (
+                wgpu::TextureFormat::Rgba8Unorm,
+                ColorSpace::Srgb,
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+            ),
+            (
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+                ColorSpace::Linear,
+                wgpu::TextureFormat::Rgba8Unorm,
+            ),
+            (
+                wgpu::TextureFormat::Rgba8Unorm,
+                ColorSpace::Linear,
+                wgpu::TextureFormat::Rgba8Unorm,
+            ),
+        ] {
+            let (texture, _view) = device.create_texture_with_data_padded(
+                &queue,
+                &wgpu::TextureDescriptor {
+                    label: None,
+                    size: wgpu::Extent3d {
+                        width: 2,
+                        height: 2,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                },
+                color_space,
+                vec![255; 4],
+            );
+            assert_eq!(texture.format(), expected, "{format:?} in {color_space:?}");
+        }
     }
 }
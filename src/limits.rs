@@ -1,5 +1,6 @@
 //! Provides some more wgpu::Limits functionality, like taking the element-wise minimum or maximum of two
-//! limits.
+//! limits, picking the best of a set of tiers of limits that an adapter supports, and building limits from
+//! wgpu's presets.
 
 use std::cmp::{max, min};
 
@@ -63,3 +64,140 @@ pub(crate) fn limits_intersection(lhs: &wgpu::Limits, rhs: &wgpu::Limits) -> wgp
 pub(crate) fn limits_union(lhs: &wgpu::Limits, rhs: &wgpu::Limits) -> wgpu::Limits {
     binop_limits!((max, min)(lhs, rhs))
 }
+
+pub(crate) fn limits_is_subset_of(lhs: &wgpu::Limits, rhs: &wgpu::Limits) -> bool {
+    &limits_intersection(lhs, rhs) == lhs
+}
+
+/// An ordered list of named tiers of limits, from the lowest tier to the highest, for example `Low`, `Medium`
+/// and `High` quality settings. Used to pick the highest tier that an adapter supports.
+///
+/// ```
+/// let profiles = lf_gfx::LimitsProfiles::new([
+///     ("Low", wgpu::Limits::downlevel_webgl2_defaults()),
+///     ("High", wgpu::Limits::default()),
+/// ]);
+///
+/// let adapter_limits = wgpu::Limits::downlevel_defaults();
+/// let (name, _) = profiles.best_supported(&adapter_limits).unwrap();
+/// assert_eq!(name, "Low");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LimitsProfiles {
+    profiles: Vec<(String, wgpu::Limits)>,
+}
+
+impl LimitsProfiles {
+    /// Creates a set of profiles, given in order from the lowest tier to the highest.
+    pub fn new<S: Into<String>>(profiles: impl IntoIterator<Item = (S, wgpu::Limits)>) -> Self {
+        Self {
+            profiles: profiles
+                .into_iter()
+                .map(|(name, limits)| (name.into(), limits))
+                .collect(),
+        }
+    }
+
+    /// Adds a profile above all of the existing profiles.
+    pub fn push(&mut self, name: impl Into<String>, limits: wgpu::Limits) {
+        self.profiles.push((name.into(), limits));
+    }
+
+    /// Iterates through the profiles, from the lowest tier to the highest.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&str, &wgpu::Limits)> {
+        self.profiles
+            .iter()
+            .map(|(name, limits)| (name.as_str(), limits))
+    }
+
+    /// Gets the highest tier whose limits are all within those supported by the adapter, or `None` if the
+    /// adapter doesn't support even the lowest tier.
+    pub fn best_supported(&self, adapter_limits: &wgpu::Limits) -> Option<(&str, &wgpu::Limits)> {
+        self.iter()
+            .rev()
+            .find(|(_, limits)| limits_is_subset_of(limits, adapter_limits))
+    }
+}
+
+macro_rules! limits_setters {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        $(
+            pub fn $field(mut self, value: $ty) -> Self {
+                self.limits.$field = value;
+                self
+            }
+        )*
+    };
+}
+
+/// Builds a set of [`wgpu::Limits`] by starting from one of wgpu's presets and changing the fields that are
+/// commonly tuned, for use in [`crate::Game::target_limits`] or [`LimitsProfiles`].
+///
+/// ```
+/// let limits = lf_gfx::LimitsBuilder::downlevel_defaults()
+///     .max_buffer_size(1 << 30)
+///     .max_push_constant_size(128)
+///     .build();
+///
+/// assert_eq!(limits.max_buffer_size, 1 << 30);
+/// assert_eq!(limits.max_push_constant_size, 128);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LimitsBuilder {
+    limits: wgpu::Limits,
+}
+
+impl LimitsBuilder {
+    /// Starts from [`wgpu::Limits::default`], supported by most modern desktop hardware.
+    pub fn default_limits() -> Self {
+        Self::from_limits(wgpu::Limits::default())
+    }
+
+    /// Starts from [`wgpu::Limits::downlevel_defaults`], supported by older hardware such as GLES3 devices.
+    pub fn downlevel_defaults() -> Self {
+        Self::from_limits(wgpu::Limits::downlevel_defaults())
+    }
+
+    /// Starts from [`wgpu::Limits::downlevel_webgl2_defaults`], supported by WebGL2 in every browser.
+    pub fn downlevel_webgl2_defaults() -> Self {
+        Self::from_limits(wgpu::Limits::downlevel_webgl2_defaults())
+    }
+
+    /// Starts from any set of limits.
+    pub fn from_limits(limits: wgpu::Limits) -> Self {
+        Self { limits }
+    }
+
+    limits_setters! {
+        max_texture_dimension_2d: u32,
+        max_bind_groups: u32,
+        max_buffer_size: u64,
+        max_uniform_buffer_binding_size: u32,
+        max_storage_buffer_binding_size: u32,
+        max_storage_buffers_per_shader_stage: u32,
+        max_storage_textures_per_shader_stage: u32,
+        max_compute_workgroup_storage_size: u32,
+        max_compute_invocations_per_workgroup: u32,
+        max_compute_workgroup_size_x: u32,
+        max_compute_workgroup_size_y: u32,
+        max_compute_workgroup_size_z: u32,
+        max_compute_workgroups_per_dimension: u32,
+        max_push_constant_size: u32,
+    }
+
+    pub fn build(self) -> wgpu::Limits {
+        self.limits
+    }
+}
+
+impl Default for LimitsBuilder {
+    fn default() -> Self {
+        Self::default_limits()
+    }
+}
+
+impl From<LimitsBuilder> for wgpu::Limits {
+    fn from(builder: LimitsBuilder) -> Self {
+        builder.build()
+    }
+}
@@ -10,6 +10,10 @@ pub enum StoreError {
     },
     #[error("local browser storage was not available - ensure cookies are permitted for this site to store your game data, and then refresh the page")]
     LocalStorageUnavailable,
+    #[error("local browser storage is full - clear some space by removing data stored by other sites, and then refresh the page")]
+    QuotaExceeded,
+    #[error("local browser storage discarded the value written - this can happen in private browsing modes, where data isn't kept")]
+    WriteDiscarded,
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -30,9 +34,57 @@ fn store_web(key: &str, value: &str) -> Result<(), StoreError> {
         .map_err(|_| StoreError::LocalStorageUnavailable)?
         .ok_or(StoreError::LocalStorageUnavailable)?;
 
-    storage
-        .set_item(key, value)
-        .map_err(|_| StoreError::LocalStorageUnavailable)
+    if let Err(err) = storage.set_item(key, value) {
+        use wasm_bindgen::JsCast;
+
+        let is_quota_exceeded = err.dyn_ref::<web_sys::DomException>().is_some_and(|err| {
+            // Firefox uses its own name, and older browsers only give the legacy code
+            err.name() == "QuotaExceededError"
+                || err.name() == "NS_ERROR_DOM_QUOTA_REACHED"
+                || err.code() == web_sys::DomException::QUOTA_EXCEEDED_ERR
+        });
+        // Some private browsing modes give storage with no space at all, which isn't something the user can free
+        let is_empty = storage.length().is_ok_and(|length| length == 0);
+
+        return Err(if is_quota_exceeded && !is_empty {
+            StoreError::QuotaExceeded
+        } else {
+            StoreError::LocalStorageUnavailable
+        });
+    }
+
+    // Other private browsing modes accept writes but don't keep them
+    match storage.get_item(key) {
+        Ok(Some(stored)) if stored == value => Ok(()),
+        _ => Err(StoreError::WriteDiscarded),
+    }
+}
+
+/// Local storage has no transactions, so each value is written in turn, and the previous values are restored if
+/// any write fails.
+#[cfg(target_arch = "wasm32")]
+fn store_batch_web(entries: &[(&str, &str)]) -> Result<(), StoreError> {
+    let previous = entries
+        .iter()
+        .map(|(key, _)| load_web(key))
+        .collect::<Vec<_>>();
+
+    for (i, (key, value)) in entries.iter().enumerate() {
+        if let Err(err) = store_web(key, value) {
+            let storage =
+                web_sys::window().and_then(|window| window.local_storage().ok().flatten());
+            if let Some(storage) = storage {
+                for ((key, _), previous) in entries[..=i].iter().zip(&previous) {
+                    let _ = match previous {
+                        Some(previous) => storage.set_item(key, previous),
+                        None => storage.remove_item(key),
+                    };
+                }
+            }
+            return Err(err);
+        }
+    }
+    Ok(())
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -96,6 +148,50 @@ fn store_native(key: &str, value: &str) -> Result<(), StoreError> {
     return Ok(());
 }
 
+/// Every value is written to a temporary file first, and only once all have been written are they moved into
+/// place, so a failed write leaves every key unchanged, and a crash part way through can't leave a value half
+/// written.
+#[cfg(not(target_arch = "wasm32"))]
+fn store_batch_native(entries: &[(&str, &str)]) -> Result<(), StoreError> {
+    let paths = entries
+        .iter()
+        .map(|(key, _)| native_path(key))
+        .collect::<Vec<_>>();
+    let temp_paths = paths
+        .iter()
+        .map(|path| path.with_extension("lfx.tmp"))
+        .collect::<Vec<_>>();
+
+    let remove_temps = || {
+        for temp_path in &temp_paths {
+            let _ = std::fs::remove_file(temp_path);
+        }
+    };
+
+    for ((key, value), temp_path) in entries.iter().zip(&temp_paths) {
+        if let Err(err) = std::fs::write(temp_path, value) {
+            log::error!("failed to record local value with key {key}: {err}");
+            remove_temps();
+            return Err(StoreError::FileUnwriteable {
+                path: temp_path.clone(),
+                err,
+            });
+        }
+    }
+
+    for ((key, _), (path, temp_path)) in entries.iter().zip(paths.iter().zip(&temp_paths)) {
+        if let Err(err) = std::fs::rename(temp_path, path) {
+            log::error!("failed to record local value with key {key}: {err}");
+            remove_temps();
+            return Err(StoreError::FileUnwriteable {
+                path: path.clone(),
+                err,
+            });
+        }
+    }
+    Ok(())
+}
+
 /// Loads the string associated with a key. These values persist through program
 /// runs.
 pub fn load(key: &str) -> Option<String> {
@@ -115,3 +211,18 @@ pub fn store(key: &str, value: &str) -> Result<(), StoreError> {
     #[cfg(not(target_arch = "wasm32"))]
     return store_native(key, value);
 }
+
+/// Stores several strings, each associated with a key, as with [`store`], for values which must persist together,
+/// such as related settings. If any value can't be written, none are changed, where the platform allows.
+///
+/// On native, each value is written to a temporary file and then moved into place, so a crash part way through
+/// can't leave a value half written. Once every value has been written, moving them into place can only fail if
+/// the files are changed by something else at the same time. On web, browser storage has no transactions, so
+/// each value is written in turn and the previous values are restored if a write fails.
+pub fn store_batch(entries: &[(&str, &str)]) -> Result<(), StoreError> {
+    #[cfg(target_arch = "wasm32")]
+    return store_batch_web(entries);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    return store_batch_native(entries);
+}
@@ -0,0 +1,26 @@
+//! The few bits of arithmetic that this crate's types call for, to avoid needing a full maths crate for them.
+
+/// Linearly interpolates between `a` and `b`, giving `a` when `t` is 0 and `b` when `t` is 1. `t` isn't clamped.
+pub fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Linearly interpolates each channel of two colors, giving `a` when `t` is 0 and `b` when `t` is 1. `t` is
+/// clamped between 0 and 1. Interpolation happens on the values given, so for perceptually even fades between
+/// sRGB colors, interpolate before converting them with [`crate::srgb_color`].
+///
+/// ```
+/// let faded = lf_gfx::math::color_lerp(wgpu::Color::BLACK, wgpu::Color::WHITE, 0.25);
+/// assert_eq!(faded.r, 0.25);
+/// assert_eq!(faded.a, 1.0);
+/// ```
+pub fn color_lerp(a: wgpu::Color, b: wgpu::Color, t: f64) -> wgpu::Color {
+    let t = t.clamp(0.0, 1.0);
+    let lerp = |a: f64, b: f64| a + (b - a) * t;
+    wgpu::Color {
+        r: lerp(a.r, b.r),
+        g: lerp(a.g, b.g),
+        b: lerp(a.b, b.b),
+        a: lerp(a.a, b.a),
+    }
+}
@@ -0,0 +1,144 @@
+//! Chains of fullscreen passes, each reading the output of the previous one.
+
+use crate::{
+    FragmentOnlyColorAttachment, FragmentOnlyRenderPassDescriptor, FragmentOnlyRenderPipeline,
+    LfCommandEncoderExt, WindowSizeDependent,
+};
+
+/// Runs a sequence of [`FragmentOnlyRenderPipeline`]s, feeding the output of each into the next, by ping-ponging
+/// between two intermediate textures sized to the surface.
+///
+/// Every pipeline in the chain must take its input at bind group 0, as a filterable 2D float texture at binding 0
+/// and a filtering sampler at binding 1, and must render to a single color target. Every pass but the last renders
+/// to an intermediate texture of the chain's [`PostProcessChain::format`], and the last renders to the `output`
+/// given to [`PostProcessChain::run`], so must match its format instead.
+pub struct PostProcessChain {
+    format: wgpu::TextureFormat,
+    size: winit::dpi::PhysicalSize<u32>,
+    sampler: wgpu::Sampler,
+    intermediates: [wgpu::TextureView; 2],
+}
+
+impl PostProcessChain {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("post process sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            format,
+            size,
+            sampler,
+            intermediates: Self::create_intermediates(device, format, size),
+        }
+    }
+
+    fn create_intermediates(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> [wgpu::TextureView; 2] {
+        ["post process intermediate 0", "post process intermediate 1"].map(|label| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: size.width.max(1),
+                        height: size.height.max(1),
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        })
+    }
+
+    /// The format of the intermediate textures, which every pass but the last must render to.
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    /// The size of the intermediate textures.
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    /// Records each pass in turn. The first pass reads from `input`, the last writes to `output`, and those in
+    /// between read from and write to the intermediate textures. Does nothing if `passes` is empty.
+    ///
+    /// `input` must not be `output`, since a texture can't be sampled in the same pass that renders to it.
+    pub fn run(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        passes: &[&FragmentOnlyRenderPipeline],
+        input: &wgpu::TextureView,
+        output: &wgpu::TextureView,
+    ) {
+        let last = passes.len().saturating_sub(1);
+        for (i, pass) in passes.iter().enumerate() {
+            let source = if i == 0 {
+                input
+            } else {
+                &self.intermediates[(i - 1) % 2]
+            };
+            let target = if i == last {
+                output
+            } else {
+                &self.intermediates[i % 2]
+            };
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("post process input"),
+                layout: &pass.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(source),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+            });
+
+            let mut render_pass =
+                encoder.begin_fragment_only_render_pass(&FragmentOnlyRenderPassDescriptor {
+                    label: Some("post process pass"),
+                    color_attachments: &[Some(FragmentOnlyColorAttachment::clear(
+                        target,
+                        wgpu::Color::BLACK,
+                    ))],
+                    stencil_attachment: None,
+                    timestamp_writes: None,
+                });
+            render_pass.set_pipeline(pass);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw();
+        }
+    }
+}
+
+impl WindowSizeDependent for PostProcessChain {
+    fn on_window_resize(&mut self, device: &wgpu::Device, new_size: winit::dpi::PhysicalSize<u32>) {
+        if new_size == self.size {
+            return;
+        }
+        self.size = new_size;
+        self.intermediates = Self::create_intermediates(device, self.format, new_size);
+    }
+}
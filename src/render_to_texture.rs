@@ -0,0 +1,157 @@
+//! Offscreen render targets sized relative to the surface, for secondary views sampled in the main pass.
+
+use crate::WindowSizeDependent;
+
+/// An offscreen color target, with an optional depth target, sized to a fraction of the surface and recreated
+/// when the surface resizes. Useful for secondary views such as security cameras, portals and minimaps, which are
+/// rendered first and then sampled through [`RenderToTexture::view`] in the main pass.
+///
+/// Since the texture is recreated on resize, bind groups that sample it must be recreated after
+/// [`WindowSizeDependent::on_window_resize`] is called.
+pub struct RenderToTexture {
+    format: wgpu::TextureFormat,
+    depth_format: Option<wgpu::TextureFormat>,
+    scale: f32,
+    size: winit::dpi::PhysicalSize<u32>,
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    depth_view: Option<wgpu::TextureView>,
+}
+
+impl RenderToTexture {
+    /// Creates a target with the given format, with each dimension `scale` times that of the surface.
+    /// If `depth_format` is given, a depth target of the same size is also created and cleared with each pass.
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+        scale: f32,
+        surface_size: winit::dpi::PhysicalSize<u32>,
+    ) -> Self {
+        let size = Self::scaled_size(surface_size, scale);
+        let texture = Self::create_texture(
+            device,
+            "render to texture color",
+            format,
+            size,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let depth_view = depth_format.map(|depth_format| {
+            Self::create_texture(
+                device,
+                "render to texture depth",
+                depth_format,
+                size,
+                wgpu::TextureUsages::RENDER_ATTACHMENT,
+            )
+            .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+
+        Self {
+            format,
+            depth_format,
+            scale,
+            size,
+            texture,
+            view,
+            depth_view,
+        }
+    }
+
+    fn scaled_size(
+        surface_size: winit::dpi::PhysicalSize<u32>,
+        scale: f32,
+    ) -> winit::dpi::PhysicalSize<u32> {
+        winit::dpi::PhysicalSize::new(
+            ((surface_size.width as f32 * scale) as u32).max(1),
+            ((surface_size.height as f32 * scale) as u32).max(1),
+        )
+    }
+
+    fn create_texture(
+        device: &wgpu::Device,
+        label: &str,
+        format: wgpu::TextureFormat,
+        size: winit::dpi::PhysicalSize<u32>,
+        usage: wgpu::TextureUsages,
+    ) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        })
+    }
+
+    /// Begins a pass rendering to this target, clearing the color to transparent black and the depth, if any, to 1.
+    pub fn begin_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("render to texture pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: self.depth_view.as_ref().map(|view| {
+                wgpu::RenderPassDepthStencilAttachment {
+                    view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+
+    /// The color target, for sampling in later passes.
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    pub fn depth_format(&self) -> Option<wgpu::TextureFormat> {
+        self.depth_format
+    }
+
+    /// The fraction of the surface size that this target is sized to.
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// The current size of the target, in pixels.
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+}
+
+impl WindowSizeDependent for RenderToTexture {
+    fn on_window_resize(&mut self, device: &wgpu::Device, new_size: winit::dpi::PhysicalSize<u32>) {
+        if Self::scaled_size(new_size, self.scale) == self.size {
+            return;
+        }
+        *self = Self::new(device, self.format, self.depth_format, self.scale, new_size);
+    }
+}
@@ -0,0 +1,13 @@
+@group(0) @binding(0)
+var source : texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler : sampler;
+
+@fragment
+fn main(
+  @location(0) frag_uv : vec2<f32>
+) -> @location(0) vec4<f32> {
+  // From clip space, with y up, to texture coordinates, with y down
+  let uv = vec2<f32>(frag_uv.x * 0.5 + 0.5, 0.5 - frag_uv.y * 0.5);
+  return textureSample(source, source_sampler, uv);
+}
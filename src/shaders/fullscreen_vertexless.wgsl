@@ -0,0 +1,22 @@
+struct VertexOutput {
+  @builtin(position) position : vec4<f32>,
+  @location(0) frag_uv : vec2<f32>,
+}
+
+@vertex
+fn main(
+  @builtin(vertex_index) vertex_index : u32
+) -> VertexOutput {
+  // The same triangle as given by the vertex buffer in `fullscreen.wgsl`
+  var positions = array<vec2<f32>, 3>(
+    vec2<f32>(-2.0, -1.0),
+    vec2<f32>(2.0, -1.0),
+    vec2<f32>(0.0, 3.0),
+  );
+  let position = positions[vertex_index];
+
+  var output : VertexOutput;
+  output.position = vec4<f32>(position, 0.5, 1.0);
+  output.frag_uv = position;
+  return output;
+}
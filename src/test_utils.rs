@@ -0,0 +1,508 @@
+//! Helpers for testing GPU code, such as running a compute shader over some input and reading back the output, or
+//! rendering a fragment-only pipeline and comparing the result against a golden image. Enabled with the
+//! `test-utils` feature.
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    FragmentOnlyColorAttachment, FragmentOnlyRenderPassDescriptor, FragmentOnlyRenderPipeline,
+    LfBufferExt, LfCommandEncoderExt,
+};
+
+/// Creates a device on the most powerful adapter available, without a window, for tests to run GPU code on. Gives
+/// `None` if there are no adapters, such as on CI machines without a GPU or software renderer.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn headless_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::all()),
+        ..Default::default()
+    });
+    pollster::block_on(async {
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                force_fallback_adapter: false,
+                compatible_surface: None,
+            })
+            .await?;
+        adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("headless test device"),
+                    required_features: wgpu::Features::empty(),
+                    required_limits: adapter.limits(),
+                },
+                None,
+            )
+            .await
+            .ok()
+    })
+}
+
+/// Everything needed to run a single compute shader dispatch for a test.
+pub struct ComputeTestDescriptor<'a> {
+    pub label: wgpu::Label<'a>,
+    pub shader: &'a wgpu::ShaderModule,
+    pub entry_point: &'a str,
+    /// The bindings for bind group 0, which must be the only bind group used by the shader.
+    pub bind_group_entries: &'a [wgpu::BindGroupEntry<'a>],
+    /// The number of workgroups dispatched in each dimension.
+    pub workgroups: [u32; 3],
+    /// The buffer whose contents are given back once the dispatch has finished. Must have been created with
+    /// `wgpu::BufferUsages::COPY_SRC`.
+    pub output: &'a wgpu::Buffer,
+}
+
+/// Runs compute shaders over test data and reads back the results, blocking until the GPU has finished.
+///
+/// ```no_run
+/// # fn test(device: &wgpu::Device, queue: &wgpu::Queue) {
+/// use lf_gfx::test_utils::{ComputeTestDescriptor, ComputeTestHarness};
+///
+/// let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+///     label: None,
+///     source: wgpu::ShaderSource::Wgsl(
+///         "@group(0) @binding(0) var<storage, read_write> data: array<u32>;
+///
+///         @compute @workgroup_size(4)
+///         fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+///             data[id.x] *= 2u;
+///         }"
+///         .into(),
+///     ),
+/// });
+///
+/// let harness = ComputeTestHarness::new(device, queue);
+/// let data = harness.storage_buffer(bytemuck::cast_slice(&[1u32, 2, 3, 4]));
+/// let output = harness.run(&ComputeTestDescriptor {
+///     label: None,
+///     shader: &shader,
+///     entry_point: "main",
+///     bind_group_entries: &[wgpu::BindGroupEntry {
+///         binding: 0,
+///         resource: data.as_entire_binding(),
+///     }],
+///     workgroups: [1, 1, 1],
+///     output: &data,
+/// });
+///
+/// assert_eq!(bytemuck::cast_slice::<u8, u32>(&output), &[2, 4, 6, 8]);
+/// # }
+/// ```
+pub struct ComputeTestHarness<'a> {
+    device: &'a wgpu::Device,
+    queue: &'a wgpu::Queue,
+}
+
+impl<'a> ComputeTestHarness<'a> {
+    pub fn new(device: &'a wgpu::Device, queue: &'a wgpu::Queue) -> Self {
+        Self { device, queue }
+    }
+
+    /// Creates a storage buffer holding the given data, which can be both bound as input and read back as output.
+    pub fn storage_buffer(&self, contents: &[u8]) -> wgpu::Buffer {
+        self.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("compute test storage buffer"),
+                contents,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST,
+            })
+    }
+
+    /// Creates a zeroed storage buffer of the given size, to be written to by a shader and read back.
+    pub fn output_buffer(&self, size: wgpu::BufferAddress) -> wgpu::Buffer {
+        self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("compute test output buffer"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Dispatches the shader once with the given bindings, then blocks and reads back the output buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the shader or bindings fail validation, or if the output buffer can't be read.
+    pub fn run(&self, desc: &ComputeTestDescriptor) -> Vec<u8> {
+        let pipeline = self
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: desc.label,
+                layout: None,
+                module: desc.shader,
+                entry_point: desc.entry_point,
+            });
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: desc.label,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: desc.bind_group_entries,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: desc.label });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: desc.label,
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            let [x, y, z] = desc.workgroups;
+            pass.dispatch_workgroups(x, y, z);
+        }
+        self.queue.submit([encoder.finish()]);
+
+        desc.output.debug_read_blocking(self.device, self.queue)
+    }
+}
+
+/// The format that [`FragmentTestHarness`] renders to, which pipelines under test must target.
+pub const FRAGMENT_TEST_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Renders single frames of [`FragmentOnlyRenderPipeline`]s and reads back the results, blocking until the GPU
+/// has finished, for comparing against golden images with [`assert_image_matches`].
+///
+/// ```no_run
+/// # fn test(pipeline: &lf_gfx::FragmentOnlyRenderPipeline) {
+/// use lf_gfx::test_utils::{assert_image_matches, headless_device, FragmentTestHarness};
+///
+/// let (device, queue) = headless_device().expect("tests need a GPU");
+/// let harness = FragmentTestHarness::new(&device, &queue);
+///
+/// let input = harness.input_texture(2, 1, &[255, 0, 0, 255, 0, 255, 0, 255]);
+/// let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+/// let output = harness.render(
+///     pipeline,
+///     &[wgpu::BindGroupEntry {
+///         binding: 0,
+///         resource: wgpu::BindingResource::TextureView(&input_view),
+///     }],
+///     64,
+///     64,
+/// );
+///
+/// assert_image_matches(&output, 64, 64, "tests/golden/blur.png", 2);
+/// # }
+/// ```
+pub struct FragmentTestHarness<'a> {
+    device: &'a wgpu::Device,
+    queue: &'a wgpu::Queue,
+}
+
+impl<'a> FragmentTestHarness<'a> {
+    pub fn new(device: &'a wgpu::Device, queue: &'a wgpu::Queue) -> Self {
+        Self { device, queue }
+    }
+
+    /// Creates a texture holding tightly packed RGBA pixels, in rows from top to bottom, to bind as input.
+    pub fn input_texture(&self, width: u32, height: u32, rgba: &[u8]) -> wgpu::Texture {
+        self.device.create_texture_with_data(
+            self.queue,
+            &wgpu::TextureDescriptor {
+                label: Some("fragment test input"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: FRAGMENT_TEST_FORMAT,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            rgba,
+        )
+    }
+
+    /// Draws the pipeline once over an output of the given size, with the given bindings for bind group 0, then
+    /// blocks and reads back the output as tightly packed RGBA pixels, in rows from top to bottom. The pipeline
+    /// must render to a single target of format [`FRAGMENT_TEST_FORMAT`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pipeline or bindings fail validation, or if the output can't be read.
+    pub fn render(
+        &self,
+        pipeline: &FragmentOnlyRenderPipeline,
+        bind_group_entries: &[wgpu::BindGroupEntry],
+        width: u32,
+        height: u32,
+    ) -> Vec<u8> {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let output = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("fragment test output"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FRAGMENT_TEST_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = (!bind_group_entries.is_empty()).then(|| {
+            self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("fragment test bindings"),
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: bind_group_entries,
+            })
+        });
+
+        // Rows copied out of textures must be aligned
+        let row_len = width * 4;
+        let padded_row_len = crate::next_multiple_of(
+            row_len as wgpu::BufferAddress,
+            wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as wgpu::BufferAddress,
+        );
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("fragment test readback"),
+            size: padded_row_len * height as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("fragment test"),
+            });
+        {
+            let mut pass =
+                encoder.begin_fragment_only_render_pass(&FragmentOnlyRenderPassDescriptor {
+                    label: Some("fragment test"),
+                    color_attachments: &[Some(FragmentOnlyColorAttachment::clear(
+                        &output_view,
+                        wgpu::Color::TRANSPARENT,
+                    ))],
+                    stencil_attachment: None,
+                    timestamp_writes: None,
+                });
+            pass.set_pipeline(pipeline);
+            if let Some(bind_group) = &bind_group {
+                pass.set_bind_group(0, bind_group, &[]);
+            }
+            pass.draw();
+        }
+        encoder.copy_texture_to_buffer(
+            output.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_len as u32),
+                    rows_per_image: Some(height),
+                },
+            },
+            size,
+        );
+        self.queue.submit([encoder.finish()]);
+
+        let padded = readback.debug_read_blocking(self.device, self.queue);
+        padded
+            .chunks_exact(padded_row_len as usize)
+            .flat_map(|row| &row[..row_len as usize])
+            .copied()
+            .collect()
+    }
+}
+
+/// Set to write the actual output of every [`assert_image_matches`] to its golden image, after a deliberate change
+/// to what is rendered.
+#[cfg(not(target_arch = "wasm32"))]
+pub const UPDATE_GOLDEN_IMAGES_VAR: &str = "LF_GFX_UPDATE_GOLDEN_IMAGES";
+
+/// Asserts that tightly packed RGBA pixels, such as those given by [`FragmentTestHarness::render`], match a golden
+/// PNG image, allowing each channel of each pixel to differ by up to `tolerance` to account for differences between
+/// GPUs.
+///
+/// If the golden image doesn't exist, or [`UPDATE_GOLDEN_IMAGES_VAR`] is set, the actual pixels are written to it
+/// instead. When it doesn't exist, the assertion still fails, so that the new image is checked before it is
+/// trusted. When the images differ, the actual pixels are written alongside the golden image with the extension
+/// `actual.png`, for comparison.
+///
+/// # Panics
+///
+/// Panics if the images differ, or if the golden image can't be read or written.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn assert_image_matches(
+    actual: &[u8],
+    width: u32,
+    height: u32,
+    expected_png_path: impl AsRef<std::path::Path>,
+    tolerance: u8,
+) {
+    let path = expected_png_path.as_ref();
+    assert_eq!(
+        actual.len(),
+        (width * height * 4) as usize,
+        "actual pixels should be tightly packed RGBA of the given size"
+    );
+
+    if std::env::var_os(UPDATE_GOLDEN_IMAGES_VAR).is_some() {
+        write_png(path, actual, width, height);
+        return;
+    }
+    if !path.exists() {
+        write_png(path, actual, width, height);
+        panic!(
+            "golden image {} didn't exist, so the actual output was written to it; check it and run again",
+            path.display()
+        );
+    }
+
+    let (expected, expected_width, expected_height) = read_png(path);
+    let matches = if (expected_width, expected_height) != (width, height) {
+        Err(format!(
+            "actual size {width}x{height} differs from golden size {expected_width}x{expected_height}"
+        ))
+    } else {
+        let differences = actual
+            .iter()
+            .zip(&expected)
+            .map(|(a, e)| a.abs_diff(*e))
+            .collect::<Vec<_>>();
+        let differing_pixels = differences
+            .chunks_exact(4)
+            .filter(|pixel| pixel.iter().any(|d| *d > tolerance))
+            .count();
+        let max_difference = differences.into_iter().max().unwrap_or(0);
+        if differing_pixels == 0 {
+            Ok(())
+        } else {
+            Err(format!(
+                "{differing_pixels} pixels differ by more than {tolerance}, by up to {max_difference}"
+            ))
+        }
+    };
+
+    if let Err(message) = matches {
+        let actual_path = path.with_extension("actual.png");
+        write_png(&actual_path, actual, width, height);
+        panic!(
+            "image doesn't match golden image {}: {message}. The actual output was written to {}",
+            path.display(),
+            actual_path.display()
+        );
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_png(path: &std::path::Path, rgba: &[u8], width: u32, height: u32) {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).expect("golden image directory should be creatable");
+    }
+    let file = std::fs::File::create(path).expect("golden image should be writeable");
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(rgba))
+        .expect("golden image should be encodable");
+}
+
+/// Reads a PNG as 8-bit RGBA, giving the pixels, width and height.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_png(path: &std::path::Path) -> (Vec<u8>, u32, u32) {
+    let file = std::fs::File::open(path).expect("golden image should be readable");
+    let mut decoder = png::Decoder::new(std::io::BufReader::new(file));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder
+        .read_info()
+        .expect("golden image should be a valid PNG");
+    let mut pixels = vec![
+        0;
+        reader
+            .output_buffer_size()
+            .expect("golden image fits in memory")
+    ];
+    let info = reader
+        .next_frame(&mut pixels)
+        .expect("golden image should be a valid PNG");
+    pixels.truncate(info.buffer_size());
+
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => pixels,
+        png::ColorType::Rgb => pixels
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks_exact(2)
+            .flat_map(|la| [la[0], la[0], la[0], la[1]])
+            .collect(),
+        png::ColorType::Grayscale => pixels.iter().flat_map(|l| [*l, *l, *l, 255]).collect(),
+        png::ColorType::Indexed => unreachable!("palettes are expanded when decoding"),
+    };
+    (rgba, info.width, info.height)
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    // A directory of its own for each test, as tests run in parallel
+    fn golden_dir(test: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("lf-gfx-{}-{test}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    const GOLDEN: [u8; 8] = [10, 20, 30, 255, 200, 100, 50, 255];
+
+    #[test]
+    fn images_within_tolerance_match() {
+        let dir = golden_dir("match");
+        let golden = dir.join("golden.png");
+        write_png(&golden, &GOLDEN, 2, 1);
+
+        assert_image_matches(&GOLDEN, 2, 1, &golden, 0);
+        assert_image_matches(&[12, 18, 30, 255, 200, 100, 52, 255], 2, 1, &golden, 2);
+        assert!(!golden.with_extension("actual.png").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn differing_images_fail_and_write_the_actual_image() {
+        let dir = golden_dir("mismatch");
+        let golden = dir.join("golden.png");
+        write_png(&golden, &GOLDEN, 2, 1);
+
+        let actual = [10, 20, 30, 255, 200, 100, 60, 255];
+        let result = std::panic::catch_unwind(|| assert_image_matches(&actual, 2, 1, &golden, 2));
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(
+            message.contains("1 pixels differ by more than 2, by up to 10"),
+            "{message}"
+        );
+
+        let size_result =
+            std::panic::catch_unwind(|| assert_image_matches(&actual, 1, 2, &golden, 2));
+        let message = *size_result.unwrap_err().downcast::<String>().unwrap();
+        assert!(
+            message.contains("differs from golden size 2x1"),
+            "{message}"
+        );
+
+        assert_eq!(
+            read_png(&golden.with_extension("actual.png")),
+            (actual.to_vec(), 1, 2)
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
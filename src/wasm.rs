@@ -1,4 +1,7 @@
-use wasm_bindgen::JsCast;
+use std::cell::RefCell;
+
+use wasm_bindgen::{closure::Closure, JsCast};
+use winit::window::UserAttentionType;
 
 pub(crate) fn get_canvas() -> web_sys::HtmlCanvasElement {
     let window = web_sys::window().unwrap();
@@ -9,3 +12,252 @@ pub(crate) fn get_canvas() -> web_sys::HtmlCanvasElement {
         .dyn_into::<web_sys::HtmlCanvasElement>()
         .unwrap()
 }
+
+/// Gives a value from a page event listener to the event loop, and wakes it to handle the value. Sending only
+/// fails once the game has exited and there is nothing left to handle it, so failures are ignored.
+pub(crate) fn send_to_loop<T>(
+    sender: &flume::Sender<T>,
+    waker: &winit::event_loop::EventLoopProxy<()>,
+    value: T,
+) {
+    if sender.send(value).is_ok() {
+        let _ = waker.send_event(());
+    }
+}
+
+/// The physical size of the screen that the page is displayed on.
+pub(crate) fn screen_size() -> Option<winit::dpi::PhysicalSize<u32>> {
+    let window = web_sys::window()?;
+    let screen = window.screen().ok()?;
+    let scale_factor = window.device_pixel_ratio();
+    let size = winit::dpi::LogicalSize::new(screen.width().ok()?, screen.height().ok()?);
+    Some(size.to_physical(scale_factor))
+}
+
+/// Put before the page title while the user's attention is requested.
+const ATTENTION_MARKER: &str = "\u{2022} ";
+
+/// How often the page title is checked, and flashed for critical requests, while attention is requested.
+const ATTENTION_INTERVAL_MS: i32 = 1000;
+
+/// A request for the user's attention, shown in the page title until the page is focused.
+struct Attention {
+    interval: i32,
+    title: String,
+    flash: bool,
+    marked: bool,
+}
+
+thread_local! {
+    // Undoes everything added to the page, run in reverse by `shutdown`
+    static CLEANUP: RefCell<Vec<Box<dyn FnOnce()>>> = const { RefCell::new(Vec::new()) };
+    static ATTENTION: RefCell<Option<Attention>> = const { RefCell::new(None) };
+    // Created once and never dropped, so that it can't be dropped while being called
+    static ATTENTION_TICK: Closure<dyn FnMut()> = Closure::new(attention_tick);
+}
+
+fn document() -> Option<web_sys::Document> {
+    web_sys::window()?.document()
+}
+
+/// Registers something to undo when the game finishes, such as removing an element added to the page.
+pub(crate) fn on_shutdown(cleanup: impl FnOnce() + 'static) {
+    CLEANUP.with_borrow_mut(|cleanups| cleanups.push(Box::new(cleanup)));
+}
+
+/// Adds an event listener, which is removed and dropped when the game finishes.
+pub(crate) fn add_listener<T: ?Sized + wasm_bindgen::closure::WasmClosure + 'static>(
+    target: &web_sys::EventTarget,
+    name: &'static str,
+    listener: Closure<T>,
+) {
+    if let Err(err) =
+        target.add_event_listener_with_callback(name, listener.as_ref().unchecked_ref())
+    {
+        log::error!("failed to listen for {name} events: {err:?}");
+        return;
+    }
+    let target = target.clone();
+    on_shutdown(move || {
+        let _ = target.remove_event_listener_with_callback(name, listener.as_ref().unchecked_ref());
+    });
+}
+
+/// Removes the listeners, observers, timers and elements that have been added to the page, and restores the page
+/// title, so that the game can be started again in the same page without them building up. The canvas belongs to
+/// the page, so is left in place.
+pub(crate) fn shutdown() {
+    let cleanups = CLEANUP.with_borrow_mut(std::mem::take);
+    for cleanup in cleanups.into_iter().rev() {
+        cleanup();
+    }
+
+    stop_attention();
+    if let Some(document) = document() {
+        while let Some(alert) = document.get_element_by_id(crate::pretty_alert::ALERT_ID) {
+            alert.remove();
+        }
+    }
+}
+
+fn attention_tick() {
+    let Some(document) = document() else {
+        return;
+    };
+    if document.has_focus().unwrap_or(false) {
+        stop_attention();
+        return;
+    }
+
+    ATTENTION.with_borrow_mut(|attention| {
+        let Some(attention) = attention.as_mut().filter(|attention| attention.flash) else {
+            return;
+        };
+        attention.marked = !attention.marked;
+        if attention.marked {
+            document.set_title(&format!("{ATTENTION_MARKER}{}", attention.title));
+        } else {
+            document.set_title(&attention.title);
+        }
+    });
+}
+
+fn stop_attention() {
+    let Some(attention) = ATTENTION.take() else {
+        return;
+    };
+    if let Some(window) = web_sys::window() {
+        window.clear_interval_with_handle(attention.interval);
+    }
+    if let Some(document) = document() {
+        document.set_title(&attention.title);
+    }
+}
+
+/// Browsers don't allow pages to flash the taskbar, so attention is requested by marking the page title until the
+/// page is focused. Critical requests flash the mark on and off.
+pub(crate) fn request_attention(kind: Option<UserAttentionType>) {
+    stop_attention();
+    let Some(kind) = kind else {
+        return;
+    };
+    let (Some(window), Some(document)) = (web_sys::window(), document()) else {
+        return;
+    };
+    if document.has_focus().unwrap_or(false) {
+        return;
+    }
+
+    let title = document.title();
+    document.set_title(&format!("{ATTENTION_MARKER}{title}"));
+    let interval = ATTENTION_TICK.with(|tick| {
+        window.set_interval_with_callback_and_timeout_and_arguments_0(
+            tick.as_ref().unchecked_ref(),
+            ATTENTION_INTERVAL_MS,
+        )
+    });
+    let interval = match interval {
+        Ok(interval) => interval,
+        Err(err) => {
+            log::error!("failed to watch for the page being focused: {err:?}");
+            document.set_title(&title);
+            return;
+        }
+    };
+
+    ATTENTION.set(Some(Attention {
+        interval,
+        title,
+        flash: kind == UserAttentionType::Critical,
+        marked: true,
+    }));
+}
+
+/// How long the canvas must keep its size before a resize is given, so that a rotation or a CSS transition
+/// doesn't reconfigure the surface on every step.
+const CANVAS_RESIZE_DEBOUNCE_MS: i32 = 100;
+
+/// Watches the canvas for changes to its displayed size, from CSS layout or from the device being rotated, which
+/// winit doesn't always report. Each change is given through the returned receiver, in physical pixels, once the
+/// size has settled, waking the event loop.
+pub(crate) fn observe_canvas_size(
+    canvas: web_sys::HtmlCanvasElement,
+    waker: winit::event_loop::EventLoopProxy<()>,
+) -> flume::Receiver<winit::dpi::PhysicalSize<u32>> {
+    let (sender, receiver) = flume::unbounded();
+    let Some(window) = web_sys::window() else {
+        return receiver;
+    };
+
+    let report = {
+        let canvas = canvas.clone();
+        Closure::<dyn FnMut()>::new(move || {
+            let Some(window) = web_sys::window() else {
+                return;
+            };
+            let size = winit::dpi::LogicalSize::new(
+                canvas.client_width().max(0) as f64,
+                canvas.client_height().max(0) as f64,
+            )
+            .to_physical(window.device_pixel_ratio());
+            send_to_loop(&sender, &waker, size);
+        })
+    };
+
+    // Every change restarts the wait for the size to settle
+    let pending = std::rc::Rc::new(std::cell::Cell::new(None));
+    let pending_on_shutdown = std::rc::Rc::clone(&pending);
+    let changed = Closure::<dyn FnMut()>::new(move || {
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        if let Some(timeout) = pending.take() {
+            window.clear_timeout_with_handle(timeout);
+        }
+        match window.set_timeout_with_callback_and_timeout_and_arguments_0(
+            report.as_ref().unchecked_ref(),
+            CANVAS_RESIZE_DEBOUNCE_MS,
+        ) {
+            Ok(timeout) => pending.set(Some(timeout)),
+            Err(err) => log::error!("failed to wait for the canvas size to settle: {err:?}"),
+        }
+    });
+
+    let observer = observe_resizes(&window, &canvas, &changed);
+    add_listener(&window, "orientationchange", changed);
+    // Cleanups run in reverse, so the observer and any waiting report are stopped before `changed` is dropped
+    match observer {
+        Ok(observer) => on_shutdown(move || {
+            let disconnect = js_sys::Reflect::get(&observer, &"disconnect".into())
+                .and_then(|disconnect| disconnect.dyn_into::<js_sys::Function>());
+            if let Ok(disconnect) = disconnect {
+                let _ = disconnect.call0(&observer);
+            }
+        }),
+        Err(err) => log::error!("failed to observe canvas resizes: {err:?}"),
+    }
+    on_shutdown(move || {
+        if let (Some(timeout), Some(window)) = (pending_on_shutdown.take(), web_sys::window()) {
+            window.clear_timeout_with_handle(timeout);
+        }
+    });
+
+    receiver
+}
+
+/// `ResizeObserver` is behind `web_sys_unstable_apis`, so it is created dynamically. Gives the observer, for
+/// disconnecting it.
+fn observe_resizes(
+    window: &web_sys::Window,
+    canvas: &web_sys::HtmlCanvasElement,
+    callback: &Closure<dyn FnMut()>,
+) -> Result<wasm_bindgen::JsValue, wasm_bindgen::JsValue> {
+    let constructor: js_sys::Function =
+        js_sys::Reflect::get(window, &"ResizeObserver".into())?.dyn_into()?;
+    let observer =
+        js_sys::Reflect::construct(&constructor, &js_sys::Array::of1(callback.as_ref()))?;
+    let observe: js_sys::Function =
+        js_sys::Reflect::get(&observer, &"observe".into())?.dyn_into()?;
+    observe.call1(&observer, canvas)?;
+    Ok(observer)
+}